@@ -1,9 +1,12 @@
 #!/bin/sh
 
+# everything but the Python, WebAssembly and C bindings, python would link libpython
+FEATURES=alog-cli,gzip,zstd,bzip2,xz,s3,gcs,azure,journald,tokio,serde
+
 if [[ "x$1" == "x--static" ]]; then
-    RUSTFLAGS='-C target-feature=+crt-static' cargo build --release --all-features
+    RUSTFLAGS='-C target-feature=+crt-static' cargo build --release --features $FEATURES
 else
-    cargo build --release --all-features
+    cargo build --release --features $FEATURES
 fi
 
 strip --strip-unneeded -R .comment target/release/alog
@@ -0,0 +1,114 @@
+#ifndef ALOG_H
+#define ALOG_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ Returned on success by all functions returning `int`
+
+ On failure one of the other `ALOG_*` constants is returned (the exit status of the commandline
+ tool for the same error), see [`alog_last_error`] for the message.
+ */
+#define ALOG_OK 0
+
+/*
+ Reading input or writing output failed
+ */
+#define ALOG_ERROR_IO 1
+
+/*
+ Invalid config or arguments, e.g. a null pointer
+ */
+#define ALOG_ERROR_USAGE 2
+
+/*
+ Opaque [`Config`] owned by the caller
+ */
+typedef struct alog_config alog_config;
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Returns a new config with default values, free it with [`alog_config_free`]
+ */
+struct alog_config *alog_config_new(void);
+
+/*
+ Returns a new config read from a TOML document, see [`Config::from_toml`]
+
+ Returns null if `toml` is not valid, see [`alog_last_error`]. Free the config with
+ [`alog_config_free`].
+
+ # Safety
+
+ `toml` must be null or a valid NUL terminated string.
+ */
+struct alog_config *alog_config_from_toml(const char *toml);
+
+/*
+ Frees a config, null is ignored
+
+ # Safety
+
+ `config` must be null or returned by [`alog_config_new`] / [`alog_config_from_toml`] and
+ not freed before.
+ */
+void alog_config_free(struct alog_config *config);
+
+/*
+ Anonymizes `input_len` bytes at `input` into a new buffer
+
+ On success `*output` and `*output_len` are set to the anonymized lines, free them with
+ [`alog_buffer_free`]. A null `config` uses the default config.
+
+ # Safety
+
+ `config` must be null or a valid config, `input` must point to `input_len` readable bytes
+ (or be null if `input_len` is 0) and `output` / `output_len` must be valid for writes.
+ */
+int alog_anonymize_buffer(const struct alog_config *config,
+                          const uint8_t *input,
+                          size_t input_len,
+                          uint8_t **output,
+                          size_t *output_len);
+
+/*
+ Frees a buffer returned by [`alog_anonymize_buffer`], null is ignored
+
+ # Safety
+
+ `buffer` and `len` must be returned by [`alog_anonymize_buffer`] and not freed before.
+ */
+void alog_buffer_free(uint8_t *buffer, size_t len);
+
+/*
+ Anonymizes everything read from `input_fd` into `output_fd` until end of input
+
+ Both file descriptors are left open. A null `config` uses the default config.
+
+ # Safety
+
+ `config` must be null or a valid config, `input_fd` and `output_fd` must be open file
+ descriptors not used elsewhere until this function returns.
+ */
+int alog_run_fd(const struct alog_config *config, int input_fd, int output_fd);
+
+/*
+ Returns the message of the last error on the calling thread, null if there was none
+
+ The string is valid until the next failing `alog_*` call on the same thread.
+ */
+const char *alog_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ALOG_H */
@@ -0,0 +1,118 @@
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{oversized, replace_record, Config, Error, Malformed, Oversized};
+
+/// Anonymized lines are buffered until at least this many bytes can be written at once
+const WRITE_SIZE: usize = 64 << 10;
+
+/// Async version of [`run_raw`](crate::run_raw), reads lines from `reader` and writes the
+/// anonymized lines to `writer`
+///
+/// `writer` is flushed but not shut down, so it can still be used afterwards.
+///
+/// ## Example
+///
+/// ```
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut buffer = vec![];
+///
+/// alog::run_raw_async(&alog::Config::default(), &b"8.8.8.8 XxX"[..], &mut buffer)
+///     .await
+///     .unwrap();
+/// assert_eq!(buffer, b"127.0.0.1 XxX");
+/// # });
+/// ```
+///
+/// ## Errors
+///
+/// This function will return an I/O error if the underlying reader or writer returns an error.
+pub async fn run_raw_async<R, W>(config: &Config<'_>, reader: R, mut writer: W) -> Result<(), Error>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    replace_remote_address(config, reader, &mut writer).await?;
+    Ok(())
+}
+
+/// Async version of [`run`](crate::run) for a single stream, anonymizes `reader` to `writer`
+/// and shuts `writer` down at the end of input, e.g. to close a `TcpStream`
+///
+/// ## Errors
+///
+/// This function will return an I/O error if the underlying reader or writer returns an error.
+pub async fn run_async<R, W>(config: &Config<'_>, reader: R, mut writer: W) -> Result<(), Error>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    replace_remote_address(config, reader, &mut writer).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// See [`crate::replace_remote_address`], [`Config::jobs`] is ignored
+async fn replace_remote_address<R, W>(
+    config: &Config<'_>,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![];
+    let mut scratch = vec![];
+    let mut out = Vec::with_capacity(WRITE_SIZE);
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let max = config
+        .get_max_line_len()
+        .map_or(u64::MAX, |m| m.max(1) as u64);
+    let mut line_no = 0;
+
+    loop {
+        buf.clear();
+        let bytes_read = (&mut reader)
+            .take(max)
+            .read_until(delimiter, &mut buf)
+            .await?;
+        if bytes_read == 0 {
+            break;
+        }
+        line_no += 1;
+
+        if bytes_read as u64 == max && buf.last() != Some(&delimiter) {
+            oversized(config, &buf, &mut out)?;
+            // copy or discard the remainder of the line, at most `max` bytes at a time
+            loop {
+                buf.clear();
+                let n = (&mut reader)
+                    .take(max)
+                    .read_until(delimiter, &mut buf)
+                    .await?;
+                if config.get_oversized() == Oversized::Pass {
+                    out.extend_from_slice(&buf);
+                }
+                if n == 0 || buf.last() == Some(&delimiter) {
+                    break;
+                }
+            }
+        } else {
+            replace_record(config, &buf, &mut scratch, &mut out)
+                .map_err(|e| Malformed::shift(e, line_no))?;
+        }
+
+        if out.len() >= WRITE_SIZE || (config.get_flush() && !out.is_empty()) {
+            writer.write_all(&out).await?;
+            out.clear();
+            if config.get_flush() {
+                writer.flush().await?;
+            }
+        }
+    }
+
+    writer.write_all(&out).await?;
+    writer.flush().await
+}
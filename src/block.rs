@@ -0,0 +1,69 @@
+use std::io::{self, Read, Write};
+
+use crate::{oversized, replace_record, Config, Malformed};
+
+/// Anonymizes `reader` reading blocks of [`Config::block_size`] bytes, complete lines are
+/// processed in place and only a trailing partial line is moved to the start of the block
+///
+/// The block grows if a single line does not fit, [`Config::max_line_len`] is applied to
+/// complete lines only.
+pub(crate) fn replace<R: Read, W: Write>(
+    config: &Config,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()> {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let max = config.get_max_line_len().unwrap_or(usize::MAX);
+    let mut block = vec![0; config.get_block_size().unwrap_or_default().max(1)];
+    let mut scratch = vec![];
+    let mut len = 0;
+    let mut line_no = 0;
+
+    loop {
+        if len == block.len() {
+            block.resize(2 * len, 0);
+        }
+        let n = match reader.read(&mut block[len..]) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let start = len;
+        len += n;
+
+        // lines before the last delimiter are complete, at EOF the remainder is a line as well
+        let end = if n == 0 {
+            len
+        } else {
+            block[start..len]
+                .iter()
+                .rposition(|&b| b == delimiter)
+                .map_or(0, |i| start + i + 1)
+        };
+        if end == 0 {
+            // nothing left after the last delimiter at EOF
+            if n == 0 {
+                break;
+            }
+            continue;
+        }
+
+        for line in block[..end].split_inclusive(|&b| b == delimiter) {
+            line_no += 1;
+            if line.len() > max {
+                oversized(config, line, &mut writer)?;
+            } else {
+                replace_record(config, line, &mut scratch, &mut writer)
+                    .map_err(|e| Malformed::shift(e, line_no))?;
+            }
+        }
+
+        block.copy_within(end..len, 0);
+        len -= end;
+        if n == 0 {
+            break;
+        }
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,417 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "regex")]
+use crate::compile_pattern;
+use crate::{
+    read_config, toml, CancellationToken, Config, Error, Format, LineObserver, LineScrubber, Log,
+    Oversized, Rejects, Stats,
+};
+
+/// Generates [`Layered`] for the fields layered by [`Config::merge`]
+macro_rules! layered {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        /// Values set on a [`ConfigBuilder`], they replace the values of lower layers even if
+        /// they are the default
+        #[derive(Debug, Clone, Default)]
+        struct Layered<'a> {
+            $($field: Option<$ty>,)*
+        }
+
+        impl<'a> Layered<'a> {
+            /// Marks the value of `key` in `config` as set
+            fn set_from(&mut self, config: &Config<'a>, key: &str) {
+                match key {
+                    $(stringify!($field) => self.$field = Some(Clone::clone(&config.$field)),)*
+                    _ => (),
+                }
+            }
+
+            /// Keeps the values set in `self`, adding those only set in `lower`
+            fn or(self, lower: Layered<'a>) -> Self {
+                Layered {
+                    $($field: self.$field.or(lower.$field),)*
+                }
+            }
+
+            /// Replaces the values of `config` with all values set
+            fn apply(self, config: &mut Config<'a>) {
+                $(
+                    if let Some(value) = self.$field {
+                        config.$field = value;
+                    }
+                )*
+            }
+        }
+    };
+}
+
+layered!(
+    ipv4: Cow<'a, str>,
+    ipv6: Cow<'a, str>,
+    host: Cow<'a, str>,
+    skip: bool,
+    strict: bool,
+    keep_dash: bool,
+    ip_only: bool,
+    keep_zone: bool,
+    verify_hosts: bool,
+    authuser: bool,
+    trim: bool,
+    strip_bom: bool,
+    thorough: bool,
+    optimize: bool,
+    flush: bool,
+    format: Format,
+    separators: Option<Cow<'a, str>>,
+    skip_fields: usize,
+    syslog: bool,
+    null_data: bool,
+    max_line_len: Option<usize>,
+    oversized: Oversized,
+    jobs: usize,
+    block_size: Option<usize>,
+);
+
+/// Builder for [`Config`], values are checked once in [`ConfigBuilder::build`]
+///
+/// ```
+/// let config = alog::Config::builder()
+///     .host("anonymous")
+///     .thorough(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.get_host_value(), "anonymous");
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct ConfigBuilder<'a> {
+    config: Config<'a>,
+    layered: Layered<'a>,
+    #[cfg(feature = "regex")]
+    drop_patterns: Vec<String>,
+    #[cfg(feature = "regex")]
+    match_patterns: Vec<String>,
+}
+
+/// Continues with the values of an existing `Config`, e.g. one read by [`Config::from_path`]
+impl<'a> From<Config<'a>> for ConfigBuilder<'a> {
+    fn from(config: Config<'a>) -> Self {
+        ConfigBuilder {
+            config,
+            layered: Layered::default(),
+            #[cfg(feature = "regex")]
+            drop_patterns: vec![],
+            #[cfg(feature = "regex")]
+            match_patterns: vec![],
+        }
+    }
+}
+
+impl<'a> ConfigBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a config file like [`Config::from_path`], the keys present in the file count as set,
+    /// see [`ConfigBuilder::over`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `path` can not be read or is not a valid config file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        read_config(path.as_ref(), Self::from_toml)
+    }
+
+    /// Parses a config file like [`Config::from_toml`], the keys present in `s` count as set, see
+    /// [`ConfigBuilder::over`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error on unknown keys or values of the wrong type.
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let config = Config::from_toml(s)?;
+        let mut layered = Layered::default();
+        for (_, key, _) in toml::parse(s).unwrap_or_default() {
+            layered.set_from(&config, &key);
+        }
+
+        Ok(ConfigBuilder {
+            layered,
+            ..config.into()
+        })
+    }
+
+    /// Layers the values set on this builder over `lower`, e.g. the command line over a config
+    /// file
+    ///
+    /// Unlike [`Config::merge`] a value set on this builder replaces the value of `lower` even if
+    /// it is the default. Patterns and scrubbers are appended, `stats`, `logger`, `observer`,
+    /// `rejects` and `cancel` are replaced if set.
+    ///
+    /// ```
+    /// let file = alog::ConfigBuilder::from_toml("authuser = true\nthorough = true").unwrap();
+    /// let config = alog::Config::builder()
+    ///     .authuser(false)
+    ///     .over(file)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(!config.get_authuser());
+    /// assert!(config.get_thorough());
+    /// ```
+    pub fn over(self, lower: ConfigBuilder<'a>) -> Self {
+        let mut config = lower.config;
+        config.merge(self.config);
+
+        ConfigBuilder {
+            config,
+            layered: self.layered.or(lower.layered),
+            #[cfg(feature = "regex")]
+            drop_patterns: [lower.drop_patterns, self.drop_patterns].concat(),
+            #[cfg(feature = "regex")]
+            match_patterns: [lower.match_patterns, self.match_patterns].concat(),
+        }
+    }
+
+    /// Returns the number of worker threads if set on this builder or read from a config file,
+    /// see [`ConfigBuilder::jobs`]
+    #[must_use]
+    pub fn get_jobs(&self) -> Option<usize> {
+        self.layered.jobs
+    }
+
+    /// IPv4 replacement, see [`Config::set_ipv4_value`]
+    pub fn ipv4(mut self, ipv4: impl Into<Cow<'a, str>>) -> Self {
+        self.layered.ipv4 = Some(ipv4.into());
+        self
+    }
+
+    /// IPv6 replacement, see [`Config::set_ipv6_value`]
+    pub fn ipv6(mut self, ipv6: impl Into<Cow<'a, str>>) -> Self {
+        self.layered.ipv6 = Some(ipv6.into());
+        self
+    }
+
+    /// `hostname` replacement, see [`Config::set_host_value`]
+    pub fn host(mut self, host: impl Into<Cow<'a, str>>) -> Self {
+        self.layered.host = Some(host.into());
+        self
+    }
+
+    /// See [`Config::set_skip`]
+    pub fn skip(mut self, b: bool) -> Self {
+        self.layered.skip = Some(b);
+        self
+    }
+
+    /// See [`Config::set_strict`]
+    pub fn strict(mut self, b: bool) -> Self {
+        self.layered.strict = Some(b);
+        self
+    }
+
+    /// See [`Config::set_keep_dash`]
+    pub fn keep_dash(mut self, b: bool) -> Self {
+        self.layered.keep_dash = Some(b);
+        self
+    }
+
+    /// See [`Config::set_ip_only`]
+    pub fn ip_only(mut self, b: bool) -> Self {
+        self.layered.ip_only = Some(b);
+        self
+    }
+
+    /// See [`Config::set_keep_zone`]
+    pub fn keep_zone(mut self, b: bool) -> Self {
+        self.layered.keep_zone = Some(b);
+        self
+    }
+
+    /// See [`Config::set_verify_hosts`]
+    pub fn verify_hosts(mut self, b: bool) -> Self {
+        self.layered.verify_hosts = Some(b);
+        self
+    }
+
+    /// See [`Config::set_authuser`]
+    pub fn authuser(mut self, b: bool) -> Self {
+        self.layered.authuser = Some(b);
+        self
+    }
+
+    /// See [`Config::set_trim`]
+    pub fn trim(mut self, b: bool) -> Self {
+        self.layered.trim = Some(b);
+        self
+    }
+
+    /// See [`Config::set_strip_bom`]
+    pub fn strip_bom(mut self, b: bool) -> Self {
+        self.layered.strip_bom = Some(b);
+        self
+    }
+
+    /// See [`Config::set_thorough`]
+    pub fn thorough(mut self, b: bool) -> Self {
+        self.layered.thorough = Some(b);
+        self
+    }
+
+    /// See [`Config::set_optimize`]
+    pub fn optimize(mut self, b: bool) -> Self {
+        self.layered.optimize = Some(b);
+        self
+    }
+
+    /// See [`Config::set_flush`]
+    pub fn flush(mut self, b: bool) -> Self {
+        self.layered.flush = Some(b);
+        self
+    }
+
+    /// See [`Config::set_format`]
+    pub fn format(mut self, format: Format) -> Self {
+        self.layered.format = Some(format);
+        self
+    }
+
+    /// See [`Config::set_syslog`]
+    pub fn syslog(mut self, b: bool) -> Self {
+        self.layered.syslog = Some(b);
+        self
+    }
+
+    /// See [`Config::set_null_data`]
+    pub fn null_data(mut self, b: bool) -> Self {
+        self.layered.null_data = Some(b);
+        self
+    }
+
+    /// See [`Config::set_separators`]
+    pub fn separators(mut self, separators: impl Into<Cow<'a, str>>) -> Self {
+        self.layered.separators = Some(Some(separators.into()));
+        self
+    }
+
+    /// See [`Config::push_comment_prefix`]
+    pub fn comment_prefix(mut self, prefix: impl Into<Cow<'a, str>>) -> Self {
+        self.config.comment_prefixes.push(prefix.into());
+        self
+    }
+
+    /// See [`Config::set_skip_fields`]
+    pub fn skip_fields(mut self, n: usize) -> Self {
+        self.layered.skip_fields = Some(n);
+        self
+    }
+
+    /// See [`Config::set_max_line_len`], must not be `0`
+    pub fn max_line_len(mut self, len: usize) -> Self {
+        self.layered.max_line_len = Some(Some(len));
+        self
+    }
+
+    /// See [`Config::set_oversized`]
+    pub fn oversized(mut self, o: Oversized) -> Self {
+        self.layered.oversized = Some(o);
+        self
+    }
+
+    /// See [`Config::set_jobs`], must not be `0`
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.layered.jobs = Some(jobs);
+        self
+    }
+
+    /// See [`Config::set_block_size`], must not be `0`
+    pub fn block_size(mut self, size: usize) -> Self {
+        self.layered.block_size = Some(Some(size));
+        self
+    }
+
+    /// See [`Config::set_stats`]
+    pub fn stats(mut self, stats: &'a Stats) -> Self {
+        self.config.stats = Some(stats);
+        self
+    }
+
+    /// See [`Config::set_rejects`]
+    pub fn rejects(mut self, rejects: &'a Rejects) -> Self {
+        self.config.rejects = Some(rejects);
+        self
+    }
+
+    /// See [`Config::set_logger`]
+    pub fn logger(mut self, logger: &'a dyn Log) -> Self {
+        self.config.logger = Some(logger);
+        self
+    }
+
+    /// See [`Config::set_observer`]
+    pub fn observer(mut self, observer: &'a dyn LineObserver) -> Self {
+        self.config.observer = Some(observer);
+        self
+    }
+
+    /// See [`Config::set_cancel`]
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.config.cancel = Some(cancel);
+        self
+    }
+
+    /// Adds a scrubber, see [`Config::push_scrubber`]
+    pub fn scrubber(mut self, scrubber: impl LineScrubber + 'a) -> Self {
+        self.config.scrubbers.push(Arc::new(scrubber));
+        self
+    }
+
+    /// Adds a drop pattern, compiled in [`ConfigBuilder::build`], see
+    /// [`Config::push_drop_pattern`]
+    #[cfg(feature = "regex")]
+    pub fn drop_pattern(mut self, pattern: &str) -> Self {
+        self.drop_patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Adds a match pattern, compiled in [`ConfigBuilder::build`], see
+    /// [`Config::push_match_pattern`]
+    #[cfg(feature = "regex")]
+    pub fn match_pattern(mut self, pattern: &str) -> Self {
+        self.match_patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Returns the finished [`Config`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`ErrorKind::Usage`] error if [`Config::validate`] fails or a pattern is not a
+    /// valid regular expression.
+    pub fn build(self) -> Result<Config<'a>, Error> {
+        let mut config = self.config;
+        self.layered.apply(&mut config);
+        config.validate().map_err(Error::Invalid)?;
+        #[cfg(feature = "regex")]
+        let config = compile_patterns(config, &self.drop_patterns, &self.match_patterns)?;
+
+        Ok(config)
+    }
+}
+
+/// Compiles and adds `drop` and `keep` patterns to `config`
+#[cfg(feature = "regex")]
+fn compile_patterns<'a>(
+    mut config: Config<'a>,
+    drop: &[String],
+    keep: &[String],
+) -> Result<Config<'a>, Error> {
+    for pattern in drop {
+        config.drop_patterns.push(compile_pattern(pattern)?);
+    }
+    for pattern in keep {
+        config.match_patterns.push(compile_pattern(pattern)?);
+    }
+    Ok(config)
+}
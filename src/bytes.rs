@@ -0,0 +1,239 @@
+//! Byte string search and replace, as used for [`Config::thorough`](crate::Config::thorough)
+//!
+//! ```
+//! use alog::bytes::Replace;
+//!
+//! let line = b"8.8.8.8 - - [10/Oct/2000:13:55:36 -0700] \"GET /?ip=8.8.8.8\"";
+//! assert_eq!(line.memmemsearch(b"8.8.8.8"), Some(vec![0, 51]));
+//! assert_eq!(
+//!     line.replace(b"8.8.8.8", b"127.0.0.1"),
+//!     b"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /?ip=127.0.0.1\""
+//! );
+//! ```
+
+#[cfg(feature = "legacy-search")]
+use std::str;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Search and replace on byte slices, implemented for `[u8]` only
+///
+/// All searches return the start of every non-overlapping match from left to right, `None` if
+/// there is none or `pattern` is empty.
+pub trait Replace: sealed::Sealed {
+    /// Returns a copy with every non-overlapping occurrence of `old` replaced by `new`
+    ///
+    /// Nothing is replaced if `old` is empty.
+    #[must_use]
+    fn replace(&self, old: &[u8], new: &[u8]) -> Vec<u8>;
+    /// Searches with the Knuth-Morris-Pratt algorithm
+    #[cfg(feature = "legacy-search")]
+    fn kmpsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
+    /// Searches with the Boyer-Moore-Horspool algorithm
+    #[cfg(feature = "legacy-search")]
+    fn bmsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
+    /// Searches with `pattern` as a regular expression, which must be valid UTF-8
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    #[cfg(feature = "legacy-search")]
+    fn research(&self, pattern: &[u8]) -> Option<Vec<usize>>;
+    /// Compares `pattern` at every position
+    #[cfg(feature = "legacy-search")]
+    fn windowsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
+    /// Searches with [`memchr::memmem`], the fastest search in most cases
+    fn memmemsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
+}
+
+impl sealed::Sealed for [u8] {}
+
+impl Replace for [u8] {
+    fn replace(&self, old: &[u8], new: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.len());
+        let mut i = 0;
+
+        // feature `legacy-search` keeps the previous default for comparison
+        #[cfg(feature = "legacy-search")]
+        let matches = self.windowsearch(old);
+        #[cfg(not(feature = "legacy-search"))]
+        let matches = self.memmemsearch(old);
+
+        if let Some(matches) = matches {
+            for m in matches {
+                result.extend_from_slice(&self[i..m]);
+                result.extend_from_slice(new);
+                i = m + old.len();
+            }
+            result.extend_from_slice(&self[i..]);
+        } else {
+            return self.to_vec();
+        }
+
+        result
+    }
+
+    #[cfg(feature = "legacy-search")]
+    #[allow(clippy::cast_sign_loss)]
+    fn kmpsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
+        let m = self.len();
+        let n = pattern.len();
+
+        let mut i = 0;
+        let mut j = 0;
+
+        if pattern.is_empty() || n > m {
+            return None;
+        }
+
+        let table = prefix_table(pattern);
+
+        let mut indices = Vec::new();
+
+        while i < m {
+            while j >= 0 && self[i] != pattern[j as usize] {
+                j = table[j as usize];
+            }
+
+            i += 1;
+            j += 1;
+            if (j as usize) == n {
+                indices.push(i - n);
+                j = table[j as usize];
+                i += n;
+            }
+        }
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(indices)
+        }
+    }
+
+    #[cfg(feature = "legacy-search")]
+    fn bmsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
+        let m = self.len();
+        let n = pattern.len();
+
+        if pattern.is_empty() || n > m {
+            return None;
+        }
+
+        let table = bad_char_table(pattern);
+
+        let mut indices = Vec::new();
+
+        let mut i = 0;
+        while i <= m - n {
+            let mut j = n - 1;
+            while pattern[j] == self[i + j] {
+                if j == 0 {
+                    indices.push(i);
+                    i += n;
+                    break;
+                }
+                j -= 1;
+            }
+            if j != 0 {
+                i += table[self[i + j] as usize];
+            }
+        }
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(indices)
+        }
+    }
+
+    #[cfg(feature = "legacy-search")]
+    fn research(&self, pattern: &[u8]) -> Option<Vec<usize>> {
+        // pattern was a &str not so long ago
+        let re = regex::bytes::Regex::new(str::from_utf8(pattern).unwrap()).unwrap();
+        let indices: Vec<_> = re.find_iter(self).map(|m| m.start()).collect();
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(indices)
+        }
+    }
+
+    #[cfg(feature = "legacy-search")]
+    fn windowsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
+        let m = self.len();
+        let n = pattern.len();
+
+        if pattern.is_empty() || n > m {
+            return None;
+        }
+
+        let mut indices = Vec::new();
+        let mut i = 0;
+
+        while i <= self.len() - n {
+            if &self[i..i + n] == pattern {
+                indices.push(i);
+                i += n;
+            } else {
+                i += 1;
+            }
+        }
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(indices)
+        }
+    }
+
+    fn memmemsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let indices: Vec<_> = memchr::memmem::find_iter(self, pattern).collect();
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(indices)
+        }
+    }
+}
+
+/// Knuth-Morris-Pratt failure function of `pattern`
+#[cfg(feature = "legacy-search")]
+#[allow(clippy::cast_sign_loss)]
+fn prefix_table(pattern: &[u8]) -> Vec<isize> {
+    let mut i = 0;
+    let mut j: isize = -1;
+    let mut table = vec![0; pattern.len() + 1];
+    table[i] = j;
+    while i < pattern.len() {
+        while j >= 0 && pattern[j as usize] != pattern[i] {
+            j = table[j as usize];
+        }
+
+        i += 1;
+        j += 1;
+        table[i] = j;
+    }
+    table
+}
+
+/// Boyer-Moore-Horspool shift for every byte value
+#[cfg(feature = "legacy-search")]
+fn bad_char_table(pattern: &[u8]) -> [usize; 256] {
+    let n = pattern.len();
+    let mut table = [n; 256];
+
+    for (i, &byte) in pattern.iter().enumerate().take(n - 1) {
+        table[byte as usize] = n - i - 1;
+    }
+
+    table
+}
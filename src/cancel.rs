@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag stopping a run between two lines, see
+/// [`Config::set_cancel`](crate::Config::set_cancel)
+///
+/// Clones share the same flag, e.g. to cancel a run from another thread.
+///
+/// ```
+/// let token = alog::CancellationToken::new();
+/// let config = alog::Config::builder().cancel(token.clone()).build().unwrap();
+/// token.cancel();
+///
+/// let mut buffer = vec![];
+/// let result = alog::run_raw(&config, &b"8.8.8.8 a\n"[..], &mut buffer);
+/// assert!(matches!(result, Err(alog::Error::Cancelled)));
+/// assert!(buffer.is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops all runs using this token before their next line
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Stops reading input, lines already read are still anonymized and the output is completed
+    ///
+    /// Runs reading input return [`Error::Interrupted`](crate::Error::Interrupted) with the
+    /// offset reached, listening and watching return `Ok(())`. Only updates atomics, so it can
+    /// be called from a signal handler, e.g. on SIGINT.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+}
+
+/// Uses an existing flag, runs are cancelled once it is `true`
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        CancellationToken {
+            cancelled: flag,
+            interrupted: Arc::default(),
+        }
+    }
+}
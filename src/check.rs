@@ -0,0 +1,176 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::net;
+
+use crate::Config;
+
+/// Kind of personal data left in a log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finding {
+    Ipv4,
+    Ipv6,
+    Email,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Finding::Ipv4 => "IPv4 address",
+            Finding::Ipv6 => "IPv6 address",
+            Finding::Email => "email address",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Returns every IP or email address in `line` except the configured replacements and
+/// unspecified addresses (`0.0.0.0`, `::`)
+fn findings<'l>(config: &Config, line: &'l [u8]) -> Vec<(Finding, &'l [u8])> {
+    let is_replacement = |token: &[u8]| {
+        [
+            config.get_ipv4_value(),
+            config.get_ipv6_value(),
+            config.get_host_value(),
+        ]
+        .iter()
+        .any(|r| r.as_bytes() == token)
+    };
+    let mut found = vec![];
+
+    for email in emails(line) {
+        if !is_replacement(email) {
+            found.push((Finding::Email, email));
+        }
+    }
+
+    for (start, token) in addresses(line) {
+        // product versions look like addresses, e.g. "Chrome/120.0.0.0"
+        if start > 0 && line[start - 1] == b'/' {
+            continue;
+        }
+        // addresses can be followed by punctuation, e.g. "from 8.8.8.8." or "[::2]:"
+        let trimmed = token
+            .iter()
+            .rposition(|&b| b != b'.' && b != b':')
+            .map_or(token, |end| &token[..=end]);
+        for token in [token, trimmed] {
+            let Ok(s) = std::str::from_utf8(token) else {
+                break;
+            };
+            let kind = match (s.parse::<net::Ipv4Addr>(), s.parse::<net::Ipv6Addr>()) {
+                (Ok(ip), _) if !ip.is_unspecified() => Finding::Ipv4,
+                (_, Ok(ip)) if !ip.is_unspecified() => Finding::Ipv6,
+                (Ok(_), _) | (_, Ok(_)) => break,
+                _ => continue,
+            };
+            if !is_replacement(token) {
+                found.push((kind, token));
+            }
+            break;
+        }
+    }
+
+    found
+}
+
+/// Returns the start of every candidate for an IPv4 or IPv6 address in `line` (validated by
+/// parsing) along with the candidate itself: every run of hex digits, colons and dots, which
+/// consists of more than dots
+fn addresses(line: &[u8]) -> Vec<(usize, &[u8])> {
+    let is_address = |b: &u8| b.is_ascii_hexdigit() || *b == b':' || *b == b'.';
+    let mut found = vec![];
+    let mut end = 0;
+
+    while let Some(start) = line[end..].iter().position(is_address).map(|i| end + i) {
+        end = line[start..]
+            .iter()
+            .position(|b| !is_address(b))
+            .map_or(line.len(), |i| start + i);
+        let token = &line[start..end];
+        if token.iter().any(|&b| b != b'.') {
+            found.push((start, token));
+        }
+    }
+
+    found
+}
+
+/// Returns every email address in `line`, like the pattern
+/// `[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+`
+fn emails(line: &[u8]) -> Vec<&[u8]> {
+    let is_local = |b: &u8| b.is_ascii_alphanumeric() || b"._%+-".contains(b);
+    let is_label = |b: &&u8| b.is_ascii_alphanumeric() || **b == b'-';
+    let mut found = vec![];
+    // end of the previous address, addresses never overlap
+    let mut end = 0;
+
+    for at in memchr::memchr_iter(b'@', line) {
+        if at < end {
+            continue;
+        }
+        let start = line[end..at]
+            .iter()
+            .rposition(|b| !is_local(b))
+            .map_or(end, |i| end + i + 1);
+        if start == at {
+            continue;
+        }
+
+        // at least two labels separated by single dots
+        let (mut labels, mut domain_end) = (0, at + 1);
+        loop {
+            let label = line[domain_end..].iter().take_while(is_label).count();
+            if label == 0 {
+                break;
+            }
+            labels += 1;
+            domain_end += label;
+            if line.get(domain_end) != Some(&b'.')
+                || !line.get(domain_end + 1).is_some_and(|b| is_label(&b))
+            {
+                break;
+            }
+            domain_end += 1;
+        }
+
+        if labels >= 2 {
+            found.push(&line[start..domain_end]);
+            end = domain_end;
+        }
+    }
+
+    found
+}
+
+/// Scans `reader` (named `name` in the report) for personal data and writes a report line per
+/// finding to `writer`
+///
+/// Returns the number of findings.
+pub(crate) fn check<R: BufRead, W: Write>(
+    config: &Config,
+    name: &str,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let mut buf = vec![];
+    let mut count = 0;
+
+    for n in 1.. {
+        buf.clear();
+        if reader.read_until(delimiter, &mut buf)? == 0 {
+            break;
+        }
+        for (kind, token) in findings(config, &buf) {
+            writeln!(
+                writer,
+                "{name}:{n}: {kind} '{}'",
+                String::from_utf8_lossy(token)
+            )?;
+            count += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
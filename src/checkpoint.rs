@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{replace_remote_address, temp_path, Config, Malformed};
+
+/// Bytes read from an input between two checkpoints
+pub(crate) const INTERVAL: u64 = 64 << 20;
+
+/// Checkpoint file, a JSON object mapping input paths to the number of bytes already processed
+#[derive(Debug)]
+pub(crate) struct State {
+    path: PathBuf,
+    offsets: BTreeMap<String, u64>,
+}
+
+impl State {
+    /// Loads the checkpoint file `path`, a missing file is an empty state
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        let offsets = match fs::read_to_string(path) {
+            Ok(s) => parse(&s).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid checkpoint file")
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(State {
+            path: path.to_path_buf(),
+            offsets,
+        })
+    }
+
+    /// Returns the number of bytes of `input` already processed
+    pub(crate) fn get(&self, input: &Path) -> u64 {
+        self.offsets
+            .get(&*input.to_string_lossy())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Records `offset` for `input` and replaces the checkpoint file atomically
+    pub(crate) fn save(&mut self, input: &Path, offset: u64) -> io::Result<()> {
+        self.offsets
+            .insert(input.to_string_lossy().into_owned(), offset);
+
+        let tmp = temp_path(&self.path);
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(self.to_json().as_bytes())?;
+        f.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        for (n, (input, offset)) in self.offsets.iter().enumerate() {
+            if n > 0 {
+                json.push_str(",\n");
+            }
+            json.push_str("  \"");
+            for c in input.chars() {
+                match c {
+                    '"' => json.push_str("\\\""),
+                    '\\' => json.push_str("\\\\"),
+                    c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => json.push(c),
+                }
+            }
+            json.push_str(&format!("\": {offset}"));
+        }
+        json.push_str("\n}\n");
+        json
+    }
+}
+
+/// Parses a flat JSON object of strings mapped to unsigned integers
+fn parse(s: &str) -> Option<BTreeMap<String, u64>> {
+    let mut offsets = BTreeMap::new();
+    let mut chars = s
+        .trim()
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .chars()
+        .peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Some(offsets);
+        }
+        if chars.next()? != '"' {
+            return None;
+        }
+
+        let mut key = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    'n' => key.push('\n'),
+                    't' => key.push('\t'),
+                    'r' => key.push('\r'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        key.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => key.push(c),
+                },
+                c => key.push(c),
+            }
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next()? != ':' {
+            return None;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_digit) {
+            value.push(c);
+        }
+        offsets.insert(key, value.parse().ok()?);
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            Some(',') => (),
+            None => return Some(offsets),
+            _ => return None,
+        }
+    }
+}
+
+/// Reader returning EOF at the first `delimiter` after `limit` bytes, so input can be processed
+/// in chunks of complete lines
+struct Chunk<R> {
+    reader: R,
+    delimiter: u8,
+    limit: u64,
+    consumed: u64,
+    /// Complete lines consumed, for the line numbers of strict mode
+    lines: u64,
+    last: u8,
+}
+
+impl<R: BufRead> Read for Chunk<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Chunk<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed >= self.limit && self.last == self.delimiter {
+            return Ok(&[]);
+        }
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt > 0 {
+            if let Ok(buf) = self.reader.fill_buf() {
+                self.last = buf[amt - 1];
+                self.lines += memchr::memchr_iter(self.delimiter, &buf[..amt]).count() as u64;
+            }
+        }
+        self.reader.consume(amt);
+        self.consumed += amt as u64;
+    }
+}
+
+/// Anonymizes `reader` (input file `input`) skipping bytes already processed according to
+/// `state`, the checkpoint is updated after every `interval` bytes
+pub(crate) fn replace<R: BufRead, W: Write>(
+    config: &Config,
+    mut reader: R,
+    mut writer: W,
+    state: &mut State,
+    input: &Path,
+    interval: u64,
+) -> io::Result<()> {
+    let offset = io::copy(&mut reader.by_ref().take(state.get(input)), &mut io::sink())?;
+
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let mut chunk = Chunk {
+        reader,
+        delimiter,
+        limit: interval,
+        consumed: 0,
+        lines: 0,
+        last: delimiter,
+    };
+
+    loop {
+        let lines = chunk.lines;
+        replace_remote_address(config, &mut chunk, &mut writer)
+            .map_err(|e| Malformed::shift(e, lines))?;
+        state.save(input, offset + chunk.consumed)?;
+        if chunk.reader.fill_buf()?.is_empty() {
+            return Ok(());
+        }
+        chunk.limit = chunk.consumed + interval;
+    }
+}
@@ -0,0 +1,286 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "gzip")]
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+
+/// Output compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Write plain text
+    #[default]
+    None,
+    /// Write gzip compressed data (requires feature `gzip`)
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Write zstd compressed data (requires feature `zstd`)
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Write bzip2 compressed data (requires feature `bzip2`)
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    /// Write xz compressed data (requires feature `xz`)
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+impl Compression {
+    /// Returns the compression matching the extension of `path`
+    ///
+    /// * `.gz` with feature `gzip` enabled,
+    /// * `.zst` with feature `zstd` enabled,
+    /// * `.bz2` with feature `bzip2` enabled,
+    /// * `.xz` with feature `xz` enabled.
+    ///
+    /// Any other extension (or disabled feature) returns [`Compression::None`].
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Some("zst") => Compression::Zstd,
+            #[cfg(feature = "bzip2")]
+            Some("bz2") => Compression::Bzip2,
+            #[cfg(feature = "xz")]
+            Some("xz") => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+
+    /// Returns the compression matching the magic bytes at the start of `data`
+    ///
+    /// Detects the same formats as [`Compression::from_path`], anything else (or a disabled
+    /// feature) returns [`Compression::None`].
+    #[must_use]
+    pub fn from_magic(data: &[u8]) -> Self {
+        match data {
+            #[cfg(feature = "gzip")]
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            #[cfg(feature = "bzip2")]
+            [b'B', b'Z', b'h', ..] => Compression::Bzip2,
+            #[cfg(feature = "xz")]
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+
+    /// Returns the file extension of the compression, e.g. `"gz"` for [`Compression::Gzip`]
+    ///
+    /// The inverse of [`Compression::from_path`], [`Compression::None`] returns `None`.
+    #[must_use]
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Some("gz"),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zst"),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Some("bz2"),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Some("xz"),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => "bzip2",
+            #[cfg(feature = "xz")]
+            Compression::Xz => "xz",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            #[cfg(feature = "gzip")]
+            "gzip" => Ok(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            "zstd" => Ok(Compression::Zstd),
+            #[cfg(feature = "bzip2")]
+            "bzip2" => Ok(Compression::Bzip2),
+            #[cfg(feature = "xz")]
+            "xz" => Ok(Compression::Xz),
+            _ => Err(format!("unsupported compression '{s}'")),
+        }
+    }
+}
+
+/// Writer compressing all data written to it with the selected [`Compression`]
+///
+/// Call [`Encoder::finish`] once all data is written. Dropping a gzip, bzip2 or xz encoder tries
+/// to finish the compressed stream as well, ignoring errors, a dropped zstd stream is left
+/// incomplete.
+///
+/// ## Example
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// let mut encoder = alog::Encoder::new(vec![], alog::Compression::default()).unwrap();
+/// alog::run_raw(&alog::Config::default(), Cursor::new(b"8.8.8.8 XxX"), &mut encoder).unwrap();
+///
+/// assert_eq!(encoder.finish().unwrap(), b"127.0.0.1 XxX");
+/// ```
+pub enum Encoder<W: Write> {
+    /// Uncompressed
+    Plain(W),
+    /// gzip compressed
+    #[cfg(feature = "gzip")]
+    Gzip(GzEncoder<W>),
+    /// zstd compressed
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    /// bzip2 compressed
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<W>),
+    /// xz compressed
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Creates a new encoder writing data compressed with `compression` to `writer`
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the compression context can not be created.
+    pub fn new(writer: W, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => Encoder::Plain(writer),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(writer, 0)?),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            )),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Encoder::Xz(xz2::write::XzEncoder::new(writer, 6)),
+        })
+    }
+
+    /// Finishes the compressed stream, flushes and returns the underlying writer
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the underlying writer returns an error.
+    #[allow(clippy::infallible_destructuring_match)]
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            Encoder::Plain(w) => w,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.finish()?,
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.finish()?,
+            #[cfg(feature = "xz")]
+            Encoder::Xz(e) => e.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.write(buf),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.write(buf),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.flush(),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(e) => e.flush(),
+        }
+    }
+}
+
+impl<W: Write> fmt::Debug for Encoder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoder::Plain(_) => write!(f, "Encoder::Plain"),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(_) => write!(f, "Encoder::Gzip"),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(_) => write!(f, "Encoder::Zstd"),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(_) => write!(f, "Encoder::Bzip2"),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(_) => write!(f, "Encoder::Xz"),
+        }
+    }
+}
+
+/// Returns a reader for `file`, decompressing data if `path` has a known compression extension
+///
+/// See [`Compression::from_path`].
+pub(crate) fn reader<R: Read + 'static>(path: &Path, file: R) -> io::Result<Box<dyn BufRead>> {
+    decoder(Compression::from_path(path), BufReader::new(file))
+}
+
+/// Returns a reader for `reader`, decompressing data if it starts with known magic bytes
+///
+/// See [`Compression::from_magic`].
+pub(crate) fn sniff<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    decoder(compression, reader)
+}
+
+/// Wraps `file` in a decoder for `compression`
+fn decoder<R: BufRead + 'static>(
+    compression: Compression,
+    file: R,
+) -> io::Result<Box<dyn BufRead>> {
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::read::MultiBzDecoder::new(file))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(BufReader::new(xz2::read::XzDecoder::new_multi_decoder(
+            file,
+        ))),
+    })
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::{replace_record, Config};
+
+/// Preview printed instead of writing any output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DryRun {
+    /// Unified diff of all changed lines
+    #[default]
+    Diff,
+    /// Only the anonymized versions of changed lines
+    Changed,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DryRun::Diff => "diff",
+            DryRun::Changed => "changed",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for DryRun {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diff" => Ok(DryRun::Diff),
+            "changed" => Ok(DryRun::Changed),
+            _ => Err(format!("unknown dry run mode '{s}'")),
+        }
+    }
+}
+
+/// Anonymizes `reader` (named `name` in diff headers) and writes a preview of the changes to
+/// `writer` according to `mode`
+///
+/// Lines are processed one at a time, [`Config::jobs`] and [`Config::max_line_len`] are
+/// ignored.
+pub(crate) fn preview<R: BufRead, W: Write>(
+    config: &Config,
+    mode: DryRun,
+    name: &str,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()> {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let (mut buf, mut scratch, mut out) = (vec![], vec![], vec![]);
+    let mut header = mode == DryRun::Diff;
+    let mut deleted = 0;
+
+    for n in 1.. {
+        buf.clear();
+        if reader.read_until(delimiter, &mut buf)? == 0 {
+            break;
+        }
+        out.clear();
+        replace_record(config, &buf, &mut scratch, &mut out)?;
+        if out == buf {
+            continue;
+        }
+
+        match mode {
+            DryRun::Changed => writer.write_all(&out)?,
+            DryRun::Diff => {
+                if header {
+                    writeln!(writer, "--- {name}\n+++ {name}")?;
+                    header = false;
+                }
+                if out.is_empty() {
+                    deleted += 1;
+                    writeln!(writer, "@@ -{n} +{},0 @@", n - deleted)?;
+                } else {
+                    writeln!(writer, "@@ -{n} +{} @@", n - deleted)?;
+                }
+                write_diff_line(&mut writer, b'-', &buf, delimiter)?;
+                if !out.is_empty() {
+                    write_diff_line(&mut writer, b'+', &out, delimiter)?;
+                }
+            }
+        }
+    }
+
+    writer.flush()
+}
+
+/// Writes `line` prefixed with `prefix`, always terminated by a newline
+fn write_diff_line<W: Write>(
+    writer: &mut W,
+    prefix: u8,
+    line: &[u8],
+    delimiter: u8,
+) -> io::Result<()> {
+    writer.write_all(&[prefix])?;
+    match line.strip_suffix(&[delimiter]) {
+        Some(line) => writer.write_all(line)?,
+        None => {
+            writer.write_all(line)?;
+            writer.write_all(b"\n\\ No newline at end of file")?;
+        }
+    }
+    writer.write_all(b"\n")
+}
@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+use std::{error, fmt, io};
+
+use crate::Format;
+
+/// Category of an [`Error`], e.g. to choose an exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Reading input or writing output failed
+    Io,
+    /// Invalid configuration or combination of options
+    Usage,
+    /// [`IOConfig::set_check`](crate::IOConfig::set_check) found personal data in the input
+    Findings,
+}
+
+/// Errors returned by [`run`](crate::run) and friends
+///
+/// Input paths are `-` for Stdin.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading input or writing output failed
+    Io(io::Error),
+    /// Input could not be opened
+    OpenInput { path: PathBuf, source: io::Error },
+    /// Reading or anonymizing an input failed after it was opened
+    Anonymize { path: PathBuf, source: io::Error },
+    /// Output could not be opened
+    OpenOutput { path: PathBuf, source: io::Error },
+    /// Writing the anonymized copy of `input` to `output` failed, both are the same path when
+    /// editing in place
+    Write {
+        input: PathBuf,
+        output: PathBuf,
+        source: io::Error,
+    },
+    /// Config file could not be read
+    ReadConfig { path: PathBuf, source: io::Error },
+    /// Invalid config file, `path` is `None` for [`Config::from_toml`](crate::Config::from_toml)
+    Parse {
+        path: Option<PathBuf>,
+        line_no: Option<usize>,
+        message: String,
+    },
+    /// Drop / match pattern is not a valid regular expression
+    Pattern { pattern: String, message: String },
+    /// Invalid value or combination of options
+    Usage(String),
+    /// [`Config`](crate::Config) rejected by [`Config::validate`](crate::Config::validate)
+    Invalid(Vec<ConfigError>),
+    /// Checkpoint file could not be read
+    Checkpoint(io::Error),
+    /// Listening socket could not be opened
+    Listen { addr: String, source: io::Error },
+    /// Watched directory could not be read
+    ReadDir { path: PathBuf, source: io::Error },
+    /// [`IOConfig::set_check`](crate::IOConfig::set_check) found `count` IP or email addresses
+    Findings { count: u64 },
+    /// Interrupted by [`CancellationToken::interrupt`](crate::CancellationToken::interrupt) (e.g.
+    /// on a signal) after reading `offset` bytes of `path`
+    Interrupted { path: PathBuf, offset: u64 },
+    /// Stopped by a [`CancellationToken`](crate::CancellationToken)
+    Cancelled,
+    /// Inputs skipped with [`IOConfig::set_keep_going`](crate::IOConfig::set_keep_going)
+    Failed { failed: usize, total: usize },
+    /// Any other failure, e.g. a panicked worker thread
+    Other(String),
+}
+
+impl Error {
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Parse { .. } | Error::Pattern { .. } | Error::Usage(_) | Error::Invalid(_) => {
+                ErrorKind::Usage
+            }
+            Error::Findings { .. } => ErrorKind::Findings,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::OpenInput { path, source } => {
+                write!(f, "Can not open input '{}': {source}", path.display())
+            }
+            Error::Anonymize { path, source } => {
+                write!(f, "Can not anonymize input '{}': {source}", path.display())
+            }
+            Error::OpenOutput { path, source } => {
+                write!(f, "Can not open output '{}': {source}", path.display())
+            }
+            Error::Write {
+                input,
+                output,
+                source,
+            } if input == output => {
+                write!(f, "Can not edit '{}' in place: {source}", input.display())
+            }
+            Error::Write {
+                input,
+                output,
+                source,
+            } => write!(
+                f,
+                "Can not anonymize '{}' to '{}': {source}",
+                input.display(),
+                output.display()
+            ),
+            Error::ReadConfig { path, source } => {
+                write!(f, "Can not read config file '{}': {source}", path.display())
+            }
+            Error::Parse {
+                path,
+                line_no,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "Invalid config file '{}', ", path.display())?;
+                }
+                if let Some(n) = line_no {
+                    write!(f, "line {n}: ")?;
+                }
+                write!(f, "{message}")
+            }
+            Error::Pattern { pattern, message } => {
+                write!(f, "Invalid pattern '{pattern}': {message}")
+            }
+            Error::Usage(message) | Error::Other(message) => write!(f, "{message}"),
+            Error::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", errors.join(", "))
+            }
+            Error::Checkpoint(e) => write!(f, "Can not read checkpoint file: {e}"),
+            Error::Listen { addr, source } => write!(f, "Can not listen on '{addr}': {source}"),
+            Error::ReadDir { path, source } => {
+                write!(f, "Can not read directory '{}': {source}", path.display())
+            }
+            Error::Findings { count } => {
+                write!(f, "Found {count} IP or email address(es) in the input")
+            }
+            Error::Interrupted { path, offset } => write!(
+                f,
+                "Interrupted after reading {offset} bytes of input '{}'",
+                path.display()
+            ),
+            Error::Cancelled => write!(f, "Cancelled"),
+            Error::Failed { failed, total } => write!(f, "{failed} of {total} input(s) failed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(source)
+            | Error::Checkpoint(source)
+            | Error::OpenInput { source, .. }
+            | Error::Anonymize { source, .. }
+            | Error::OpenOutput { source, .. }
+            | Error::Write { source, .. }
+            | Error::ReadConfig { source, .. }
+            | Error::Listen { source, .. }
+            | Error::ReadDir { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Line rejected by [`Config::strict`](crate::Config::strict), wrapped in an [`io::Error`] of
+/// kind [`io::ErrorKind::InvalidData`]
+///
+/// The [`io::Error`] is the source of [`Error::Anonymize`], of [`Error::Write`] when editing in
+/// place or writing to an output directory, and of [`Error::Io`] for
+/// [`run_raw`](crate::run_raw). Use [`io::Error::get_ref`] and `downcast_ref::<Malformed>()` to
+/// find the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Malformed {
+    /// Line number counted from 1, `0` if unknown
+    pub(crate) line_no: u64,
+    pub(crate) reason: &'static str,
+}
+
+impl Malformed {
+    /// Get the line number counted from 1, `0` if unknown, e.g. for
+    /// [`anonymize_line`](crate::anonymize_line)
+    #[must_use]
+    pub fn line_no(&self) -> u64 {
+        self.line_no
+    }
+
+    /// Get the reason the line was rejected, e.g. `"missing syslog header"`
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        self.reason
+    }
+
+    pub(crate) fn error(reason: &'static str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, Malformed { line_no: 0, reason })
+    }
+
+    pub(crate) fn is(e: &io::Error) -> bool {
+        e.get_ref().is_some_and(|e| e.is::<Malformed>())
+    }
+
+    /// Adds `lines` to the line number of a [`Malformed`] error, e.g. the number of lines read
+    /// before the chunk it was found in, other errors are returned unchanged
+    pub(crate) fn shift(e: io::Error, lines: u64) -> io::Error {
+        match e.get_ref().and_then(|e| e.downcast_ref::<Malformed>()) {
+            Some(&Malformed { line_no, reason }) => io::Error::new(
+                io::ErrorKind::InvalidData,
+                Malformed {
+                    line_no: line_no + lines,
+                    reason,
+                },
+            ),
+            None => e,
+        }
+    }
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line_no {
+            0 => write!(f, "malformed line: {}", self.reason),
+            n => write!(f, "malformed line {n}: {}", self.reason),
+        }
+    }
+}
+
+impl error::Error for Malformed {}
+
+/// Problem found by [`Config::validate`](crate::Config::validate), fields are named like in
+/// [`Config`](crate::Config)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// Replacement string `field` contains a line terminator or NUL byte, which would split the
+    /// anonymized line
+    Replacement { field: &'static str, value: String },
+    /// `field` is `0`
+    Zero { field: &'static str },
+    /// `field` has no effect with `format`, which has no leading `$remote_addr` / `$remote_user`
+    Unsupported { field: &'static str, format: Format },
+    /// `separators` is empty or contains non-ASCII characters, line terminators or NUL
+    Separators { value: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Replacement { field, value } => write!(
+                f,
+                "Invalid {field} replacement {value:?}, must not contain line terminators or NUL"
+            ),
+            ConfigError::Zero { field } => {
+                write!(f, "Invalid {field} '0', must be greater than zero")
+            }
+            ConfigError::Unsupported { field, format } => {
+                write!(f, "Option {field} is not supported with format '{format}'")
+            }
+            ConfigError::Separators { value } => write!(
+                f,
+                "Invalid separators {value:?}, must be ASCII characters other than line \
+                 terminators or NUL"
+            ),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
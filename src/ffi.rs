@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::{ptr, slice};
+
+use crate::{run_raw, Config, Error, ErrorKind};
+
+/// Returned on success by all functions returning `int`
+///
+/// On failure one of the other `ALOG_*` constants is returned (the exit status of the commandline
+/// tool for the same error), see [`alog_last_error`] for the message.
+pub const ALOG_OK: c_int = 0;
+/// Reading input or writing output failed
+pub const ALOG_ERROR_IO: c_int = 1;
+/// Invalid config or arguments, e.g. a null pointer
+pub const ALOG_ERROR_USAGE: c_int = 2;
+
+/// Opaque [`Config`] owned by the caller
+pub struct AlogConfig(Config<'static>);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Records `error` and returns its status
+fn fail(error: &Error) -> c_int {
+    set_last_error(&error.to_string());
+    match error.kind() {
+        ErrorKind::Usage => ALOG_ERROR_USAGE,
+        _ => ALOG_ERROR_IO,
+    }
+}
+
+fn usage(message: &str) -> c_int {
+    fail(&Error::Usage(message.to_string()))
+}
+
+/// Returns the config behind `config`, the default config for a null pointer
+///
+/// # Safety
+///
+/// `config` must be null or returned by [`alog_config_new`] / [`alog_config_from_toml`].
+unsafe fn config_or_default<'c>(
+    config: *const AlogConfig,
+    default: &'c Config<'static>,
+) -> &'c Config<'static> {
+    // SAFETY: guaranteed by the caller
+    unsafe { config.as_ref() }.map_or(default, |c| &c.0)
+}
+
+/// Returns a new config with default values, free it with [`alog_config_free`]
+#[no_mangle]
+pub extern "C" fn alog_config_new() -> *mut AlogConfig {
+    Box::into_raw(Box::new(AlogConfig(Config::default())))
+}
+
+/// Returns a new config read from a TOML document, see [`Config::from_toml`]
+///
+/// Returns null if `toml` is not valid, see [`alog_last_error`]. Free the config with
+/// [`alog_config_free`].
+///
+/// # Safety
+///
+/// `toml` must be null or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn alog_config_from_toml(toml: *const c_char) -> *mut AlogConfig {
+    if toml.is_null() {
+        usage("toml must not be NULL");
+        return ptr::null_mut();
+    }
+    // SAFETY: guaranteed by the caller
+    let toml = unsafe { CStr::from_ptr(toml) };
+    let Ok(toml) = toml.to_str() else {
+        usage("toml is not valid UTF-8");
+        return ptr::null_mut();
+    };
+    let config = Config::from_toml(toml).and_then(|config| {
+        config.validate().map_err(Error::Invalid)?;
+        Ok(config)
+    });
+    match config {
+        Ok(config) => Box::into_raw(Box::new(AlogConfig(config))),
+        Err(e) => {
+            fail(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a config, null is ignored
+///
+/// # Safety
+///
+/// `config` must be null or returned by [`alog_config_new`] / [`alog_config_from_toml`] and
+/// not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn alog_config_free(config: *mut AlogConfig) {
+    if !config.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+/// Anonymizes `input_len` bytes at `input` into a new buffer
+///
+/// On success `*output` and `*output_len` are set to the anonymized lines, free them with
+/// [`alog_buffer_free`]. A null `config` uses the default config.
+///
+/// # Safety
+///
+/// `config` must be null or a valid config, `input` must point to `input_len` readable bytes
+/// (or be null if `input_len` is 0) and `output` / `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn alog_anonymize_buffer(
+    config: *const AlogConfig,
+    input: *const u8,
+    input_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> c_int {
+    if output.is_null() || output_len.is_null() || (input.is_null() && input_len > 0) {
+        return usage("input, output and output_len must not be NULL");
+    }
+    let input = if input_len == 0 {
+        &[][..]
+    } else {
+        // SAFETY: guaranteed by the caller
+        unsafe { slice::from_raw_parts(input, input_len) }
+    };
+    let default = Config::default();
+    // SAFETY: guaranteed by the caller
+    let config = unsafe { config_or_default(config, &default) };
+
+    let mut buffer = Vec::with_capacity(input.len());
+    if let Err(e) = run_raw(config, input, &mut buffer) {
+        return fail(&e);
+    }
+    let buffer = Box::into_raw(buffer.into_boxed_slice());
+    // SAFETY: both checked for null above, valid for writes as guaranteed by the caller
+    unsafe {
+        *output_len = buffer.len();
+        *output = buffer.cast::<u8>();
+    }
+    ALOG_OK
+}
+
+/// Frees a buffer returned by [`alog_anonymize_buffer`], null is ignored
+///
+/// # Safety
+///
+/// `buffer` and `len` must be returned by [`alog_anonymize_buffer`] and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn alog_buffer_free(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, len)) });
+    }
+}
+
+/// Anonymizes everything read from `input_fd` into `output_fd` until end of input
+///
+/// Both file descriptors are left open. A null `config` uses the default config.
+///
+/// # Safety
+///
+/// `config` must be null or a valid config, `input_fd` and `output_fd` must be open file
+/// descriptors not used elsewhere until this function returns.
+#[cfg(unix)]
+#[no_mangle]
+pub unsafe extern "C" fn alog_run_fd(
+    config: *const AlogConfig,
+    input_fd: c_int,
+    output_fd: c_int,
+) -> c_int {
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter, Write};
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    if input_fd < 0 || output_fd < 0 {
+        return usage("Invalid file descriptor");
+    }
+    let default = Config::default();
+    // SAFETY: guaranteed by the caller
+    let config = unsafe { config_or_default(config, &default) };
+    // SAFETY: open as guaranteed by the caller, never closed thanks to `ManuallyDrop`
+    let input = ManuallyDrop::new(unsafe { File::from_raw_fd(input_fd) });
+    // SAFETY: see above
+    let output = ManuallyDrop::new(unsafe { File::from_raw_fd(output_fd) });
+
+    let mut writer = BufWriter::new(&*output);
+    let result = run_raw(config, BufReader::new(&*input), &mut writer)
+        .and_then(|()| writer.flush().map_err(Error::from));
+    match result {
+        Ok(()) => ALOG_OK,
+        Err(e) => fail(&e),
+    }
+}
+
+/// Returns the message of the last error on the calling thread, null if there was none
+///
+/// The string is valid until the next failing `alog_*` call on the same thread.
+#[no_mangle]
+pub extern "C" fn alog_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
@@ -0,0 +1,146 @@
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::{signal, CancellationToken};
+
+/// Time to wait for new data after reaching the end of the file
+const INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reader following a growing file (like `tail -f`)
+///
+/// Reaching the end of the file the reader waits for new data instead of returning `Ok(0)`, until
+/// `cancel` is interrupted (e.g. on SIGINT or SIGTERM).
+/// If the file at `path` was replaced (rotated) the new file is opened once all remaining data
+/// was read from the old one, if it was truncated reading starts from the beginning again.
+pub(crate) struct Follow {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    cancel: Option<CancellationToken>,
+}
+
+impl Follow {
+    pub(crate) fn open(path: &Path, cancel: Option<CancellationToken>) -> io::Result<Self> {
+        Ok(Follow {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            pos: 0,
+            cancel,
+        })
+    }
+
+    /// Returns `true` if a new file was opened or the current file was truncated
+    fn reopen(&mut self) -> io::Result<bool> {
+        let Ok(meta) = fs::metadata(&self.path) else {
+            // rotated, but no new file (yet)
+            return Ok(false);
+        };
+
+        if !same_file(&meta, &self.file.metadata()?) {
+            self.file = File::open(&self.path)?;
+            self.pos = 0;
+            Ok(true)
+        } else if meta.len() < self.pos {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.pos = 0;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+
+            thread::sleep(INTERVAL);
+            if signal::interrupted(self.cancel.as_ref()) {
+                return Ok(0);
+            }
+
+            // read any data written to a rotated file before switching
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            self.reopen()?;
+        }
+    }
+}
+
+/// Reader reopening a FIFO (named pipe) every time the writing process closes it
+///
+/// Opening the FIFO blocks until the next writer opens it.
+pub(crate) struct Reopen {
+    path: PathBuf,
+    file: File,
+    cancel: Option<CancellationToken>,
+}
+
+impl Reopen {
+    pub(crate) fn open(path: &Path, cancel: Option<CancellationToken>) -> io::Result<Self> {
+        Ok(Reopen {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            cancel,
+        })
+    }
+}
+
+impl Read for Reopen {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+            if signal::interrupted(self.cancel.as_ref()) {
+                return Ok(0);
+            }
+            self.file = File::open(&self.path)?;
+        }
+    }
+}
+
+/// Returns `true` if `path` is a FIFO (named pipe)
+#[cfg(unix)]
+pub(crate) fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_fifo(_: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
@@ -0,0 +1,412 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::{self, FromStr};
+
+use crate::{address_kind, replacement, skip_whitespace, zone_start, Address, Config};
+
+/// Log format profile, decides which parts of a line will be replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Format {
+    /// Common / Combined Log Format, the first *word* on every line is the `$remote_addr`
+    #[default]
+    Combined,
+    /// OpenSSH `sshd` messages (e.g. `/var/log/auth.log`)
+    Sshd,
+    /// Dovecot / Exim mail logs
+    Mail,
+    /// `varnishncsa` logs, like [`Format::Combined`] but the first field can be a list of
+    /// `X-Forwarded-For` addresses
+    Varnish,
+}
+
+impl Format {
+    /// All supported formats, see [`Format::description`] and [`Format::fields`]
+    pub const ALL: [Format; 4] = [
+        Format::Combined,
+        Format::Sshd,
+        Format::Mail,
+        Format::Varnish,
+    ];
+
+    /// Short description of the logs handled by this format
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::Combined => "Common / Combined Log Format, e.g. Apache or nginx access logs",
+            Format::Sshd => "OpenSSH sshd messages, e.g. /var/log/auth.log",
+            Format::Mail => "Dovecot / Exim mail logs",
+            Format::Varnish => "varnishncsa logs, X-Forwarded-For lists in the first field",
+        }
+    }
+
+    /// Fields anonymized with this format
+    #[must_use]
+    pub fn fields(self) -> &'static [&'static str] {
+        match self {
+            Format::Combined => &["$remote_addr (first word)", "$remote_user (with authuser)"],
+            Format::Sshd => &[
+                "address following 'from'",
+                "IPv4 / IPv6 addresses",
+                "user names",
+                "PAM rhost=, ruser= and user=",
+                "addresses in key=value words",
+            ],
+            Format::Mail => &[
+                "Dovecot user=<> and rip=",
+                "Exim H= host, HELO name and address",
+                "Exim A= and U= user names",
+            ],
+            Format::Varnish => &[
+                "$remote_addr (every address in the first field)",
+                "$remote_user (with authuser)",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Combined => "combined",
+            Format::Sshd => "sshd",
+            Format::Mail => "mail",
+            Format::Varnish => "varnish",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combined" => Ok(Format::Combined),
+            "sshd" => Ok(Format::Sshd),
+            "mail" => Ok(Format::Mail),
+            "varnish" => Ok(Format::Varnish),
+            _ => Err(format!("unknown format '{s}'")),
+        }
+    }
+}
+
+/// Returns start and end index of every whitespace separated word in `line`
+fn words(line: &[u8]) -> Vec<(usize, usize)> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (i, byte) in line.iter().enumerate() {
+        match (byte.is_ascii_whitespace(), start) {
+            (true, Some(s)) => {
+                words.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, line.len()));
+    }
+
+    words
+}
+
+/// Replaces remote addresses and user names in `sshd` messages
+///
+/// * the word following `from` is replaced like any `$remote_addr`,
+/// * any other word parseable as IPv4 / IPv6 address is replaced as well,
+/// * user names in `Accepted <method> for <user>`, `Failed <method> for <user>`,
+///   `Invalid user <user>`, `invalid user <user>`, `authenticating user <user>` and
+///   `from user <user>` / `by user <user>` are replaced with `"-"`, the word `user` is kept,
+/// * user names in PAM `session opened for user <user>(uid=…) by <user>(uid=…)` and
+///   `session closed for user <user>` are replaced with `"-"`, the `(uid=…)` suffix is kept,
+/// * PAM fields: `rhost=<host>` is replaced like any `$remote_addr`, `ruser=<user>` and
+///   `user=<user>` with `"-"`,
+/// * IPv4 / IPv6 addresses in any other `key=<address>` word, e.g. `SRC=` in kernel messages.
+pub(crate) fn replace_sshd<W: Write>(
+    config: &Config,
+    line: &[u8],
+    writer: &mut W,
+) -> io::Result<()> {
+    let words = words(line);
+    let word = |i: usize| -> &[u8] { &line[words[i].0..words[i].1] };
+    let mut last = 0;
+
+    for (i, &(start, end)) in words.iter().enumerate() {
+        let prev = |n: usize| if i >= n { word(i - n) } else { b"" };
+        let current = word(i);
+
+        let field = if let Some(key) = [&b"ruser="[..], b"user="]
+            .into_iter()
+            .find(|key| current.starts_with(key))
+        {
+            Some((key.len(), true))
+        } else if current.starts_with(b"rhost=") {
+            Some((6, false))
+        } else {
+            // `key=<address>` words, e.g. `SRC=1.2.3.4` in kernel messages
+            memchr::memchr(b'=', current)
+                .map(|i| i + 1)
+                .filter(|&i| is_ip(&current[i..]))
+                .map(|i| (i, false))
+        };
+        if let Some((offset, is_user)) = field {
+            let start = start + offset;
+            if start == end {
+                continue;
+            }
+            let (repl, end) = if is_user {
+                (&b"-"[..], end)
+            } else {
+                let addr = &line[start..end];
+                let addr = &addr[..zone_start(config, addr)];
+                (replacement(config, addr), start + addr.len())
+            };
+
+            writer.write_all(&line[last..start])?;
+            writer.write_all(repl)?;
+            last = end;
+            continue;
+        }
+
+        let is_user = (prev(1) == b"user"
+            && matches!(
+                prev(2),
+                b"invalid" | b"Invalid" | b"authenticating" | b"from" | b"by"
+            ))
+            || (prev(1) == b"for"
+                && matches!(prev(3), b"Accepted" | b"Failed")
+                && current != b"invalid")
+            || (prev(1) == b"user"
+                && prev(2) == b"for"
+                && matches!(prev(3), b"opened" | b"closed")
+                && prev(4) == b"session")
+            || (prev(1) == b"by"
+                && prev(5) == b"opened"
+                && prev(6) == b"session"
+                && !current.starts_with(b"("));
+
+        let (repl, end) = if is_user {
+            // keep the `(uid=1000)` suffix of PAM session messages
+            let uid = memchr::memmem::find(current, b"(uid=");
+            (&b"-"[..], uid.map_or(end, |i| start + i))
+        } else if (prev(1) == b"from" && !matches!(current, b"user" | b"invalid")) || is_ip(current)
+        {
+            let addr = &current[..zone_start(config, current)];
+            (replacement(config, addr), start + addr.len())
+        } else {
+            continue;
+        };
+
+        writer.write_all(&line[last..start])?;
+        writer.write_all(repl)?;
+        last = end;
+    }
+    writer.write_all(&line[last..])?;
+
+    Ok(())
+}
+
+/// Replaces remote addresses, HELO names and user names in Dovecot / Exim logs
+///
+/// * Dovecot: `user=<alice>` and `rip=1.2.3.4`,
+/// * Exim: `H=host (helo) [1.2.3.4]:port`, `A=authenticator:alice` and `U=alice`.
+///
+/// Addresses and HELO names are replaced like any `$remote_addr`, user names with `"-"`.
+pub(crate) fn replace_mail<W: Write>(
+    config: &Config,
+    line: &[u8],
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut last = 0;
+    let mut in_host = false;
+
+    for (start, end) in words(line) {
+        let word = &line[start..end];
+
+        let field = if word.starts_with(b"user=<") {
+            Some((delimited(word, 6, b">"), true))
+        } else if word.starts_with(b"rip=") {
+            Some((delimited(word, 4, b","), false))
+        } else if word.starts_with(b"U=") {
+            Some((delimited(word, 2, b""), true))
+        } else if word.starts_with(b"A=") {
+            word.iter()
+                .position(|&b| b == b':')
+                .map(|p| ((p + 1, word.len()), true))
+        } else if word.starts_with(b"H=") {
+            in_host = true;
+            match word.get(2) {
+                Some(b'(') => Some((delimited(word, 3, b")"), false)),
+                Some(b'[') => {
+                    in_host = false;
+                    Some((delimited(word, 3, b"]"), false))
+                }
+                _ => Some((delimited(word, 2, b""), false)),
+            }
+        } else if in_host && word.starts_with(b"(") {
+            Some((delimited(word, 1, b")"), false))
+        } else if in_host && word.starts_with(b"[") {
+            in_host = false;
+            Some((delimited(word, 1, b"]"), false))
+        } else {
+            in_host = false;
+            None
+        };
+
+        if let Some(((from, to), is_user)) = field {
+            if from == to {
+                continue;
+            }
+            let (repl, to) = if is_user {
+                (&b"-"[..], to)
+            } else {
+                let to = from + zone_start(config, &word[from..to]);
+                (replacement(config, &word[from..to]), to)
+            };
+
+            writer.write_all(&line[last..start + from])?;
+            writer.write_all(repl)?;
+            last = start + to;
+        }
+    }
+    writer.write_all(&line[last..])?;
+
+    Ok(())
+}
+
+/// Returns the length of the syslog envelope (RFC 3164 or RFC 5424) `line` is wrapped in, `0` if
+/// there is none
+///
+/// With `trim` set to `true` any ASCII whitespace following the envelope is included as well.
+pub(crate) fn syslog_envelope(line: &[u8], trim: bool) -> usize {
+    let pri = match line {
+        [b'<', rest @ ..] => rest
+            .iter()
+            .position(|&b| b == b'>')
+            .filter(|&p| p > 0 && rest[..p].iter().all(u8::is_ascii_digit))
+            .map(|p| p + 2),
+        _ => None,
+    };
+
+    let mut end = match pri {
+        Some(pri) if line[pri..].starts_with(b"1 ") => rfc5424_header(line, pri + 2),
+        Some(pri) => rfc3164_header(line, pri),
+        None => rfc3164_header(line, 0),
+    }
+    .unwrap_or(0);
+
+    if end > 0 && trim {
+        end += skip_whitespace(&line[end..]);
+    }
+
+    end
+}
+
+/// Returns the end of the RFC 3164 header (`TIMESTAMP [HOSTNAME] TAG: `), `pos` pointing to the
+/// timestamp
+///
+/// Timestamps are `Mmm dd hh:mm:ss` or RFC 3339 (e.g. rsyslog's high precision format), the
+/// hostname is optional (e.g. messages sent to `/dev/log`).
+fn rfc3164_header(line: &[u8], mut pos: usize) -> Option<usize> {
+    pos += timestamp_len(&line[pos..])?;
+
+    // HOSTNAME and TAG, or TAG only
+    for _ in 0..2 {
+        if line.get(pos) != Some(&b' ') {
+            return None;
+        }
+        pos += 1;
+        let len = line[pos..]
+            .iter()
+            .position(|&b| b == b' ')
+            .unwrap_or(line.len() - pos);
+        let word = &line[pos..pos + len];
+        pos += len;
+        if let Some(tag) = word.strip_suffix(b":") {
+            let is_tag = !tag.is_empty()
+                && tag
+                    .iter()
+                    .all(|&b| b.is_ascii_alphanumeric() || b"-_./[]".contains(&b));
+            return is_tag.then(|| (pos + 1).min(line.len()));
+        }
+    }
+
+    None
+}
+
+/// Returns the length of the RFC 3164 (`Oct 16 10:00:00`, `Oct  6 10:00:00`) or RFC 3339
+/// timestamp at the start of `line`
+fn timestamp_len(line: &[u8]) -> Option<usize> {
+    match line {
+        [m, o, n, b' ', d1, d2, b' ', h1, h2, b':', m1, m2, b':', s1, s2, ..]
+            if m.is_ascii_uppercase()
+                && o.is_ascii_lowercase()
+                && n.is_ascii_lowercase()
+                && (*d1 == b' ' || d1.is_ascii_digit())
+                && [d2, h1, h2, m1, m2, s1, s2]
+                    .iter()
+                    .all(|b| b.is_ascii_digit()) =>
+        {
+            Some(15)
+        }
+        [y1, y2, y3, y4, b'-', ..] if [y1, y2, y3, y4].iter().all(|b| b.is_ascii_digit()) => {
+            let len = line.iter().position(|&b| b == b' ').unwrap_or(line.len());
+            line[..len].contains(&b'T').then_some(len)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the end of the RFC 5424 header and structured data, `pos` pointing to the timestamp
+fn rfc5424_header(line: &[u8], mut pos: usize) -> Option<usize> {
+    // TIMESTAMP HOSTNAME APP-NAME PROCID MSGID
+    for _ in 0..5 {
+        pos += line[pos..].iter().position(|&b| b == b' ')? + 1;
+    }
+
+    if line[pos..].starts_with(b"-") {
+        pos += 1;
+    } else {
+        while line.get(pos) == Some(&b'[') {
+            let mut quoted = false;
+            let mut escaped = false;
+            let len = line[pos..].iter().position(|&b| {
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => quoted = !quoted,
+                    b']' if !quoted => return true,
+                    _ => (),
+                }
+                false
+            })?;
+            pos += len + 1;
+        }
+    }
+
+    if line.get(pos) == Some(&b' ') {
+        pos += 1;
+    }
+
+    Some(pos)
+}
+
+/// Returns the range in `word` starting at `from` up to (not including) the first of any
+/// `terminators`
+fn delimited(word: &[u8], from: usize, terminators: &[u8]) -> (usize, usize) {
+    let from = from.min(word.len());
+    let to = word[from..]
+        .iter()
+        .position(|b| terminators.contains(b))
+        .map_or(word.len(), |p| from + p);
+
+    (from, to)
+}
+
+fn is_ip(word: &[u8]) -> bool {
+    address_kind(word) != Address::Host
+}
@@ -0,0 +1,289 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::{open_output, replace_remote_address, temp_path, Config, Error, Format, IOConfig};
+
+/// Field anonymized unless others are set, see [`IOConfig::push_journal_field`]
+const DEFAULT_FIELD: &str = "MESSAGE";
+
+/// Field holding the position of an entry, passed to `journalctl --after-cursor`
+const CURSOR_FIELD: &[u8] = b"__CURSOR";
+
+/// Field holding the free text message of an entry
+const MESSAGE_FIELD: &[u8] = b"MESSAGE";
+
+/// Field holding the name of the program which logged an entry
+const IDENTIFIER_FIELD: &[u8] = b"SYSLOG_IDENTIFIER";
+
+/// Maximum length of a binary field value, the limit of `systemd-journal-remote`
+const MAX_FIELD_LEN: u64 = 768 * 1024 * 1024;
+
+/// Reads the systemd journal with `journalctl --output=export`, writing every entry with the
+/// fields of [`IOConfig::get_journal_fields`] anonymized to the output in the same format
+///
+/// With a checkpoint file ([`IOConfig::set_resume`]) the cursor of the last entry written is
+/// recorded whenever `journalctl` has no more entries ready and at the end, after flushing the
+/// output. Entries are written at least once, but may be written again if interrupted before
+/// the cursor was recorded.
+pub(crate) fn replace(config: &Config, ioconfig: &IOConfig) -> Result<(), Error> {
+    if ioconfig.get_input().is_some()
+        || ioconfig.get_in_place()
+        || ioconfig.get_output_dir().is_some()
+    {
+        return Err(Error::Usage(
+            "Reading the journal can not be combined with input files".to_string(),
+        ));
+    }
+
+    let cursor_file = ioconfig.get_resume();
+    let cursor = match cursor_file.map(fs::read_to_string) {
+        Some(Ok(cursor)) => Some(cursor.trim().to_string()).filter(|c| !c.is_empty()),
+        Some(Err(e)) if e.kind() == io::ErrorKind::NotFound => None,
+        Some(Err(e)) => return Err(Error::Checkpoint(e)),
+        None => None,
+    };
+
+    let mut command = Command::new("journalctl");
+    command.arg("--output=export");
+    if let Some(cursor) = &cursor {
+        command.arg(format!("--after-cursor={cursor}"));
+    }
+    if ioconfig.get_follow() {
+        command.arg("--follow");
+    }
+    command.args(ioconfig.get_journal_matches().iter().map(AsRef::as_ref));
+
+    let path = PathBuf::from("journalctl");
+    let mut child = match command.stdout(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(source) => return Err(Error::OpenInput { path, source }),
+    };
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or(io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+    // lines of a field are anonymized on their own, no worker threads per field
+    let config = Config {
+        jobs: 1,
+        ..config.clone()
+    };
+    let mut writer = open_output(ioconfig)?;
+    let result = copy_entries(
+        &config,
+        ioconfig.get_journal_fields(),
+        cursor_file,
+        BufReader::new(stdout),
+        &mut writer,
+    );
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait();
+    result.map_err(|source| Error::Anonymize {
+        path: path.clone(),
+        source,
+    })?;
+    match status {
+        Ok(status) if !status.success() => {
+            return Err(Error::Anonymize {
+                path,
+                source: io::Error::other(format!("journalctl failed ({status})")),
+            })
+        }
+        Err(source) => return Err(Error::Anonymize { path, source }),
+        Ok(_) => (),
+    }
+
+    writer.finish()?.finish()?;
+    Ok(())
+}
+
+/// Copies journal entries in export format from `reader` to `writer`, anonymizing `fields`
+/// (`MESSAGE` if empty) and recording the last cursor in `cursor_file`
+///
+/// `MESSAGE` fields are free text, their format is chosen for every entry, see
+/// [`entry_format`]. Other fields are anonymized with [`Config::format`].
+pub(crate) fn copy_entries<R: Read, W: Write>(
+    config: &Config,
+    fields: &[Cow<str>],
+    cursor_file: Option<&Path>,
+    mut reader: BufReader<R>,
+    mut writer: W,
+) -> io::Result<()> {
+    let selected = |name: &[u8]| match fields {
+        [] => name == DEFAULT_FIELD.as_bytes(),
+        fields => fields.iter().any(|f| f.as_bytes() == name),
+    };
+
+    let sshd = Config {
+        format: Format::Sshd,
+        ..config.clone()
+    };
+    let mail = Config {
+        format: Format::Mail,
+        ..config.clone()
+    };
+
+    let mut entry = vec![];
+    let (mut cursor, mut recorded) = (None, None);
+    while read_entry(&mut reader, &mut entry)? {
+        let identifier = entry
+            .iter()
+            .find(|(name, _)| name == IDENTIFIER_FIELD)
+            .map(|(_, value)| value.as_slice());
+        let message_config = match entry_format(config.get_format(), identifier) {
+            Format::Sshd => &sshd,
+            Format::Mail => &mail,
+            _ => config,
+        };
+
+        for (name, value) in &mut entry {
+            if name == CURSOR_FIELD {
+                cursor = Some(value.clone());
+            } else if selected(name) {
+                let config = if name == MESSAGE_FIELD {
+                    message_config
+                } else {
+                    config
+                };
+                *value = anonymize(config, value)?;
+            }
+            write_field(&mut writer, name, value)?;
+        }
+        writer.write_all(b"\n")?;
+
+        // the next read may block until new entries arrive, record what was written so far
+        if reader.buffer().is_empty() && cursor != recorded {
+            writer.flush()?;
+            record_cursor(cursor_file, cursor.as_deref())?;
+            recorded.clone_from(&cursor);
+        }
+    }
+
+    writer.flush()?;
+    if cursor != recorded {
+        record_cursor(cursor_file, cursor.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Returns the format of an entry logged by `identifier`
+///
+/// [`Format::Sshd`] and [`Format::Mail`] are kept. Otherwise entries of web servers are
+/// anonymized with `format`, mail servers with [`Format::Mail`] and any other entry with
+/// [`Format::Sshd`], which replaces every IP address and the address following `from` in free
+/// text.
+fn entry_format(format: Format, identifier: Option<&[u8]>) -> Format {
+    if matches!(format, Format::Sshd | Format::Mail) {
+        return format;
+    }
+    match identifier {
+        Some(b"nginx" | b"apache2" | b"httpd" | b"varnishncsa") => format,
+        Some(b"dovecot" | b"exim" | b"exim4") => Format::Mail,
+        _ => Format::Sshd,
+    }
+}
+
+/// Reads the fields of the next entry into `entry`, returns `false` at the end of the input
+///
+/// Fields are either `NAME=value\n` or, for values containing newlines or binary data, `NAME\n`
+/// followed by the length of the value (64 bit little endian), the value and `\n`. Entries end
+/// with an empty line. Binary values longer than [`MAX_FIELD_LEN`] are rejected.
+fn read_entry<R: BufRead>(reader: &mut R, entry: &mut Vec<(Vec<u8>, Vec<u8>)>) -> io::Result<bool> {
+    entry.clear();
+    let mut line = vec![];
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            if entry.is_empty() {
+                return Ok(false);
+            }
+            return Err(truncated());
+        }
+        if line.pop() != Some(b'\n') {
+            return Err(truncated());
+        }
+        if line.is_empty() {
+            if entry.is_empty() {
+                continue;
+            }
+            return Ok(true);
+        }
+
+        match memchr::memchr(b'=', &line) {
+            Some(i) => entry.push((line[..i].to_vec(), line[i + 1..].to_vec())),
+            None => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                let len = u64::from_le_bytes(len);
+                if len > MAX_FIELD_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "journal export field too long",
+                    ));
+                }
+                let mut value = vec![];
+                if reader.take(len).read_to_end(&mut value)? as u64 != len {
+                    return Err(truncated());
+                }
+                let mut newline = [0];
+                reader.read_exact(&mut newline)?;
+                if newline != *b"\n" {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid journal export field",
+                    ));
+                }
+                entry.push((line.clone(), value));
+            }
+        }
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated journal entry")
+}
+
+/// Writes a field in export format, in binary form if `value` contains a newline
+fn write_field<W: Write>(writer: &mut W, name: &[u8], value: &[u8]) -> io::Result<()> {
+    writer.write_all(name)?;
+    if value.contains(&b'\n') {
+        writer.write_all(b"\n")?;
+        writer.write_all(&(value.len() as u64).to_le_bytes())?;
+    } else {
+        writer.write_all(b"=")?;
+    }
+    writer.write_all(value)?;
+    writer.write_all(b"\n")
+}
+
+/// Anonymizes every line of a field value
+fn anonymize(config: &Config, value: &[u8]) -> io::Result<Vec<u8>> {
+    let mut line = Vec::with_capacity(value.len() + 1);
+    line.extend_from_slice(value);
+    line.push(b'\n');
+
+    let mut out = Vec::with_capacity(line.len());
+    replace_remote_address(config, &line[..], &mut out)?;
+    if out.last() == Some(&b'\n') {
+        out.pop();
+    }
+    Ok(out)
+}
+
+/// Replaces the contents of `path` with `cursor`, if there is a checkpoint file
+fn record_cursor(path: Option<&Path>, cursor: Option<&[u8]>) -> io::Result<()> {
+    let (Some(path), Some(cursor)) = (path, cursor) else {
+        return Ok(());
+    };
+    let tmp = temp_path(path);
+    let mut data = cursor.to_vec();
+    data.push(b'\n');
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
@@ -36,7 +36,30 @@
 //! anchor ^ if the line starts with an ASCII whitespace character.
 //!
 //! With version 0.9 the [`Config::thorough`] option was added. If set to `true` every occurrence
-//! of `$remote_addr` will also be replaced in the remainder of each line.
+//! of `$remote_addr` will also be replaced in the remainder of each line. Together with
+//! [`Config::authuser`] every occurrence of a cleared `$remote_user` is replaced with `"-"` as
+//! well, e.g. in request paths like `/~frank/`.
+//!
+//! The first *word* ends at the first ASCII whitespace character, unless other separators are
+//! set with [`Config::separators`], e.g. a tab for tab-delimited logs with spaces inside fields. A
+//! UTF-8 byte order mark in front of the first word is kept (or removed with
+//! [`Config::strip_bom`]) but never replaced as part of it.
+//!
+//! Other log formats can be selected with [`Config::format`]. With [`Format::Sshd`] remote
+//! addresses and user names in OpenSSH `sshd` messages (`/var/log/auth.log`) are replaced,
+//! [`Format::Mail`] does the same for Dovecot / Exim mail logs (including Exim's HELO names).
+//! [`Format::Varnish`] handles `varnishncsa` logs, where `%h` can be substituted by a list of
+//! `X-Forwarded-For` addresses.
+//!
+//! Log lines shipped via syslog (e.g. by CDNs like Fastly) can be handled with [`Config::syslog`]
+//! set to `true`. The syslog envelope is written unchanged and only the payload is anonymized.
+//!
+//! Presets for common formats are returned by [`Config::combined`],
+//! [`Config::vhost_combined`], [`Config::varnish`], [`Config::haproxy`], [`Config::sshd`] and
+//! [`Config::mail`].
+//!
+//! With the `tokio` feature [`run_async`] and [`run_raw_async`] anonymize any `AsyncBufRead`
+//! into an `AsyncWrite`, e.g. log lines received over the network, without blocking a thread.
 //!
 //! ### Personal data in server logs
 //!
@@ -57,212 +80,192 @@
 //!
 //! [GDPR]: https://gdpr.eu/article-4-definitions/
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
-use std::path::Path;
-use std::{fmt, net, str};
-
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, iter, net, process, str, thread};
+
+use bytes::Replace;
+#[cfg(feature = "regex")]
 use regex::bytes::Regex;
 
-#[macro_use(lazy_static)]
-extern crate lazy_static;
-
+#[cfg(feature = "tokio")]
+mod aio;
+mod block;
+mod builder;
+pub mod bytes;
+mod cancel;
+mod check;
+mod checkpoint;
+mod compress;
+mod diff;
+mod error;
+#[cfg(feature = "cdylib")]
+mod ffi;
+mod follow;
+mod format;
+#[cfg(feature = "journald")]
+mod journal;
+mod limit;
+mod listen;
+mod log;
+mod mapping;
+#[cfg(unix)]
+mod mmap;
+mod observe;
+mod parallel;
+mod pipeline;
+mod progress;
+#[cfg(feature = "python")]
+mod python;
+mod reader;
+mod rejects;
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+mod remote;
+mod rotate;
+mod scrub;
+mod signal;
+mod stats;
+mod stream;
 #[cfg(test)]
 mod tests;
+mod toml;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod writer;
+
+#[cfg(feature = "tokio")]
+pub use aio::{run_async, run_raw_async};
+pub use builder::ConfigBuilder;
+pub use cancel::CancellationToken;
+pub use check::Finding;
+pub use compress::{Compression, Encoder};
+pub use diff::DryRun;
+pub use error::{ConfigError, Error, ErrorKind, Malformed};
+pub use format::Format;
+pub use log::{Level, Log, StderrLog};
+pub use mapping::MappingStore;
+pub use observe::{LineInfo, LineObserver, Outcome};
+pub use pipeline::{Pipeline, Stage};
+pub use reader::AnonymizingReader;
+pub use rejects::Rejects;
+pub use rotate::Interval;
+pub use scrub::{LineCtx, LineScrubber};
+pub use signal::reopen_outputs;
+pub use stats::{Address, Stats, Summary};
+pub use stream::InputReader;
+pub use writer::AnonymizingWriter;
+
+/// Previous name of [`Error`]
+#[deprecated(note = "use `alog::Error` instead")]
+pub type IOError = Error;
+
+/// How to open existing output files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Append to existing files
+    #[default]
+    Append,
+    /// Truncate existing files
+    Truncate,
+    /// Refuse to write to existing files
+    NoClobber,
+}
 
-lazy_static! {
-    // $remote_user *can* contain whitespaces, so we search for the 'next'
-    // field (`$time_local`) instead
-    static ref RE: Regex = Regex::new(" \\[[0-9]{1,2}/").unwrap();
-}
-
-#[allow(dead_code)]
-trait Replace {
-    fn replace(&self, old: &[u8], new: &[u8]) -> Vec<u8>;
-    fn kmpsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
-    fn bmsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
-    fn research(&self, pattern: &[u8]) -> Option<Vec<usize>>;
-    fn windowsearch(&self, pattern: &[u8]) -> Option<Vec<usize>>;
-    fn prefix_table(pattern: &[u8]) -> Vec<isize>;
-    fn bad_char_table(pattern: &[u8]) -> [usize; 256];
-}
-
-impl Replace for [u8] {
-    fn replace(&self, old: &[u8], new: &[u8]) -> Vec<u8> {
-        let mut result = Vec::with_capacity(self.len());
-        let mut i = 0;
-
-        if let Some(matches) = self.windowsearch(old) {
-            for m in matches {
-                result.extend_from_slice(&self[i..m]);
-                result.extend_from_slice(new);
-                i = m + old.len();
-            }
-            result.extend_from_slice(&self[i..]);
-        } else {
-            return self.to_vec();
-        }
-
-        result
-    }
-
-    #[allow(clippy::cast_sign_loss)]
-    fn kmpsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
-        let m = self.len();
-        let n = pattern.len();
-
-        let mut i = 0;
-        let mut j = 0;
-
-        if pattern.is_empty() || n > m {
-            return None;
-        }
-
-        let table = Self::prefix_table(pattern);
-
-        let mut indices = Vec::new();
-
-        while i < m {
-            while j >= 0 && self[i] != pattern[j as usize] {
-                j = table[j as usize];
-            }
-
-            i += 1;
-            j += 1;
-            if (j as usize) == n {
-                indices.push(i - n);
-                j = table[j as usize];
-                i += n;
-            }
-        }
-
-        if indices.is_empty() {
-            None
-        } else {
-            Some(indices)
-        }
-    }
-
-    fn bmsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
-        let m = self.len();
-        let n = pattern.len();
-
-        if pattern.is_empty() || n > m {
-            return None;
-        }
-
-        let table = Self::bad_char_table(pattern);
-
-        let mut indices = Vec::new();
-
-        let mut i = 0;
-        while i <= m - n {
-            let mut j = n - 1;
-            while pattern[j] == self[i + j] {
-                if j == 0 {
-                    indices.push(i);
-                    i += n;
-                    break;
-                }
-                j -= 1;
-            }
-            if j != 0 {
-                i += table[self[i + j] as usize];
-            }
-        }
-
-        if indices.is_empty() {
-            None
-        } else {
-            Some(indices)
-        }
+impl OutputMode {
+    fn open_options(self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        match self {
+            OutputMode::Append => options.create(true).append(true),
+            OutputMode::Truncate => options.create(true).write(true).truncate(true),
+            OutputMode::NoClobber => options.create_new(true).write(true),
+        };
+        options
     }
+}
 
-    fn research(&self, pattern: &[u8]) -> Option<Vec<usize>> {
-        // pattern was a &str not so long ago
-        let re = Regex::new(str::from_utf8(pattern).unwrap()).unwrap();
-        let indices: Vec<_> = re.find_iter(self).map(|m| m.start()).collect();
+/// How to handle lines longer than [`Config::max_line_len`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Oversized {
+    /// Write oversized lines as is, without replacing anything
+    #[default]
+    Pass,
+    /// Drop oversized lines
+    Skip,
+    /// Return an error
+    Error,
+}
 
-        if indices.is_empty() {
-            None
-        } else {
-            Some(indices)
-        }
+impl fmt::Display for Oversized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Oversized::Pass => "pass",
+            Oversized::Skip => "skip",
+            Oversized::Error => "error",
+        };
+        write!(f, "{name}")
     }
+}
 
-    fn windowsearch(&self, pattern: &[u8]) -> Option<Vec<usize>> {
-        let m = self.len();
-        let n = pattern.len();
-
-        if pattern.is_empty() || n > m {
-            return None;
-        }
-
-        let mut indices = Vec::new();
-        let mut i = 0;
-
-        while i <= self.len() - n {
-            if &self[i..i + n] == pattern {
-                indices.push(i);
-                i += n;
-            } else {
-                i += 1;
-            }
-        }
+impl str::FromStr for Oversized {
+    type Err = String;
 
-        if indices.is_empty() {
-            None
-        } else {
-            Some(indices)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pass" => Ok(Oversized::Pass),
+            "skip" => Ok(Oversized::Skip),
+            "error" => Ok(Oversized::Error),
+            _ => Err(format!("unknown oversized line handling '{s}'")),
         }
     }
+}
 
-    #[allow(clippy::cast_sign_loss)]
-    fn prefix_table(pattern: &[u8]) -> Vec<isize> {
-        let mut i = 0;
-        let mut j: isize = -1;
-        let mut table = vec![0; pattern.len() + 1];
-        table[i] = j;
-        while i < pattern.len() {
-            while j >= 0 && pattern[j as usize] != pattern[i] {
-                j = table[j as usize];
-            }
+/// Input source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input<'a> {
+    /// Read from [`std::io::Stdin`]
+    Stdin,
+    /// Read from a file
+    Path(Cow<'a, Path>),
+    /// Read from a reader, see [`IOConfig::push_reader`]
+    Reader(InputReader),
+}
 
-            i += 1;
-            j += 1;
-            table[i] = j;
+impl Input<'_> {
+    /// Returns the input file path, `None` for [`Input::Stdin`]
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Input::Stdin | Input::Reader(_) => None,
+            Input::Path(p) => Some(p),
         }
-        table
     }
 
-    fn bad_char_table(pattern: &[u8]) -> [usize; 256] {
-        let n = pattern.len();
-        let mut table = [n; 256];
-
-        for (i, &byte) in pattern.iter().enumerate().take(n - 1) {
-            table[byte as usize] = n - i - 1;
+    /// Returns the input path used in errors, `-` for [`Input::Stdin`]
+    fn to_path_buf(&self) -> PathBuf {
+        match self {
+            Input::Stdin => PathBuf::from("-"),
+            Input::Path(p) => p.to_path_buf(),
+            Input::Reader(r) => PathBuf::from(r.name()),
         }
-
-        table
     }
 }
 
-#[derive(Debug)]
-pub struct IOError {
-    message: String,
-}
-
-impl fmt::Display for IOError {
+/// Displays [`Input::Stdin`] as `-` and [`Input::Reader`] by its name
+impl fmt::Display for Input<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
-    }
-}
-
-impl From<io::Error> for IOError {
-    fn from(error: io::Error) -> Self {
-        IOError {
-            message: error.to_string(),
+        match self {
+            Input::Stdin => write!(f, "-"),
+            Input::Path(p) => write!(f, "{}", p.display()),
+            Input::Reader(r) => write!(f, "{}", r.name()),
         }
     }
 }
@@ -270,31 +273,110 @@ impl From<io::Error> for IOError {
 /// INPUT / OUTPUT config
 #[derive(Debug)]
 pub struct IOConfig<'a> {
-    /// List of input paths / files, e.g. `Some(vec![Path::new("/tmp/test1.log"), Path::new("/tmp/test2.log")])`
+    /// List of inputs, e.g. `Some(vec![Input::Path(Path::new("/tmp/test1.log").into()), Input::Stdin])`
     /// If set to `None` the reader will read from Stdin.
-    input: Option<Vec<&'a Path>>,
-    /// Single output path / file
-    /// If set to `None` the writer will write to Stdout.
-    output: Option<&'a Path>,
+    input: Option<Vec<Input<'a>>>,
+    /// Output paths / files, the same data is written to every output
+    /// If empty the writer will write to Stdout.
+    output: Vec<Cow<'a, Path>>,
+    /// Output writer, receives the same data as any output files
+    writer: Option<stream::OutputWriter>,
+    /// Write to Stdout in addition to any output files
+    tee: bool,
+    /// Output compression
+    compression: Compression,
+    /// Edit input files in place
+    in_place: bool,
+    /// Keep the original of files edited in place, with this suffix appended to the file name
+    backup_suffix: Option<Cow<'a, str>>,
+    /// Output directory, every input is written to a file with the same name in this directory
+    output_dir: Option<Cow<'a, Path>>,
+    /// Input / output pairs, every input is written to its own output
+    pairs: Vec<(Cow<'a, Path>, Cow<'a, Path>)>,
+    /// How to open existing output files
+    output_mode: OutputMode,
+    /// Keep reading the input file as it grows
+    follow: bool,
+    /// Watch input directories for new files
+    watch: bool,
+    /// Reopen FIFO inputs once the writing process closes them
+    reopen_fifo: bool,
+    /// Listen on this address instead of reading input files, e.g. `udp://0.0.0.0:5514`
+    listen: Option<Cow<'a, str>>,
+    /// Read the systemd journal instead of input files
+    journal: bool,
+    /// Journal fields to anonymize, `MESSAGE` if empty
+    journal_fields: Vec<Cow<'a, str>>,
+    /// Journal matches passed to `journalctl`, e.g. `_SYSTEMD_UNIT=nginx.service`
+    journal_matches: Vec<Cow<'a, str>>,
+    /// Preserve permissions, times and ownership of input files when editing in place or
+    /// writing to an output directory
+    preserve: bool,
+    /// Rotate the output file once it exceeds this size (in bytes)
+    rotate_size: Option<u64>,
+    /// Start a new output file every interval, the output path is expanded with `strftime`
+    rotate_time: Option<Interval>,
+    /// Name of rotated output files, `{path}` and `{n}` are replaced
+    rotate_name: Cow<'a, str>,
+    /// Checkpoint file recording the bytes of every input already processed
+    resume: Option<Cow<'a, Path>>,
+    /// Memory map regular input files instead of reading them
+    mmap: bool,
+    /// Print a preview of the changes instead of writing any output
+    dry_run: Option<DryRun>,
+    /// Report personal data left in the inputs instead of writing any output
+    check: bool,
+    /// Skip inputs which can not be read instead of stopping
+    keep_going: bool,
+    /// Stop after reading this many lines of all inputs
+    max_lines: Option<u64>,
+    /// Stop after the line in which this many bytes of all inputs were read
+    max_bytes: Option<u64>,
+    /// Report throughput to stderr every interval and once at the end
+    progress_interval: Option<Duration>,
+    /// Draw a progress bar on stderr if the size of all inputs is known
+    progress_bar: bool,
 }
 
 /// Collection of replacement strings / config flags
+///
+/// With the `serde` feature enabled `Config` can be (de)serialized, patterns as their source
+/// strings. `stats`, `logger`, `observer`, `rejects`, `scrubbers` and `cancel` are skipped,
+/// missing fields keep their default value.
 #[allow(clippy::struct_excessive_bools)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Config<'a> {
     /// IPv4-parseable `$remote_addr` replacement string
-    pub ipv4: &'a str,
+    pub ipv4: Cow<'a, str>,
     /// IPv6-parseable `$remote_addr` replacement string
-    pub ipv6: &'a str,
+    pub ipv6: Cow<'a, str>,
     /// `$remote_addr` replacement string
-    pub host: &'a str,
+    pub host: Cow<'a, str>,
     /// Skip lines w/o a `$remote_addr` part / first word
     pub skip: bool,
+    /// Fail with a [`io::ErrorKind::InvalidData`] error naming the line number on the first line
+    /// which can not be parsed according to `format`, instead of writing or skipping it
+    pub strict: bool,
+    /// Keep a `$remote_addr` of `-` (unknown host) instead of replacing it like any host
+    pub keep_dash: bool,
+    /// Replace only `$remote_addr`s parseable as IPv4 / IPv6 address, lines starting with
+    /// anything else are kept unchanged (e.g. application logs mixed with access logs)
+    pub ip_only: bool,
+    /// Keep the zone ID of a scoped IPv6 address (e.g. `%eth0` of `fe80::1%eth0`) instead of
+    /// replacing it along with the address
+    pub keep_zone: bool,
+    /// Replace only host names valid according to RFC 1123 with `host`, other first words (e.g.
+    /// binary junk of corrupted lines) are kept unchanged
+    pub verify_hosts: bool,
     /// Try to clear the authuser field
     pub authuser: bool,
     /// Trim spaces from the start of every line
     pub trim: bool,
-    /// Replace all occurrences of `$remote_addr` in each
+    /// Remove a UTF-8 byte order mark at the start of a line instead of writing it back
+    pub strip_bom: bool,
+    /// Replace all occurrences of `$remote_addr` (and a cleared `$remote_user`) in each
     /// line
     pub thorough: bool,
     /// Don't clear authuser fields starting with "- ["
@@ -302,6 +384,56 @@ pub struct Config<'a> {
     pub optimize: bool,
     /// Flush output after each line
     pub flush: bool,
+    /// Log format profile
+    pub format: Format,
+    /// ASCII characters separating the first *word* from the rest of the line, e.g. `"\t"` for
+    /// tab-delimited logs with spaces inside fields (defaults to any ASCII whitespace)
+    pub separators: Option<Cow<'a, str>>,
+    /// Number of fields before `$remote_addr` which are kept unchanged, e.g. `1` for a leading
+    /// `vhost:port` or container name
+    pub skip_fields: usize,
+    /// Lines starting with any of these prefixes (e.g. `#` for W3C `#Fields:` directives) are
+    /// written unchanged
+    pub comment_prefixes: Vec<Cow<'a, str>>,
+    /// Lines are wrapped in a syslog envelope, only the payload will be anonymized
+    pub syslog: bool,
+    /// Records are terminated by NUL instead of newline bytes
+    pub null_data: bool,
+    /// Maximum line length in bytes (including the line terminator), longer lines are never
+    /// buffered completely
+    pub max_line_len: Option<usize>,
+    /// How to handle lines longer than `max_line_len`
+    pub oversized: Oversized,
+    /// Number of worker threads anonymizing lines in parallel
+    pub jobs: usize,
+    /// Read input in blocks of this many bytes and process the lines inside each block in place
+    pub block_size: Option<usize>,
+    /// Counters updated while anonymizing
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub stats: Option<&'a Stats>,
+    /// Receiver of diagnostic events
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub logger: Option<&'a dyn Log>,
+    /// Receiver of every processed line
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub observer: Option<&'a dyn LineObserver>,
+    /// Receiver of lines rejected by `skip` or `strict`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub rejects: Option<&'a Rejects>,
+    /// Lines matching any of these patterns are dropped
+    #[cfg(feature = "regex")]
+    #[cfg_attr(feature = "serde", serde(with = "patterns"))]
+    pub drop_patterns: Vec<Regex>,
+    /// If not empty only lines matching any of these patterns are written
+    #[cfg(feature = "regex")]
+    #[cfg_attr(feature = "serde", serde(with = "patterns"))]
+    pub match_patterns: Vec<Regex>,
+    /// Additional anonymizers, run in order after the built-in replacements
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub scrubbers: Vec<Arc<dyn LineScrubber + 'a>>,
+    /// Stops anonymizing before the next line once cancelled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancel: Option<CancellationToken>,
 }
 
 /// defaults to `None` for both input and output
@@ -309,7 +441,35 @@ impl<'a> Default for IOConfig<'a> {
     fn default() -> Self {
         IOConfig {
             input: None,
-            output: None,
+            output: vec![],
+            writer: None,
+            tee: false,
+            compression: Compression::None,
+            in_place: false,
+            backup_suffix: None,
+            output_dir: None,
+            pairs: vec![],
+            output_mode: OutputMode::Append,
+            follow: false,
+            watch: false,
+            reopen_fifo: false,
+            listen: None,
+            journal: false,
+            journal_fields: vec![],
+            journal_matches: vec![],
+            preserve: false,
+            rotate_size: None,
+            rotate_time: None,
+            rotate_name: Cow::Borrowed("{path}.{n}"),
+            resume: None,
+            mmap: false,
+            dry_run: None,
+            check: false,
+            keep_going: false,
+            max_lines: None,
+            max_bytes: None,
+            progress_interval: None,
+            progress_bar: false,
         }
     }
 }
@@ -318,41 +478,426 @@ impl<'a> Default for IOConfig<'a> {
 impl<'a> Default for Config<'a> {
     fn default() -> Self {
         Config {
-            ipv4: "127.0.0.1",
-            ipv6: "::1",
-            host: "localhost",
+            ipv4: Cow::Borrowed("127.0.0.1"),
+            ipv6: Cow::Borrowed("::1"),
+            host: Cow::Borrowed("localhost"),
             skip: false,
+            strict: false,
+            keep_dash: false,
+            ip_only: false,
+            keep_zone: false,
+            verify_hosts: false,
             authuser: false,
             trim: true,
+            strip_bom: false,
             thorough: false,
             optimize: true,
             flush: false,
+            format: Format::Combined,
+            separators: None,
+            skip_fields: 0,
+            comment_prefixes: vec![],
+            syslog: false,
+            null_data: false,
+            max_line_len: None,
+            oversized: Oversized::Pass,
+            jobs: 1,
+            block_size: None,
+            stats: None,
+            logger: None,
+            observer: None,
+            rejects: None,
+            #[cfg(feature = "regex")]
+            drop_patterns: vec![],
+            #[cfg(feature = "regex")]
+            match_patterns: vec![],
+            scrubbers: vec![],
+            cancel: None,
         }
     }
 }
 
+/// Lets [`run`] and [`run_raw`] accept a `Config` or a [`Pipeline`]
+impl<'a> AsRef<Config<'a>> for Config<'a> {
+    fn as_ref(&self) -> &Config<'a> {
+        self
+    }
+}
+
 impl<'a> Config<'a> {
     #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Returns a [`ConfigBuilder`] starting with the default values
+    pub fn builder() -> ConfigBuilder<'a> {
+        ConfigBuilder::new()
+    }
+
+    /// Returns a config for Common / Combined Log Format access logs (e.g. Apache or nginx),
+    /// clearing `$remote_user` as well
+    #[must_use]
+    pub fn combined() -> Self {
+        Config {
+            authuser: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a config for Apache's `vhost_combined` access logs, keeping the leading
+    /// `%v:%p` (virtual host and port) and clearing `$remote_user` as well
+    #[must_use]
+    pub fn vhost_combined() -> Self {
+        Config {
+            authuser: true,
+            skip_fields: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a config for `varnishncsa` logs, clearing `$remote_user` as well
+    #[must_use]
+    pub fn varnish() -> Self {
+        Config {
+            format: Format::Varnish,
+            authuser: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a config for HAProxy logs in syslog format, the client `address:port` following
+    /// the envelope is replaced
+    #[must_use]
+    pub fn haproxy() -> Self {
+        Config {
+            syslog: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a config for OpenSSH `sshd` messages in syslog format (e.g. `/var/log/auth.log`),
+    /// the envelope is kept unchanged
+    #[must_use]
+    pub fn sshd() -> Self {
+        Config {
+            format: Format::Sshd,
+            syslog: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a config for Dovecot / Exim mail logs
+    #[must_use]
+    pub fn mail() -> Self {
+        Config {
+            format: Format::Mail,
+            ..Self::default()
+        }
+    }
+
+    /// Overrides the values of `self` with all values of `other` differing from their default,
+    /// e.g. to layer defaults < config file < environment < command line
+    ///
+    /// Patterns and scrubbers of `other` are appended, `stats`, `logger`, `observer`, `rejects`
+    /// and `cancel` are replaced if set. A later layer can not reset a value to its default, see
+    /// [`ConfigBuilder::over`]. The result is not validated, see [`Config::validate`].
+    ///
+    /// ```
+    /// let mut config = alog::Config::from_toml("host = \"file\"\nthorough = true").unwrap();
+    /// config.merge(alog::Config::builder().host("cli").build().unwrap());
+    /// assert_eq!(config.get_host_value(), "cli");
+    /// assert!(config.get_thorough());
+    /// ```
+    pub fn merge(&mut self, other: Config<'a>) {
+        let default = Config::default();
+        let Config {
+            ipv4,
+            ipv6,
+            host,
+            skip,
+            strict,
+            keep_dash,
+            ip_only,
+            keep_zone,
+            verify_hosts,
+            authuser,
+            trim,
+            strip_bom,
+            thorough,
+            optimize,
+            flush,
+            format,
+            separators,
+            skip_fields,
+            comment_prefixes,
+            syslog,
+            null_data,
+            max_line_len,
+            oversized,
+            jobs,
+            block_size,
+            stats,
+            logger,
+            observer,
+            rejects,
+            #[cfg(feature = "regex")]
+            drop_patterns,
+            #[cfg(feature = "regex")]
+            match_patterns,
+            scrubbers,
+            cancel,
+        } = other;
+
+        macro_rules! layer {
+            ($($field:ident),*) => {
+                $(
+                    if $field != default.$field {
+                        self.$field = $field;
+                    }
+                )*
+            };
+        }
+        layer!(
+            ipv4,
+            ipv6,
+            host,
+            skip,
+            strict,
+            keep_dash,
+            ip_only,
+            keep_zone,
+            verify_hosts,
+            authuser,
+            trim,
+            strip_bom,
+            thorough,
+            optimize,
+            flush,
+            format,
+            separators,
+            skip_fields,
+            syslog,
+            null_data,
+            max_line_len,
+            oversized,
+            jobs,
+            block_size
+        );
+
+        self.stats = stats.or(self.stats);
+        self.logger = logger.or(self.logger);
+        self.observer = observer.or(self.observer);
+        self.rejects = rejects.or(self.rejects);
+        self.cancel = cancel.or(self.cancel.take());
+        #[cfg(feature = "regex")]
+        {
+            self.drop_patterns.extend(drop_patterns);
+            self.match_patterns.extend(match_patterns);
+        }
+        self.comment_prefixes.extend(comment_prefixes);
+        self.scrubbers.extend(scrubbers);
+    }
+
+    /// Reads a config file, see [`Config::from_toml`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `path` can not be read or is not a valid config file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        read_config(path.as_ref(), Config::from_toml)
+    }
+
+    /// Parses a config file in TOML format, keys are the names of the [`Config`] fields, e.g.
+    ///
+    /// ```toml
+    /// ipv4 = "0.0.0.0"
+    /// host = "anonymous"
+    /// format = "varnish"
+    /// thorough = true
+    /// max_line_len = 65_536
+    /// ```
+    ///
+    /// `comment_prefixes` are separated by whitespace, e.g. `"# ;"` and `jobs = 0` uses all CPUs.
+    /// Fields missing in `s` keep their default value, replacement strings are copied so the
+    /// result does not borrow from `s`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error on unknown keys or values of the wrong type.
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let mut config = Config::default();
+        let parse_error = |line_no, message| Error::Parse {
+            path: None,
+            line_no: Some(line_no),
+            message,
+        };
+        let entries = toml::parse(s).map_err(|(n, message)| parse_error(n, message))?;
+
+        for (n, key, value) in entries {
+            let invalid = || parse_error(n, format!("invalid value for '{key}'"));
+            match (key.as_str(), value) {
+                ("ipv4", toml::Value::String(v)) => config.ipv4 = v.to_string().into(),
+                ("ipv6", toml::Value::String(v)) => config.ipv6 = v.to_string().into(),
+                ("host", toml::Value::String(v)) => config.host = v.to_string().into(),
+                ("skip", toml::Value::Boolean(b)) => config.skip = b,
+                ("strict", toml::Value::Boolean(b)) => config.strict = b,
+                ("keep_dash", toml::Value::Boolean(b)) => config.keep_dash = b,
+                ("ip_only", toml::Value::Boolean(b)) => config.ip_only = b,
+                ("keep_zone", toml::Value::Boolean(b)) => config.keep_zone = b,
+                ("verify_hosts", toml::Value::Boolean(b)) => config.verify_hosts = b,
+                ("authuser", toml::Value::Boolean(b)) => config.authuser = b,
+                ("trim", toml::Value::Boolean(b)) => config.trim = b,
+                ("strip_bom", toml::Value::Boolean(b)) => config.strip_bom = b,
+                ("thorough", toml::Value::Boolean(b)) => config.thorough = b,
+                ("optimize", toml::Value::Boolean(b)) => config.optimize = b,
+                ("flush", toml::Value::Boolean(b)) => config.flush = b,
+                ("syslog", toml::Value::Boolean(b)) => config.syslog = b,
+                ("null_data", toml::Value::Boolean(b)) => config.null_data = b,
+                ("format", toml::Value::String(v)) => {
+                    config.format = v.parse().map_err(|message| parse_error(n, message))?;
+                }
+                ("separators", toml::Value::String(v)) => {
+                    config.separators = Some(v.replace("\\t", "\t").into());
+                }
+                ("comment_prefixes", toml::Value::String(v)) => {
+                    let prefixes = v.split_ascii_whitespace().map(|p| p.to_string().into());
+                    config.comment_prefixes.extend(prefixes);
+                }
+                ("oversized", toml::Value::String(v)) => {
+                    config.oversized = v.parse().map_err(|message| parse_error(n, message))?;
+                }
+                ("skip_fields", toml::Value::Integer(n)) => {
+                    config.skip_fields = usize::try_from(n).map_err(|_| invalid())?;
+                }
+                ("max_line_len", toml::Value::Integer(n)) => {
+                    config.max_line_len = Some(usize::try_from(n).map_err(|_| invalid())?);
+                }
+                ("block_size", toml::Value::Integer(n)) => {
+                    config.block_size = Some(usize::try_from(n).map_err(|_| invalid())?);
+                }
+                #[cfg(feature = "regex")]
+                ("drop_pattern", toml::Value::String(v)) => config.push_drop_pattern(v)?,
+                #[cfg(feature = "regex")]
+                ("match", toml::Value::String(v)) => config.push_match_pattern(v)?,
+                #[cfg(feature = "regex")]
+                ("drop_pattern" | "match", _) => return Err(invalid()),
+                #[cfg(not(feature = "regex"))]
+                ("drop_pattern" | "match", _) => {
+                    return Err(parse_error(n, format!("'{key}' requires feature `regex`")));
+                }
+                // `0` uses all CPUs, like `--jobs 0`
+                ("jobs", toml::Value::Integer(0)) => {
+                    config.jobs = thread::available_parallelism().map_or(1, usize::from);
+                }
+                ("jobs", toml::Value::Integer(n)) => {
+                    config.jobs = usize::try_from(n).map_err(|_| invalid())?;
+                }
+                (
+                    "ipv4" | "ipv6" | "host" | "skip" | "strict" | "keep_dash" | "ip_only"
+                    | "keep_zone" | "verify_hosts" | "authuser" | "trim" | "strip_bom" | "thorough"
+                    | "optimize" | "flush" | "syslog" | "null_data" | "format" | "separators"
+                    | "skip_fields" | "comment_prefixes" | "oversized" | "max_line_len"
+                    | "block_size" | "jobs",
+                    _,
+                ) => return Err(invalid()),
+                _ => return Err(parse_error(n, format!("unknown key '{key}'"))),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Checks all values and their combinations, see [`ConfigError`]
+    ///
+    /// Done by [`ConfigBuilder::build`], configs changed later (or created otherwise) are not
+    /// checked by [`run`].
+    ///
+    /// ```
+    /// let mut config = alog::Config::default();
+    /// config.set_host_value("a\nb");
+    /// config.set_jobs(0);
+    /// assert_eq!(config.validate().unwrap_err().len(), 2);
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+
+        for (field, value) in [
+            ("ipv4", &self.ipv4),
+            ("ipv6", &self.ipv6),
+            ("host", &self.host),
+        ] {
+            if value.contains(['\n', '\r', '\0']) {
+                errors.push(ConfigError::Replacement {
+                    field,
+                    value: value.to_string(),
+                });
+            }
+        }
+
+        if let Some(separators) = &self.separators {
+            if separators.is_empty()
+                || !separators.is_ascii()
+                || separators.contains(['\n', '\r', '\0'])
+            {
+                errors.push(ConfigError::Separators {
+                    value: separators.to_string(),
+                });
+            }
+        }
+
+        for (field, value) in [
+            ("jobs", Some(self.jobs)),
+            ("block_size", self.block_size),
+            ("max_line_len", self.max_line_len),
+        ] {
+            if value == Some(0) {
+                errors.push(ConfigError::Zero { field });
+            }
+        }
+
+        if !matches!(self.format, Format::Combined | Format::Varnish) {
+            for (field, value) in [
+                ("authuser", self.authuser),
+                ("thorough", self.thorough),
+                ("skip_fields", self.skip_fields > 0),
+            ] {
+                if value {
+                    errors.push(ConfigError::Unsupported {
+                        field,
+                        format: self.format,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Get IPv4 replacement value
     #[must_use]
-    pub fn get_ipv4_value(&self) -> &'a str {
-        self.ipv4
+    pub fn get_ipv4_value(&self) -> &str {
+        &self.ipv4
     }
 
     /// Get IPv6 replacement value
     #[must_use]
-    pub fn get_ipv6_value(&self) -> &'a str {
-        self.ipv6
+    pub fn get_ipv6_value(&self) -> &str {
+        &self.ipv6
     }
 
     /// Get string replacement value
     #[must_use]
-    pub fn get_host_value(&self) -> &'a str {
-        self.host
+    pub fn get_host_value(&self) -> &str {
+        &self.host
     }
 
     /// Get `skip` value
@@ -361,6 +906,36 @@ impl<'a> Config<'a> {
         self.skip
     }
 
+    /// Get `strict` value
+    #[must_use]
+    pub fn get_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Get `keep_dash` value
+    #[must_use]
+    pub fn get_keep_dash(&self) -> bool {
+        self.keep_dash
+    }
+
+    /// Get `ip_only` value
+    #[must_use]
+    pub fn get_ip_only(&self) -> bool {
+        self.ip_only
+    }
+
+    /// Get `keep_zone` value
+    #[must_use]
+    pub fn get_keep_zone(&self) -> bool {
+        self.keep_zone
+    }
+
+    /// Get `verify_hosts` value
+    #[must_use]
+    pub fn get_verify_hosts(&self) -> bool {
+        self.verify_hosts
+    }
+
     /// Get `authuser` value
     #[must_use]
     pub fn get_authuser(&self) -> bool {
@@ -372,6 +947,13 @@ impl<'a> Config<'a> {
     pub fn get_trim(&self) -> bool {
         self.trim
     }
+
+    /// Get `strip_bom` value
+    #[must_use]
+    pub fn get_strip_bom(&self) -> bool {
+        self.strip_bom
+    }
+
     /// Get `thorough` value
     #[must_use]
     pub fn get_thorough(&self) -> bool {
@@ -390,19 +972,129 @@ impl<'a> Config<'a> {
         self.flush
     }
 
+    /// Get log `format`
+    #[must_use]
+    pub fn get_format(&self) -> Format {
+        self.format
+    }
+
+    /// Get `syslog` value
+    #[must_use]
+    pub fn get_syslog(&self) -> bool {
+        self.syslog
+    }
+
+    /// Get `null_data` value
+    #[must_use]
+    pub fn get_null_data(&self) -> bool {
+        self.null_data
+    }
+
+    /// Get `separators` value
+    #[must_use]
+    pub fn get_separators(&self) -> Option<&str> {
+        self.separators.as_deref()
+    }
+
+    /// Get `skip_fields` value
+    #[must_use]
+    pub fn get_skip_fields(&self) -> usize {
+        self.skip_fields
+    }
+
+    /// Get `comment_prefixes` value
+    #[must_use]
+    pub fn get_comment_prefixes(&self) -> &[Cow<'a, str>] {
+        &self.comment_prefixes
+    }
+
+    /// Get `max_line_len` value
+    #[must_use]
+    pub fn get_max_line_len(&self) -> Option<usize> {
+        self.max_line_len
+    }
+
+    /// Get `oversized` value
+    #[must_use]
+    pub fn get_oversized(&self) -> Oversized {
+        self.oversized
+    }
+
+    /// Get `jobs` value
+    #[must_use]
+    pub fn get_jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Get `block_size` value
+    #[must_use]
+    pub fn get_block_size(&self) -> Option<usize> {
+        self.block_size
+    }
+
+    /// Get `stats` value
+    #[must_use]
+    pub fn get_stats(&self) -> Option<&'a Stats> {
+        self.stats
+    }
+
+    /// Get `logger` value
+    #[must_use]
+    pub fn get_logger(&self) -> Option<&'a dyn Log> {
+        self.logger
+    }
+
+    /// Get `observer` value
+    #[must_use]
+    pub fn get_observer(&self) -> Option<&'a dyn LineObserver> {
+        self.observer
+    }
+
+    /// Get `rejects` value
+    #[must_use]
+    pub fn get_rejects(&self) -> Option<&'a Rejects> {
+        self.rejects
+    }
+
+    #[cfg(feature = "regex")]
+    /// Get `drop_patterns` value
+    #[must_use]
+    pub fn get_drop_patterns(&self) -> &[Regex] {
+        &self.drop_patterns
+    }
+
+    #[cfg(feature = "regex")]
+    /// Get `match_patterns` value
+    #[must_use]
+    pub fn get_match_patterns(&self) -> &[Regex] {
+        &self.match_patterns
+    }
+
+    /// Get `scrubbers` value
+    #[must_use]
+    pub fn get_scrubbers(&self) -> &[Arc<dyn LineScrubber + 'a>] {
+        &self.scrubbers
+    }
+
+    /// Get `cancel` value
+    #[must_use]
+    pub fn get_cancel(&self) -> Option<&CancellationToken> {
+        self.cancel.as_ref()
+    }
+
     /// Set IPv4 replacement `String`
-    pub fn set_ipv4_value(&mut self, ipv4: &'a str) {
-        self.ipv4 = ipv4;
+    pub fn set_ipv4_value(&mut self, ipv4: impl Into<Cow<'a, str>>) {
+        self.ipv4 = ipv4.into();
     }
 
     /// Set IPv6 replacement `String`
-    pub fn set_ipv6_value(&mut self, ipv6: &'a str) {
-        self.ipv6 = ipv6;
+    pub fn set_ipv6_value(&mut self, ipv6: impl Into<Cow<'a, str>>) {
+        self.ipv6 = ipv6.into();
     }
 
     /// Set `hostname` replacement `String`
-    pub fn set_host_value(&mut self, host: &'a str) {
-        self.host = host;
+    pub fn set_host_value(&mut self, host: impl Into<Cow<'a, str>>) {
+        self.host = host.into();
     }
 
     /// Set `flush` field
@@ -419,6 +1111,12 @@ impl<'a> Config<'a> {
     pub fn set_trim(&mut self, b: bool) {
         self.trim = b;
     }
+
+    /// Set `strip_bom` field
+    pub fn set_strip_bom(&mut self, b: bool) {
+        self.strip_bom = b;
+    }
+
     /// Set `thorough` field
     pub fn set_thorough(&mut self, b: bool) {
         self.thorough = b;
@@ -433,229 +1131,2450 @@ impl<'a> Config<'a> {
     pub fn set_skip(&mut self, b: bool) {
         self.skip = b;
     }
-}
 
-impl<'a> IOConfig<'a> {
-    #[must_use]
-    pub fn new() -> Self {
-        Self::default()
+    /// Set `strict` field, the first malformed line fails the run instead of being written as is
+    /// (or skipped with `skip`), unless it can be written to [`Config::rejects`]
+    ///
+    /// A line is malformed if its first word is missing or neither an IP address, a host name nor
+    /// `-`, if `authuser` is set and no `[$time_local]` field follows it, or if `syslog` is set
+    /// and there is no syslog header. Lines of [`Format::Sshd`] and [`Format::Mail`] are only
+    /// checked for the syslog header. The error holds a [`Malformed`] with the line number.
+    pub fn set_strict(&mut self, b: bool) {
+        self.strict = b;
     }
 
-    #[must_use]
-    /// Get input / reader names, if any (defaults to `None`)
-    pub fn get_input(&self) -> Option<&Vec<&'a Path>> {
-        self.input.as_ref()
+    /// Set `keep_dash` field, a `$remote_addr` of `-` (unknown host) is kept unchanged
+    pub fn set_keep_dash(&mut self, b: bool) {
+        self.keep_dash = b;
     }
 
-    #[must_use]
-    /// Get output / writer name (defaults to `None`)
-    pub fn get_output(&self) -> Option<&'a Path> {
-        self.output
+    /// Set `ip_only` field, only IPv4 / IPv6 addresses are replaced
+    pub fn set_ip_only(&mut self, b: bool) {
+        self.ip_only = b;
     }
 
-    /// Add input `Path`
-    pub fn push_input<P: AsRef<Path> + ?Sized>(&mut self, i: &'a P) {
-        if let Some(input) = &mut self.input {
-            input.push(i.as_ref());
-        } else {
-            self.input = Some(vec![]);
-            self.push_input(i);
-        }
+    /// Set `keep_zone` field, the zone ID of a scoped IPv6 address (e.g. `%eth0`) is kept after
+    /// its replacement
+    pub fn set_keep_zone(&mut self, b: bool) {
+        self.keep_zone = b;
     }
 
-    /// Set output `Path`
-    pub fn set_output(&mut self, o: &'a Path) {
-        self.output = Some(o);
+    /// Set `verify_hosts` field, only valid host names are replaced with `host`
+    ///
+    /// A valid host name has at most 253 characters (plus an optional trailing dot) in labels of
+    /// 1 to 63 ASCII letters, digits and hyphens, not starting or ending with a hyphen. A first
+    /// word of `-` is not a host name and kept as well.
+    pub fn set_verify_hosts(&mut self, b: bool) {
+        self.verify_hosts = b;
     }
-}
 
-/// Reads lines from `reader`, if there is a '*first word*' (any String separated from the
-/// remainder of the line by a b' ' (Space) byte) this word will be replaced
-///
-/// Any word, that can be parsed as
-/// * [`std::net::Ipv4Addr`] will be replaced with [`alog::Config::ipv4`],
-/// * [`std::net::Ipv6Addr`] will be replaced with [`alog::Config::ipv6`],
-/// * any other *word* will be replaced with [`alog::Config::host`].
-///
-/// Any line without a 'first word' will be written as is if [`alog::Config::get_skip()`] returns
-/// `false` (default), or will be skipped otherwise.
-///
-/// ## Errors
-///
-/// This function will return an I/O error if the underlying reader or writer returns an error.
-///
-/// [`alog::Config::get_ipv4_value()`]: ./struct.Config.html#method.get_ipv4_value
-/// [`alog::Config::get_ipv6_value()`]: ./struct.Config.html#method.get_ipv6_value
-/// [`alog::Config::get_host_value()`]: ./struct.Config.html#method.get_host_value
-fn replace_remote_address<R: BufRead, W: Write>(
-    config: &Config,
-    mut reader: R,
-    mut writer: W,
-) -> Result<(), io::Error> {
-    let mut buf = vec![];
-    let mut repl;
+    /// Set log `format`
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
 
-    'lines: loop {
-        buf.clear();
-        let bytes_read = reader.read_until(b'\n', &mut buf)?;
+    /// Set `syslog` field
+    pub fn set_syslog(&mut self, b: bool) {
+        self.syslog = b;
+    }
+
+    /// Set `null_data` field
+    pub fn set_null_data(&mut self, b: bool) {
+        self.null_data = b;
+    }
+
+    /// Set `separators` field, ASCII characters ending the first *word* instead of whitespace
+    pub fn set_separators(&mut self, separators: impl Into<Cow<'a, str>>) {
+        self.separators = Some(separators.into());
+    }
+
+    /// Set `skip_fields` field, the first `n` fields are written unchanged and the field after
+    /// them is handled as `$remote_addr`
+    ///
+    /// Fields end at [`Config::separators`] like the first *word*, further spaces and tabs after
+    /// a field are part of the separator unless other separators are set. Only supported with
+    /// [`Format::Combined`] and [`Format::Varnish`].
+    pub fn set_skip_fields(&mut self, n: usize) {
+        self.skip_fields = n;
+    }
+
+    /// Add a prefix to `comment_prefixes`, lines starting with it are written unchanged
+    ///
+    /// Prefixes are matched after leading spaces were trimmed (see [`Config::trim`]) and after
+    /// drop patterns were applied, empty prefixes are ignored.
+    pub fn push_comment_prefix(&mut self, prefix: impl Into<Cow<'a, str>>) {
+        self.comment_prefixes.push(prefix.into());
+    }
+
+    /// Set `max_line_len` field
+    pub fn set_max_line_len(&mut self, len: usize) {
+        self.max_line_len = Some(len);
+    }
+
+    /// Set `oversized` field
+    pub fn set_oversized(&mut self, o: Oversized) {
+        self.oversized = o;
+    }
+
+    /// Set `jobs` field, the number of worker threads, must not be `0` (`jobs = 0` in a config file
+    /// and `--jobs 0` on the command line use all CPUs instead)
+    ///
+    /// Lines are processed serially when flushing every line or with a maximum line length set. When
+    /// editing in place or writing to an output directory up to `jobs` files are processed
+    /// concurrently instead.
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs;
+    }
+
+    /// Set `block_size` field
+    ///
+    /// Instead of copying every line out of the input buffer, blocks of `size` bytes are read and
+    /// all complete lines are anonymized in place. Lines longer than a block are buffered
+    /// completely, even with [`Config::max_line_len`] set.
+    pub fn set_block_size(&mut self, size: usize) {
+        self.block_size = Some(size);
+    }
+
+    /// Set `stats` field, lines, replacements and bytes written are counted in `stats`
+    ///
+    /// Every line is written in one piece to compare it with the input, which costs some
+    /// performance.
+    pub fn set_stats(&mut self, stats: &'a Stats) {
+        self.stats = Some(stats);
+    }
+
+    /// Set `logger` field, diagnostic events (e.g. inputs opened, oversized lines) are passed to
+    /// `logger`
+    pub fn set_logger(&mut self, logger: &'a dyn Log) {
+        self.logger = Some(logger);
+    }
+
+    /// Set `observer` field, every line is reported to `observer` with its outcome and
+    /// replacements
+    ///
+    /// Like [`Config::set_stats`] every line is written in one piece, which costs some
+    /// performance.
+    pub fn set_observer(&mut self, observer: &'a dyn LineObserver) {
+        self.observer = Some(observer);
+    }
+
+    /// Set `rejects` field, lines skipped with `skip` are written to `rejects` as well as
+    /// malformed lines with `strict`, which no longer fail the run
+    pub fn set_rejects(&mut self, rejects: &'a Rejects) {
+        self.rejects = Some(rejects);
+    }
+
+    /// Add a pattern to `drop_patterns`, matching lines are not written at all
+    ///
+    /// Patterns are matched against the whole line (without terminator) before anything is
+    /// replaced. Unicode support is disabled, e.g. `\d` only matches ASCII digits.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn push_drop_pattern(&mut self, pattern: &str) -> Result<(), Error> {
+        self.drop_patterns.push(compile_pattern(pattern)?);
+        Ok(())
+    }
+
+    /// Add a pattern to `match_patterns`, only lines matching any of them are written
+    ///
+    /// Patterns are matched like [`Config::push_drop_pattern`], lines matching a drop pattern are
+    /// dropped even if they match.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn push_match_pattern(&mut self, pattern: &str) -> Result<(), Error> {
+        self.match_patterns.push(compile_pattern(pattern)?);
+        Ok(())
+    }
+
+    /// Add a [`LineScrubber`] to `scrubbers`, run on every written line after `$remote_addr`
+    /// replacement and after all scrubbers added before
+    ///
+    /// Lines are not scrubbed if dropped, skipped or oversized.
+    pub fn push_scrubber(&mut self, scrubber: impl LineScrubber + 'a) {
+        self.scrubbers.push(Arc::new(scrubber));
+    }
+
+    /// Set `cancel` field, [`run`] and [`run_raw`] stop with [`Error::Cancelled`] before the
+    /// next line once `cancel` is cancelled
+    ///
+    /// Output written so far is flushed, no line is written partially. In follow and listen mode
+    /// the cancellation takes effect with the next line received.
+    ///
+    /// [`CancellationToken::interrupt`] stops reading input instead, lines already read are
+    /// written and [`run`] returns [`Error::Interrupted`]. The library does not install any
+    /// signal handlers, programs can interrupt the token from their own SIGINT / SIGTERM handler.
+    pub fn set_cancel(&mut self, cancel: CancellationToken) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Returns `true` if [`Config::cancel`] was cancelled
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Returns `true` if [`Config::cancel`] was interrupted
+    fn is_interrupted(&self) -> bool {
+        signal::interrupted(self.cancel.as_ref())
+    }
+
+    /// Passes an event to the logger, if any
+    fn log(&self, level: Level, message: fmt::Arguments<'_>) {
+        if let Some(logger) = self.logger.filter(|l| l.enabled(level)) {
+            logger.log(level, message);
+        }
+    }
+}
+
+impl<'a> IOConfig<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    /// Get inputs, if any (defaults to `None`)
+    pub fn get_input(&self) -> Option<&Vec<Input<'a>>> {
+        self.input.as_ref()
+    }
+
+    #[must_use]
+    /// Get (first) output / writer name (defaults to `None`)
+    pub fn get_output(&self) -> Option<&Path> {
+        self.output.first().map(AsRef::as_ref)
+    }
+
+    #[must_use]
+    /// Get all output / writer names (defaults to an empty slice)
+    pub fn get_outputs(&self) -> &[Cow<'a, Path>] {
+        &self.output
+    }
+
+    #[must_use]
+    /// Get `tee` value (defaults to `false`)
+    pub fn get_tee(&self) -> bool {
+        self.tee
+    }
+
+    #[must_use]
+    /// Get output compression (defaults to `Compression::None`)
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+
+    #[must_use]
+    /// Get `in_place` value (defaults to `false`)
+    pub fn get_in_place(&self) -> bool {
+        self.in_place
+    }
+
+    #[must_use]
+    /// Get backup suffix for files edited in place (defaults to `None`)
+    pub fn get_backup_suffix(&self) -> Option<&str> {
+        self.backup_suffix.as_deref()
+    }
+
+    #[must_use]
+    /// Get output directory (defaults to `None`)
+    pub fn get_output_dir(&self) -> Option<&Path> {
+        self.output_dir.as_deref()
+    }
+
+    #[must_use]
+    /// Get input / output pairs (defaults to an empty slice)
+    pub fn get_pairs(&self) -> &[(Cow<'a, Path>, Cow<'a, Path>)] {
+        &self.pairs
+    }
+
+    #[must_use]
+    /// Get output mode (defaults to `OutputMode::Append`)
+    pub fn get_output_mode(&self) -> OutputMode {
+        self.output_mode
+    }
+
+    #[must_use]
+    /// Get `follow` value (defaults to `false`)
+    pub fn get_follow(&self) -> bool {
+        self.follow
+    }
+
+    #[must_use]
+    /// Get `watch` value (defaults to `false`)
+    pub fn get_watch(&self) -> bool {
+        self.watch
+    }
+
+    #[must_use]
+    /// Get `reopen_fifo` value (defaults to `false`)
+    pub fn get_reopen_fifo(&self) -> bool {
+        self.reopen_fifo
+    }
+
+    #[must_use]
+    /// Get listen address (defaults to `None`)
+    pub fn get_listen(&self) -> Option<&str> {
+        self.listen.as_deref()
+    }
+
+    #[must_use]
+    /// Get `journal` value (defaults to `false`)
+    pub fn get_journal(&self) -> bool {
+        self.journal
+    }
+
+    #[must_use]
+    /// Get journal fields to anonymize (defaults to empty, i.e. `MESSAGE`)
+    pub fn get_journal_fields(&self) -> &[Cow<'a, str>] {
+        &self.journal_fields
+    }
+
+    #[must_use]
+    /// Get journal matches (defaults to empty)
+    pub fn get_journal_matches(&self) -> &[Cow<'a, str>] {
+        &self.journal_matches
+    }
+
+    #[must_use]
+    /// Get `preserve` value (defaults to `false`)
+    pub fn get_preserve(&self) -> bool {
+        self.preserve
+    }
+
+    #[must_use]
+    /// Get output rotation size in bytes (defaults to `None`)
+    pub fn get_rotate_size(&self) -> Option<u64> {
+        self.rotate_size
+    }
+
+    #[must_use]
+    /// Get time-based output rotation interval (defaults to `None`)
+    pub fn get_rotate_time(&self) -> Option<Interval> {
+        self.rotate_time
+    }
+
+    #[must_use]
+    /// Get rotated output file name pattern (defaults to `"{path}.{n}"`)
+    pub fn get_rotate_name(&self) -> &str {
+        &self.rotate_name
+    }
+
+    #[must_use]
+    /// Get checkpoint file (defaults to `None`)
+    pub fn get_resume(&self) -> Option<&Path> {
+        self.resume.as_deref()
+    }
+
+    #[must_use]
+    /// Get `mmap` value (defaults to `false`)
+    pub fn get_mmap(&self) -> bool {
+        self.mmap
+    }
+
+    #[must_use]
+    /// Get dry run mode (defaults to `None`)
+    pub fn get_dry_run(&self) -> Option<DryRun> {
+        self.dry_run
+    }
+
+    #[must_use]
+    /// Get `check` value (defaults to `false`)
+    pub fn get_check(&self) -> bool {
+        self.check
+    }
+
+    #[must_use]
+    /// Get `keep_going` value (defaults to `false`)
+    pub fn get_keep_going(&self) -> bool {
+        self.keep_going
+    }
+
+    #[must_use]
+    /// Get maximum number of lines read (defaults to `None`)
+    pub fn get_max_lines(&self) -> Option<u64> {
+        self.max_lines
+    }
+
+    #[must_use]
+    /// Get maximum number of bytes read (defaults to `None`)
+    pub fn get_max_bytes(&self) -> Option<u64> {
+        self.max_bytes
+    }
+
+    #[must_use]
+    /// Get progress report interval (defaults to `None`)
+    pub fn get_progress_interval(&self) -> Option<Duration> {
+        self.progress_interval
+    }
+
+    #[must_use]
+    /// Get `progress_bar` value (defaults to `false`)
+    pub fn get_progress_bar(&self) -> bool {
+        self.progress_bar
+    }
+
+    /// Add input `Path`
+    pub fn push_input<P: AsRef<Path> + ?Sized>(&mut self, i: &'a P) {
+        self.input
+            .get_or_insert_with(Vec::new)
+            .push(Input::Path(Cow::Borrowed(i.as_ref())));
+    }
+
+    /// Add input `Path` owned by this config, e.g. a `PathBuf` or `OsString`
+    pub fn push_input_owned(&mut self, i: impl Into<PathBuf>) {
+        self.input
+            .get_or_insert_with(Vec::new)
+            .push(Input::Path(Cow::Owned(i.into())));
+    }
+
+    /// Add input `Path` anonymized to its own output `Path`, see [`run`]
+    ///
+    /// The input is added to the inputs as well, all inputs need an output once a pair was
+    /// added.
+    pub fn add_pair(&mut self, input: impl Into<Cow<'a, Path>>, output: impl Into<Cow<'a, Path>>) {
+        let input = input.into();
+        self.input
+            .get_or_insert_with(Vec::new)
+            .push(Input::Path(input.clone()));
+        self.pairs.push((input, output.into()));
+    }
+
+    /// Add Stdin as input, e.g. between two input files
+    pub fn push_stdin(&mut self) {
+        self.input.get_or_insert_with(Vec::new).push(Input::Stdin);
+    }
+
+    /// Add `reader` as input, e.g. a `TcpStream` wrapped in a `BufReader`
+    ///
+    /// `name` is used instead of a path in log messages, errors and [`Summary::files`]. The
+    /// reader is read by the first [`run`] using this config only.
+    pub fn push_reader(&mut self, name: &str, reader: impl BufRead + Send + 'static) {
+        self.input
+            .get_or_insert_with(Vec::new)
+            .push(Input::Reader(InputReader::new(name, reader)));
+    }
+
+    /// Set checkpoint file `Path`, input files are resumed where a previous run stopped
+    pub fn set_resume(&mut self, r: impl Into<Cow<'a, Path>>) {
+        self.resume = Some(r.into());
+    }
+
+    /// Set `mmap` field, memory map uncompressed regular input files on Unix
+    ///
+    /// An input file truncated while being processed terminates the process (`SIGBUS`) instead
+    /// of returning an error.
+    pub fn set_mmap(&mut self, b: bool) {
+        self.mmap = b;
+    }
+
+    /// Set dry run mode, [`run`] prints a preview of the changes to Stdout and writes no output
+    /// files (neither in place nor to an output directory)
+    pub fn set_dry_run(&mut self, mode: DryRun) {
+        self.dry_run = Some(mode);
+    }
+
+    /// Set `check` field, [`run`] reports IP and email addresses left in the inputs to Stdout
+    /// instead of anonymizing them, see [`check_raw`]
+    ///
+    /// If anything was found [`run`] returns an error.
+    pub fn set_check(&mut self, b: bool) {
+        self.check = b;
+    }
+
+    /// Set `keep_going` field, [`run`] skips inputs which can not be opened or anonymized,
+    /// reporting them as warnings to the logger, see [`Config::set_logger`]
+    ///
+    /// If any input failed [`run`] returns an error once all other inputs are done. Output
+    /// already written for a failed input is kept.
+    pub fn set_keep_going(&mut self, b: bool) {
+        self.keep_going = b;
+    }
+
+    /// Set maximum number of lines [`run`] reads, counted over all inputs, e.g. to try a
+    /// configuration on the head of a huge file
+    ///
+    /// Applies to dry runs and checks as well, but can not be combined with in-place editing or
+    /// an output directory.
+    pub fn set_max_lines(&mut self, n: u64) {
+        self.max_lines = Some(n);
+    }
+
+    /// Set maximum number of bytes [`run`] reads, counted over all inputs, see
+    /// [`IOConfig::set_max_lines`]
+    ///
+    /// The line in which the limit is reached is still read completely.
+    pub fn set_max_bytes(&mut self, n: u64) {
+        self.max_bytes = Some(n);
+    }
+
+    /// Set progress report interval, bytes and lines read per second are printed to stderr
+    /// every interval and in a summary at the end of [`run`]
+    pub fn set_progress_interval(&mut self, i: Duration) {
+        self.progress_interval = Some(i);
+    }
+
+    /// Set `progress_bar` field, a progress bar with ETA is drawn on stderr if all inputs are
+    /// regular files
+    ///
+    /// The progress bar replaces the periodic reports of [`IOConfig::set_progress_interval`].
+    pub fn set_progress_bar(&mut self, b: bool) {
+        self.progress_bar = b;
+    }
+
+    /// Set output `Path`, replacing any other outputs
+    pub fn set_output(&mut self, o: impl Into<Cow<'a, Path>>) {
+        self.output = vec![o.into()];
+    }
+
+    /// Add output `Path`, every output receives the same data
+    pub fn push_output(&mut self, o: impl Into<Cow<'a, Path>>) {
+        self.output.push(o.into());
+    }
+
+    /// Set output writer, it receives the same data as any output files instead of Stdout
+    ///
+    /// The writer is flushed and dropped at the end of the first [`run`] using this config.
+    pub fn set_writer(&mut self, writer: impl Write + Send + 'static) {
+        self.writer = Some(stream::OutputWriter::new(writer));
+    }
+
+    /// Set `tee` field, write to Stdout in addition to any output files
+    pub fn set_tee(&mut self, b: bool) {
+        self.tee = b;
+    }
+
+    /// Set output compression
+    pub fn set_compression(&mut self, c: Compression) {
+        self.compression = c;
+    }
+
+    /// Set `in_place` field
+    pub fn set_in_place(&mut self, b: bool) {
+        self.in_place = b;
+    }
+
+    /// Set backup suffix, files edited in place are kept as `<file><suffix>`, e.g. `.bak`
+    pub fn set_backup_suffix(&mut self, suffix: impl Into<Cow<'a, str>>) {
+        self.backup_suffix = Some(suffix.into());
+    }
+
+    /// Set output directory `Path`
+    pub fn set_output_dir(&mut self, d: impl Into<Cow<'a, Path>>) {
+        self.output_dir = Some(d.into());
+    }
+
+    /// Set output mode
+    pub fn set_output_mode(&mut self, m: OutputMode) {
+        self.output_mode = m;
+    }
+
+    /// Set `follow` field
+    pub fn set_follow(&mut self, b: bool) {
+        self.follow = b;
+    }
+
+    /// Set `watch` field
+    pub fn set_watch(&mut self, b: bool) {
+        self.watch = b;
+    }
+
+    /// Set `reopen_fifo` field
+    pub fn set_reopen_fifo(&mut self, b: bool) {
+        self.reopen_fifo = b;
+    }
+
+    /// Set listen address, `udp://<addr>:<port>`, `tcp://<addr>:<port>` or on Unix
+    /// `unixgram://<path>` and `unix://<path>`
+    pub fn set_listen(&mut self, addr: impl Into<Cow<'a, str>>) {
+        self.listen = Some(addr.into());
+    }
+
+    /// Set `journal` field, [`run`] reads the systemd journal instead of input files (requires
+    /// feature `journald`)
+    ///
+    /// Entries are read with `journalctl --output=export` and written in the same format, e.g.
+    /// for `systemd-journal-remote`, with the fields of [`IOConfig::push_journal_field`]
+    /// anonymized. Unless [`Config::format`] is [`Format::Sshd`] or [`Format::Mail`] the format of
+    /// `MESSAGE` is chosen by the `SYSLOG_IDENTIFIER` of every entry: web servers (`nginx`, `apache2`, `httpd`,
+    /// `varnishncsa`) keep the configured format, `dovecot` and `exim` use [`Format::Mail`] and
+    /// any other free text message [`Format::Sshd`], replacing every IP address and the address
+    /// following `from`. With [`IOConfig::set_follow`] new entries are read as they arrive, with
+    /// [`IOConfig::set_resume`] the journal cursor is recorded and the next run starts after it.
+    pub fn set_journal(&mut self, b: bool) {
+        self.journal = b;
+    }
+
+    /// Add a journal field to anonymize, e.g. `SYSLOG_IDENTIFIER`, replaces the default
+    /// `MESSAGE`
+    pub fn push_journal_field(&mut self, field: impl Into<Cow<'a, str>>) {
+        self.journal_fields.push(field.into());
+    }
+
+    /// Add a journal match, e.g. `_SYSTEMD_UNIT=nginx.service`, see `journalctl(1)`
+    pub fn push_journal_match(&mut self, m: impl Into<Cow<'a, str>>) {
+        self.journal_matches.push(m.into());
+    }
+
+    /// Set `preserve` field
+    pub fn set_preserve(&mut self, b: bool) {
+        self.preserve = b;
+    }
+
+    /// Set output rotation size in bytes
+    pub fn set_rotate_size(&mut self, size: u64) {
+        self.rotate_size = Some(size);
+    }
+
+    /// Set time-based output rotation interval
+    ///
+    /// `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%s` in the output path are replaced with the
+    /// (UTC) start time of the current file, e.g. `access-%Y-%m-%d.log`.
+    pub fn set_rotate_time(&mut self, i: Interval) {
+        self.rotate_time = Some(i);
+    }
+
+    /// Set rotated output file name pattern, `{path}` is replaced with the output path, `{n}`
+    /// with the lowest unused number starting at `1`
+    pub fn set_rotate_name(&mut self, pattern: impl Into<Cow<'a, str>>) {
+        self.rotate_name = pattern.into();
+    }
+}
+
+/// Anonymizes all lines of `reader` to `writer`, see [`process()`]
+fn replace_remote_address<R: BufRead, W: Write>(
+    config: &Config,
+    mut reader: R,
+    mut writer: W,
+) -> Result<(), io::Error> {
+    if config.get_jobs() > 1 && !config.get_flush() && config.get_max_line_len().is_none() {
+        config.log(
+            Level::Debug,
+            format_args!("processing lines with {} threads", config.get_jobs()),
+        );
+        return parallel::replace(config, reader, writer);
+    }
+    if let Some(size) = config.get_block_size() {
+        config.log(
+            Level::Debug,
+            format_args!("processing lines in blocks of {size} bytes"),
+        );
+        return block::replace(config, reader, writer);
+    }
+
+    let mut buf = vec![];
+    let mut scratch = vec![];
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let max = config
+        .get_max_line_len()
+        .map_or(u64::MAX, |m| m.max(1) as u64);
+    let mut line_no = 0;
+
+    loop {
+        buf.clear();
+        let bytes_read = reader.by_ref().take(max).read_until(delimiter, &mut buf)?;
         if bytes_read == 0 {
             break;
         }
+        line_no += 1;
+
+        if bytes_read as u64 == max && buf.last() != Some(&delimiter) {
+            oversized(config, &buf, &mut writer)?;
+            // copy or discard the remainder of the line, at most `max` bytes at a time
+            loop {
+                buf.clear();
+                let n = reader.by_ref().take(max).read_until(delimiter, &mut buf)?;
+                if let Some(stats) = config.get_stats() {
+                    stats.read(buf.len());
+                }
+                if config.get_oversized() == Oversized::Pass {
+                    writer.write_all(&buf)?;
+                    if let Some(stats) = config.get_stats() {
+                        stats.written(buf.len());
+                    }
+                }
+                if n == 0 || buf.last() == Some(&delimiter) {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        replace_record(config, &buf, &mut scratch, &mut writer)
+            .map_err(|e| Malformed::shift(e, line_no))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Handles the start of a line longer than [`Config::max_line_len`] according to
+/// [`Config::oversized`]
+fn oversized<W: Write>(config: &Config, buf: &[u8], writer: &mut W) -> io::Result<()> {
+    let max = config.get_max_line_len().unwrap_or_default();
+    match config.get_oversized() {
+        Oversized::Pass => config.log(
+            Level::Warn,
+            format_args!("line exceeds maximum length of {max} bytes, written as is"),
+        ),
+        Oversized::Skip => config.log(
+            Level::Warn,
+            format_args!("line exceeds maximum length of {max} bytes, skipped"),
+        ),
+        Oversized::Error => (),
+    }
+    match (config.get_oversized(), config.get_stats()) {
+        (Oversized::Pass, Some(stats)) => stats.line(buf, buf),
+        (Oversized::Skip, Some(stats)) => stats.skip(buf),
+        _ => (),
+    }
+    match config.get_oversized() {
+        Oversized::Pass => observe(config, buf, Outcome::Written, false),
+        Oversized::Skip => observe(config, buf, Outcome::Skipped, false),
+        Oversized::Error => (),
+    }
+
+    match config.get_oversized() {
+        Oversized::Pass => writer.write_all(buf),
+        Oversized::Skip => Ok(()),
+        Oversized::Error => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line exceeds maximum length of {max} bytes"),
+        )),
+    }
+}
+
+/// Anonymizes a single line `buf` (including its terminator) and writes it to `writer`, see
+/// [`replace_remote_address`]
+///
+/// `scratch` is used for lines which have to be modified before they can be processed.
+fn replace_record<'b, W: Write>(
+    config: &Config,
+    mut buf: &'b [u8],
+    scratch: &'b mut Vec<u8>,
+    writer: &mut W,
+) -> io::Result<()> {
+    let record = buf;
+    if config.is_cancelled() {
+        return Err(io::Error::other("Cancelled"));
+    }
+
+    // A byte order mark (e.g. at the start of files exported on Windows) is not part of the
+    // first word, it is written back in front of the line unless stripped
+    let bom: &[u8] = if buf.starts_with(BOM) {
+        buf = &buf[BOM.len()..];
+        if config.get_strip_bom() {
+            b""
+        } else {
+            BOM
+        }
+    } else {
+        b""
+    };
+
+    if is_dropped(config, buf) {
+        if let Some(stats) = config.get_stats() {
+            stats.drop_line(record);
+        }
+        observe(config, record, Outcome::Dropped, false);
+        config.log(Level::Debug, format_args!("line dropped"));
+        return Ok(());
+    }
+
+    if config.get_trim() {
+        let s = skip_whitespace(buf);
+        buf = if s < buf.len() { &buf[s..] } else { buf };
+    }
+
+    if is_comment(config, buf) {
+        let line = match record.strip_prefix(BOM) {
+            Some(line) if bom.is_empty() => line,
+            _ => record,
+        };
+        if let Some(stats) = config.get_stats() {
+            stats.line(record, line);
+        }
+        observe(config, record, Outcome::Written, *line != *record);
+        writer.write_all(line)?;
+        if config.get_flush() {
+            writer.flush()?;
+        }
+        return Ok(());
+    }
+
+    // Lines terminated by "\r\n" (or NUL) are handled like lines terminated by "\n", the
+    // original terminator is restored on output
+    let terminator: &[u8] = match buf {
+        [.., b'\0'] if config.get_null_data() => b"\0",
+        [.., b'\r', b'\n'] if !config.get_null_data() => b"\r\n",
+        _ => b"",
+    };
+    if !terminator.is_empty() {
+        scratch.clear();
+        scratch.extend_from_slice(&buf[..buf.len() - terminator.len()]);
+        scratch.push(b'\n');
+        buf = scratch;
+    }
+
+    let envelope = if config.get_syslog() {
+        format::syslog_envelope(buf, config.get_trim())
+    } else {
+        0
+    };
+    if envelope == 0 && config.get_syslog() && config.get_strict() {
+        return reject(config, record, Malformed::error("missing syslog header"));
+    }
+
+    let stats = config.get_stats();
+    let full_stats = stats.is_some_and(Stats::is_full);
+    let scrub = !config.get_scrubbers().is_empty();
+    let observed = config.get_observer().is_some();
+    let buffered = envelope > 0 || !bom.is_empty() || !terminator.is_empty();
+    let written = if buffered || full_stats || scrub || observed {
+        let mut line = buf[..envelope].to_vec();
+        let written = match replace_line(config, &buf[envelope..], &mut line) {
+            Ok(written) => written,
+            Err(e) => return reject(config, record, e),
+        };
+        if written && scrub && !scrub_line(config, record, &mut line) {
+            if let Some(stats) = stats {
+                stats.drop_line(record);
+            }
+            observe(config, record, Outcome::Dropped, false);
+            config.log(Level::Debug, format_args!("line dropped"));
+            return Ok(());
+        }
+        if !terminator.is_empty() && line.last() == Some(&b'\n') {
+            line.pop();
+            line.extend_from_slice(terminator);
+        }
+        line.splice(..0, bom.iter().copied());
+        match (written, stats) {
+            (true, Some(stats)) => stats.line(record, &line),
+            (false, Some(stats)) => stats.skip(record),
+            _ => (),
+        }
+        if written {
+            observe(config, record, Outcome::Written, *record != *line);
+        } else {
+            observe(config, record, Outcome::Skipped, false);
+        }
+        if written {
+            writer.write_all(&line)?;
+        }
+        written
+    } else {
+        let written = match replace_line(config, buf, &mut *writer) {
+            Ok(written) => written,
+            Err(e) => return reject(config, record, e),
+        };
+        if let Some(stats) = stats.filter(|_| !written) {
+            stats.skip(record);
+        }
+        written
+    };
+
+    if !written {
+        if let Some(rejects) = config.get_rejects() {
+            rejects.write(record)?;
+        }
+        config.log(Level::Debug, format_args!("line skipped"));
+    }
+    if written && config.get_flush() {
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Writes `record` to [`Config::rejects`] if `error` is a malformed line found by
+/// [`Config::strict`], the line counts as skipped. Without rejects or for other errors `error`
+/// is returned.
+fn reject(config: &Config, record: &[u8], error: io::Error) -> io::Result<()> {
+    let Some(rejects) = config.get_rejects().filter(|_| Malformed::is(&error)) else {
+        return Err(error);
+    };
+    rejects.write(record)?;
+    if let Some(stats) = config.get_stats() {
+        stats.skip(record);
+    }
+    observe(config, record, Outcome::Skipped, false);
+    config.log(Level::Debug, format_args!("line rejected: {error}"));
+    Ok(())
+}
+
+/// UTF-8 byte order mark
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Returns `true` if `line` starts with any of [`Config::comment_prefixes`]
+fn is_comment(config: &Config, line: &[u8]) -> bool {
+    config
+        .get_comment_prefixes()
+        .iter()
+        .any(|prefix| !prefix.is_empty() && line.starts_with(prefix.as_bytes()))
+}
+
+/// Runs all [`Config::scrubbers`] on the anonymized `line`, `record` is the original input
+///
+/// Returns `false` if a scrubber dropped the line.
+fn scrub_line(config: &Config, record: &[u8], line: &mut Vec<u8>) -> bool {
+    let newline = line.last() == Some(&b'\n');
+    if newline {
+        line.pop();
+    }
+    let ctx = LineCtx {
+        original: record,
+        format: config.get_format(),
+    };
+    for scrubber in config.get_scrubbers() {
+        if !scrubber.process(line, &ctx) {
+            return false;
+        }
+    }
+    if newline {
+        line.push(b'\n');
+    }
+    true
+}
+
+/// Reports `record` to [`Config::observer`], if any
+fn observe(config: &Config, record: &[u8], outcome: Outcome, modified: bool) {
+    let Some(observer) = config.get_observer() else {
+        return;
+    };
+
+    let mut word = record;
+    if config.get_trim() {
+        word = &word[skip_whitespace(word)..];
+    }
+    if config.get_syslog() {
+        word = &word[format::syslog_envelope(word, config.get_trim())..];
+    }
+    if matches!(config.get_format(), Format::Combined | Format::Varnish) {
+        word = &word[prefix_len(config, word)..];
+    }
+    word = &word[..find_separator(config, word).unwrap_or(word.len())];
+
+    let replaced = match config.get_format() {
+        Format::Combined | Format::Varnish if modified => {
+            let addr = word.split(|&b| b == b',').next().unwrap_or(word);
+            let (repl, kind) = replacement_kind(config, &addr[..zone_start(config, addr)]);
+            (repl != addr).then_some(kind)
+        }
+        _ => None,
+    };
+
+    observer.observe(&LineInfo {
+        original: record,
+        outcome,
+        modified,
+        replaced,
+        word_len: word.len(),
+    });
+}
+
+/// Returns `true` if `record` (including its terminator) matches any of
+/// [`Config::drop_patterns`] or none of [`Config::match_patterns`]
+#[cfg(feature = "regex")]
+fn is_dropped(config: &Config, record: &[u8]) -> bool {
+    let (drop, keep) = (config.get_drop_patterns(), config.get_match_patterns());
+    if drop.is_empty() && keep.is_empty() {
+        return false;
+    }
+
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let line = record.strip_suffix(&[delimiter]).unwrap_or(record);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    drop.iter().any(|p| p.is_match(line))
+        || (!keep.is_empty() && !keep.iter().any(|p| p.is_match(line)))
+}
+
+/// Without feature `regex` there are no patterns, no line is dropped
+#[cfg(not(feature = "regex"))]
+fn is_dropped(_: &Config, _: &[u8]) -> bool {
+    false
+}
+
+/// Compiles a user supplied pattern, without Unicode support to match arbitrary bytes
+#[cfg(feature = "regex")]
+fn compile_pattern(pattern: &str) -> Result<Regex, Error> {
+    regex::bytes::RegexBuilder::new(pattern)
+        .unicode(false)
+        .build()
+        .map_err(|e| Error::Pattern {
+            pattern: pattern.to_string(),
+            message: e.to_string(),
+        })
+}
+
+/// (De)serializes compiled patterns as their source strings
+#[cfg(all(feature = "serde", feature = "regex"))]
+mod patterns {
+    use regex::bytes::Regex;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::Serializer;
+
+    pub(crate) fn serialize<S: Serializer>(
+        patterns: &[Regex],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(patterns.iter().map(Regex::as_str))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Regex>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|p| super::compile_pattern(p).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Replaces all parts of `line` selected by [`Config::format`] and writes the line to `writer`
+///
+/// Returns `false` if nothing was written.
+fn replace_line<W: Write>(config: &Config, line: &[u8], mut writer: W) -> io::Result<bool> {
+    match config.get_format() {
+        Format::Combined | Format::Varnish => replace_first_word(config, line, writer),
+        Format::Sshd => {
+            format::replace_sshd(config, line, &mut writer)?;
+            Ok(true)
+        }
+        Format::Mail => {
+            format::replace_mail(config, line, &mut writer)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Replaces the first *word* of `buf` and writes the line to `writer`
+///
+/// With [`Format::Varnish`] the first *word* can be a list of addresses separated by `", "`.
+///
+/// Returns `false` if the line was skipped or had no first *word* at all. The first *word* of a
+/// last line without terminator can end at the end of `buf`.
+fn replace_first_word<W: Write>(config: &Config, buf: &[u8], mut writer: W) -> io::Result<bool> {
+    if buf.is_empty() {
+        return Ok(false);
+    }
+    let (prefix, buf) = buf.split_at(prefix_len(config, buf));
+    let mut i = find_separator(config, buf).unwrap_or(buf.len());
+    if i == 0 && config.get_strict() {
+        return Err(Malformed::error("missing first word"));
+    }
+    if i == 0 && config.get_skip() {
+        return Ok(false);
+    }
+    // Lines with no more than `skip_fields` fields have nothing to replace
+    if i == 0 && !prefix.is_empty() {
+        writer.write_all(prefix)?;
+        writer.write_all(buf)?;
+        return Ok(true);
+    }
+
+    let is_varnish = config.get_format() == Format::Varnish;
+    if is_varnish {
+        i = list_end(config, buf, i);
+    }
+    if config.get_strict() {
+        if let Some(reason) = malformed(config, buf, i) {
+            return Err(Malformed::error(reason));
+        }
+    }
+    writer.write_all(prefix)?;
+
+    // Lines not starting with an address are not access log lines, keep them unchanged
+    if config.get_ip_only()
+        && !buf[..i]
+            .split(|&b| is_varnish && b == b',')
+            .any(|addr| address_kind(&addr[skip_whitespace(addr)..]) != Address::Host)
+    {
+        writer.write_all(buf)?;
+        return Ok(true);
+    }
+
+    let is_authuser = config.get_authuser();
+    let is_thorough = config.get_thorough();
+
+    // Common case: at most two slices per line, the original line if nothing changes
+    if !is_varnish && !is_authuser && !is_thorough {
+        let end = zone_start(config, &buf[..i]);
+        let repl = replacement(config, &buf[..end]);
+        if repl == &buf[..end] {
+            writer.write_all(buf)?;
+        } else {
+            writer.write_all(repl)?;
+            writer.write_all(&buf[end..])?;
+        }
+        return Ok(true);
+    }
+
+    // Needles are only collected if they will be searched for, the common path allocates nothing
+    let mut needles = vec![];
+    for (n, addr) in buf[..i].split(|&b| is_varnish && b == b',').enumerate() {
+        let start = skip_whitespace(addr);
+        let end = start + zone_start(config, &addr[start..]);
+        let needle = &addr[start..end];
+        let repl = replacement(config, needle);
+
+        if n > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(&addr[..start])?;
+        writer.write_all(repl)?;
+        writer.write_all(&addr[end..])?;
+
+        if is_thorough {
+            needles.push((needle, repl));
+        }
+    }
+
+    let is_optimized = config.get_optimize() && buf.len() >= i + 6;
+
+    if is_authuser {
+        if is_optimized && buf[i + 3..i + 6].iter().cmp(b"- [") == Ordering::Equal {
+            write_or_replace(&buf[i..], &needles, is_thorough, &mut writer)?;
+        } else if let Some(time_field) = find_time_local(buf, i) {
+            writer.write_all(b" - -")?;
+            let user = remote_user(&buf[i..time_field]);
+            if let Some(stats) = config.get_stats() {
+                stats.clear_authuser(user);
+            }
+            if is_thorough && !user.is_empty() && user != b"-" {
+                needles.push((user, b"-"));
+            }
+            write_or_replace(&buf[time_field..], &needles, is_thorough, &mut writer)?;
+        } else {
+            write_or_replace(&buf[i..], &needles, is_thorough, &mut writer)?;
+        }
+    } else {
+        write_or_replace(&buf[i..], &needles, is_thorough, &mut writer)?;
+    }
+
+    Ok(true)
+}
+
+/// Returns the `$remote_user` in `fields`, the part of a line between `$remote_addr` and
+/// `$time_local` (following the `$remote_ident`)
+fn remote_user(fields: &[u8]) -> &[u8] {
+    let fields = &fields[skip_whitespace(fields)..];
+    let Some(end) = find_whitespace(fields) else {
+        return b"";
+    };
+    &fields[end + skip_whitespace(&fields[end..])..]
+}
+
+/// Returns the index of the first `" [D/"` or `" [DD/"` (`D` being an ASCII digit) in `buf`
+/// starting at `start`, the beginning of the `$time_local` field
+///
+/// `$remote_user` *can* contain whitespace, so the field following it is searched for instead.
+fn find_time_local(buf: &[u8], start: usize) -> Option<usize> {
+    memchr::memmem::find_iter(&buf[start..], b" [")
+        .map(|i| start + i)
+        .find(|&i| match &buf[i + 2..] {
+            [d, b'/', ..] => d.is_ascii_digit(),
+            [d, e, b'/', ..] => d.is_ascii_digit() && e.is_ascii_digit(),
+            _ => false,
+        })
+}
+
+/// Returns the length of the first [`Config::skip_fields`] fields of `buf` including the
+/// separators after them
+fn prefix_len(config: &Config, buf: &[u8]) -> usize {
+    let mut len = 0;
+    for _ in 0..config.get_skip_fields() {
+        let rest = &buf[len..];
+        let end = find_separator(config, rest).unwrap_or(rest.len());
+        let separators = match config.get_separators() {
+            Some(_) => usize::from(rest.get(end).is_some_and(|&b| b != b'\n')),
+            None => rest[end..]
+                .iter()
+                .position(|&b| b != b' ' && b != b'\t')
+                .unwrap_or(rest.len() - end),
+        };
+        len += end + separators;
+    }
+    len
+}
+
+/// Returns the end of a list of addresses separated by `", "` (e.g. `X-Forwarded-For` headers)
+/// starting at the beginning of `buf`, `end` being the end of the first *word*
+fn list_end(config: &Config, buf: &[u8], mut end: usize) -> usize {
+    while end > 0 && buf[end - 1] == b',' {
+        let start = skip_whitespace(&buf[end..]);
+        if start == buf.len() - end {
+            break;
+        }
+        end += start;
+        end += find_separator(config, &buf[end..]).unwrap_or(buf.len() - end);
+    }
+
+    end
+}
+
+/// Returns the index of the first of [`Config::separators`] or the line terminator in `buf`,
+/// the first ASCII whitespace character if no separators are set
+fn find_separator(config: &Config, buf: &[u8]) -> Option<usize> {
+    match config.get_separators().map(str::as_bytes) {
+        None => find_whitespace(buf),
+        Some(&[separator]) => memchr::memchr2(separator, b'\n', buf),
+        Some(separators) => buf
+            .iter()
+            .position(|b| *b == b'\n' || separators.contains(b)),
+    }
+}
+
+/// Returns the index of the first ASCII whitespace character in `buf`
+///
+/// Space, tab and newline are searched for with `memchr`, only the bytes before the first match
+/// are scanned again for the rare carriage return and form feed.
+fn find_whitespace(buf: &[u8]) -> Option<usize> {
+    let end = memchr::memchr3(b' ', b'\t', b'\n', buf);
+    memchr::memchr2(b'\r', b'\x0C', &buf[..end.unwrap_or(buf.len())]).or(end)
+}
+
+/// Returns the index of the first character in `buf` that is not ASCII whitespace, `buf.len()`
+/// if there is none
+///
+/// Most lines do not start with whitespace, so the first byte is checked before scanning.
+pub(crate) fn skip_whitespace(buf: &[u8]) -> usize {
+    match buf.first() {
+        Some(b) if !b.is_ascii_whitespace() => 0,
+        _ => buf
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(buf.len()),
+    }
+}
+
+/// Returns the replacement for any `$remote_addr`, `addr` itself if it is kept
+///
+/// `addr` is parsed without any allocation, anything not valid UTF-8 is a *host*.
+fn replacement<'a>(config: &'a Config, addr: &'a [u8]) -> &'a [u8] {
+    let (repl, kind) = replacement_kind(config, addr);
+
+    if let Some(stats) = config.get_stats().filter(|_| repl != addr) {
+        stats.address(kind, addr);
+    }
+    repl
+}
+
+/// Returns the replacement for `addr` like [`replacement`] along with the kind of `addr`,
+/// without counting it
+fn replacement_kind<'a>(config: &'a Config, addr: &'a [u8]) -> (&'a [u8], Address) {
+    let kind = address_kind(addr);
+    let repl = match kind {
+        Address::Host
+            if config.get_ip_only()
+                || (config.get_keep_dash() && addr == b"-")
+                || (config.get_verify_hosts() && !is_hostname(addr)) =>
+        {
+            addr
+        }
+        kind => replacement_value(config, kind).as_bytes(),
+    };
+    (repl, kind)
+}
+
+/// Returns the kind of `$remote_addr`, anything not valid UTF-8 is a *host*
+fn address_kind(addr: &[u8]) -> Address {
+    match str::from_utf8(addr) {
+        Ok(s) if s.parse::<net::Ipv4Addr>().is_ok() => Address::Ipv4,
+        Ok(s) if s.parse::<net::Ipv6Addr>().is_ok() || is_scoped_ipv6(s) => Address::Ipv6,
+        _ => Address::Host,
+    }
+}
+
+/// Returns `true` if `name` is a valid host name according to RFC 1123
+fn is_hostname(name: &[u8]) -> bool {
+    let name = name.strip_suffix(b".").unwrap_or(name);
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split(|&b| b == b'.').all(|label| {
+            (1..=63).contains(&label.len())
+                && label.first() != Some(&b'-')
+                && label.last() != Some(&b'-')
+                && label
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || *b == b'-')
+        })
+}
+
+/// Returns `true` for an IPv6 address with a zone ID, e.g. `fe80::1%eth0`
+fn is_scoped_ipv6(s: &str) -> bool {
+    s.split_once('%').is_some_and(|(addr, zone)| {
+        !zone.is_empty()
+            && zone
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._".contains(&b))
+            && addr.parse::<net::Ipv6Addr>().is_ok()
+    })
+}
+
+/// Returns the length of `addr` without the zone ID of a scoped IPv6 address if
+/// [`Config::keep_zone`] is set, the zone is written unchanged like the rest of the line
+pub(crate) fn zone_start(config: &Config, addr: &[u8]) -> usize {
+    if config.get_keep_zone() && address_kind(addr) == Address::Ipv6 {
+        memchr::memchr(b'%', addr).unwrap_or(addr.len())
+    } else {
+        addr.len()
+    }
+}
+
+/// Returns the replacement string for addresses of `kind`
+fn replacement_value<'c>(config: &'c Config, kind: Address) -> &'c str {
+    match kind {
+        Address::Ipv4 => config.get_ipv4_value(),
+        Address::Ipv6 => config.get_ipv6_value(),
+        Address::Host => config.get_host_value(),
+    }
+}
+
+/// Returns why `buf` with the first word ending at `end` is rejected by [`Config::strict`]
+fn malformed(config: &Config, buf: &[u8], end: usize) -> Option<&'static str> {
+    let is_varnish = config.get_format() == Format::Varnish;
+    let is_host = |addr: &[u8]| {
+        !addr.is_empty()
+            && addr
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._".contains(b))
+    };
+    let invalid = buf[..end]
+        .split(|&b| is_varnish && b == b',')
+        .map(|addr| &addr[skip_whitespace(addr)..])
+        .any(|addr| address_kind(addr) == Address::Host && !is_host(addr));
+    if invalid {
+        return Some("first word is not an IP address or host name");
+    }
+
+    let is_cleared = config.get_optimize()
+        && buf.len() >= end + 6
+        && buf[end + 3..end + 6].iter().cmp(b"- [") == Ordering::Equal;
+    if config.get_authuser() && !is_cleared && find_time_local(buf, end).is_none() {
+        return Some("missing [$time_local] field");
+    }
+
+    None
+}
+
+fn write_or_replace<W: Write>(
+    slice: &[u8],
+    needles: &[(&[u8], &[u8])],
+    should_replace: bool,
+    writer: &mut W,
+) -> Result<(), io::Error> {
+    if should_replace {
+        let mut line = slice.to_vec();
+        for (needle, repl) in needles {
+            if !needle.is_empty() {
+                line = line.replace(needle, repl);
+            }
+        }
+        writer.write_all(&line)?;
+    } else {
+        writer.write_all(slice)?;
+    }
+    Ok(())
+}
+
+/// Creates a reader (defaults to [`std::io::Stdin`]) and writer (defaults to [`std::io::Stdout`])
+/// from [`alog::IOConfig`] and uses both along with [`alog::Config`] to actually replace
+/// any first *word* in `reader` with strings stored in [`alog::Config`].
+///
+/// Appends data if the writer points to an existing, writeable file, unless another
+/// [`OutputMode`] is set with [`IOConfig::set_output_mode`].
+///
+/// With features `gzip`, `zstd`, `bzip2` or `xz` enabled, inputs ending in `.gz`, `.zst`, `.bz2`
+/// or `.xz` are decompressed transparently and output can be compressed with
+/// [`IOConfig::set_compression`]. Compressed data on Stdin is detected by its magic bytes.
+///
+/// With [`IOConfig::set_in_place`] set to `true` every input file is replaced by its anonymized
+/// version. Data is written to a temporary file in the same directory first (compressed like
+/// the input), which is renamed over the input file once processing succeeded.
+///
+/// With an output directory set ([`IOConfig::set_output_dir`]) every input file is anonymized to
+/// a file with the same name (and compression) in that directory instead.
+///
+/// With input / output pairs ([`IOConfig::add_pair`]) every input file is anonymized to its own
+/// output file, compressed according to the output's extension.
+///
+/// With [`IOConfig::set_follow`] set to `true` a single input file is followed (like `tail -f`),
+/// new lines are anonymized (and flushed) as they arrive. Rotated or truncated files are
+/// detected and read from the beginning. This function will not return unless an error occurs.
+///
+/// Output is flushed after every line read from FIFO (named pipe) inputs. With
+/// [`IOConfig::set_reopen_fifo`] set to `true` FIFOs are reopened (blocking until the next
+/// process opens it for writing) instead of reaching the end of input when the writing process
+/// closes them, e.g. to use `alog` as the target of nginx' `access_log /path/to/fifo`.
+///
+/// With feature `s3`, `gcs` or `azure` enabled, inputs and output can be object storage URLs
+/// (`s3://<bucket>/<key>`, `gs://<bucket>/<object>` or `az://<container>/<blob>`), objects are
+/// streamed through the AWS, Google Cloud or Azure CLI, which needs to be installed and
+/// configured. Objects can not be appended to, an existing output object will be replaced.
+///
+/// With a checkpoint file set ([`IOConfig::set_resume`]) the number of bytes processed is recorded
+/// for every input file (every 64 MiB and at the end of the file), a later run skips these bytes
+/// and appends only new data to the output. Inputs are expected to only grow.
+///
+/// With several outputs ([`IOConfig::push_output`]) every output receives the same data, with
+/// [`IOConfig::set_tee`] set to `true` it is written to [`std::io::Stdout`] as well.
+///
+/// With [`IOConfig::set_rotate_size`] set the output file is renamed (see
+/// [`IOConfig::set_rotate_name`]) and reopened once it exceeds the given size. Rotation happens
+/// on line boundaries, output is flushed after every line. With [`IOConfig::set_rotate_time`]
+/// set a new output file is started every hour / day, the output path is expanded like
+/// `strftime` (UTC), e.g. `access-%Y-%m-%d.log`.
+///
+/// With a listen address set ([`IOConfig::set_listen`]) syslog messages are received via UDP,
+/// TCP or Unix domain sockets instead of reading any input, see [`IOConfig::set_listen`]. Every message is anonymized
+/// and flushed to the output as it arrives, the syslog envelope is kept unchanged (see
+/// [`Config::syslog`]). This function will not return unless an error occurs.
+///
+/// With [`IOConfig::set_journal`] set to `true` (and feature `journald` enabled) entries of the
+/// systemd journal are read through `journalctl`, see [`IOConfig::set_journal`].
+///
+/// With [`IOConfig::set_watch`] set to `true` all inputs are directories, which are scanned for
+/// new or changed files whenever inotify reports a change on Linux, every second on other
+/// systems. Files not modified for at least five seconds are anonymized to the output directory,
+/// unless the output file is newer than its input. Output files are
+/// replaced atomically. This function will not return unless an error occurs.
+///
+/// ## Errors
+///
+/// Returns an error if the new reader / writer retruns an error.
+///
+/// ## Example
+///
+/// ```no_run
+/// alog::run(
+///     &alog::Config {
+///         host: "XXX".into(),
+///         ..Default::default()
+///     },
+///     &alog::IOConfig::default()
+/// ).unwrap();
+/// ```
+///
+/// [`alog::Config`]: ./struct.Config.html
+/// [`alog::IOConfig`]: ./struct.IOConfig.html
+/// [`alog::IOConfig::get_output()`]: ./struct.IOConfig.html#method.get_output
+/// [`std::io::Stdin`]: https://doc.rust-lang.org/std/io/struct.Stdin.html
+/// [`std::io::Stdout`]: https://doc.rust-lang.org/std/io/struct.Stdout.html
+/// [`std::net::Ipv4Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv4Addr.html
+/// [`std::net::Ipv6Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv6Addr.html
+pub fn run<'c>(config: &impl AsRef<Config<'c>>, ioconfig: &IOConfig) -> Result<(), Error> {
+    let config = config.as_ref();
+    cancelled(config, run_config(config, ioconfig))
+}
+
+/// Returns [`Error::Cancelled`] instead of the error caused by cancelling `config`
+fn cancelled(config: &Config, result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Err(_) if config.is_cancelled() => Err(Error::Cancelled),
+        result => result,
+    }
+}
+
+/// See [`run`]
+fn run_config(config: &Config, ioconfig: &IOConfig) -> Result<(), Error> {
+    if ioconfig.get_watch() {
+        return watch::watch(config, ioconfig);
+    }
+    if let Some(addr) = ioconfig.get_listen() {
+        return listen::listen(config, ioconfig, addr);
+    }
+    if ioconfig.get_journal() {
+        #[cfg(feature = "journald")]
+        return journal::replace(config, ioconfig);
+        #[cfg(not(feature = "journald"))]
+        return Err(Error::Usage(
+            "Reading the journal requires feature `journald`".to_string(),
+        ));
+    }
+    if let Some(mode) = ioconfig.get_dry_run() {
+        return dry_run(config, ioconfig, mode);
+    }
+    if ioconfig.get_check() {
+        return check_inputs(config, ioconfig, io::stdout().lock());
+    }
+
+    if !ioconfig.get_pairs().is_empty() {
+        return replace_pairs(config, ioconfig);
+    }
+
+    if ioconfig.get_in_place() || ioconfig.get_output_dir().is_some() {
+        let message = match ioconfig.get_input() {
+            None => "In-place editing and output directories require input files",
+            Some(input) if input.iter().any(|i| i.path().is_none()) => {
+                "In-place editing and output directories require input files"
+            }
+            Some(_) if ioconfig.get_output().is_some() || ioconfig.writer.is_some() => {
+                "In-place editing and output directories can not be combined with an output file"
+            }
+            Some(_) if ioconfig.get_follow() => {
+                "In-place editing and output directories can not be combined with follow mode"
+            }
+            Some(_) if ioconfig.get_in_place() && ioconfig.get_output_dir().is_some() => {
+                "In-place editing can not be combined with an output directory"
+            }
+            Some(_) if budget(config, ioconfig).is_limited() => {
+                "In-place editing and output directories can not be combined with input limits"
+            }
+            Some(input) => {
+                let paths: Vec<&Path> = input.iter().filter_map(|i| i.path()).collect();
+                let failed = AtomicUsize::new(0);
+                for_each_file(config, &paths, |config, path| {
+                    let start = Instant::now();
+                    config.log(
+                        Level::Info,
+                        format_args!("reading input '{}'", path.display()),
+                    );
+                    let result = with_input_stats(config, path.to_path_buf(), |config| {
+                        match ioconfig.get_output_dir() {
+                            Some(dir) => replace_to_dir(config, ioconfig, path, dir),
+                            None => replace_in_place(config, ioconfig, path),
+                        }
+                    });
+                    if result.is_ok() {
+                        log_finished(config, path.display(), start);
+                    }
+                    skip_failed(config, ioconfig, path.display(), result, &failed)
+                })?;
+                return failed_inputs(&failed, paths.len());
+            }
+        };
+        return Err(Error::Usage(message.to_string()));
+    }
+
+    if ioconfig.get_follow()
+        && ioconfig
+            .get_input()
+            .is_some_and(|i| i.len() > 1 || i.iter().any(|i| i.path().is_none()))
+    {
+        return Err(Error::Usage(
+            "Follow mode supports a single input file".to_string(),
+        ));
+    }
+
+    let flush_config;
+    let config = if ioconfig.get_follow()
+        || ioconfig.get_rotate_size().is_some()
+        || ioconfig.get_rotate_time().is_some()
+        || ioconfig
+            .get_input()
+            .is_some_and(|i| i.iter().filter_map(|i| i.path()).any(follow::is_fifo))
+    {
+        flush_config = Config {
+            flush: true,
+            ..config.clone()
+        };
+        &flush_config
+    } else {
+        config
+    };
+
+    // Set writer
+    let mut writer = open_output(ioconfig)?;
+
+    let mut state = match ioconfig.get_resume().map(checkpoint::State::load) {
+        Some(Err(e)) => return Err(Error::Checkpoint(e)),
+        Some(Ok(state)) => Some(state),
+        None => None,
+    };
+
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let total = input_size(ioconfig).filter(|_| ioconfig.get_progress_bar());
+    let progress = (ioconfig.get_progress_interval().is_some() || total.is_some())
+        .then(|| progress::Progress::new(delimiter));
+
+    // Stop reading once interrupted, the output is still completed below
+    let result = thread::scope(|s| {
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        match (&progress, total, ioconfig.get_progress_interval()) {
+            (Some(progress), Some(total), _) => {
+                s.spawn(move || progress.bar(total, &stopped));
+            }
+            (Some(progress), None, Some(interval)) => {
+                s.spawn(move || progress.report(interval, &stopped));
+            }
+            _ => (),
+        }
+        let result = replace_inputs(
+            config,
+            ioconfig,
+            &mut writer,
+            state.as_mut(),
+            progress.as_ref(),
+        );
+        drop(stop);
+        result
+    });
+
+    // a failed input is reported rather than the output it left unflushed
+    let finished = writer.finish().and_then(Sink::finish);
+    if let Some(progress) = progress.filter(|_| ioconfig.get_progress_interval().is_some()) {
+        progress.summary();
+    }
+    result?;
+    Ok(finished?)
+}
+
+/// Prints a preview of the changes to all inputs of `ioconfig` (Stdin by default) to Stdout
+fn dry_run(config: &Config, ioconfig: &IOConfig, mode: DryRun) -> Result<(), Error> {
+    let stdin = vec![Input::Stdin];
+    let inputs = ioconfig.get_input().unwrap_or(&stdin);
+    let failed = AtomicUsize::new(0);
+    let mut budget = budget(config, ioconfig);
+    for arg in inputs {
+        if budget.is_exhausted() {
+            break;
+        }
+        let reader = match arg {
+            Input::Stdin => compress::sniff(io::stdin().lock()),
+            Input::Path(path) => open_input(config, ioconfig, path),
+            Input::Reader(reader) => reader.take().and_then(compress::sniff),
+        };
+        let result = reader
+            .map(|reader| limited(reader, &mut budget))
+            .map_err(|source| Error::OpenInput {
+                path: arg.to_path_buf(),
+                source,
+            })
+            .and_then(|reader| {
+                diff::preview(config, mode, &arg.to_string(), reader, io::stdout().lock())
+                    .map_err(Error::from)
+            });
+        skip_failed(config, ioconfig, arg, result, &failed)?;
+    }
+
+    failed_inputs(&failed, inputs.len())
+}
+
+/// Reports personal data left in all inputs of `ioconfig` (Stdin by default) to `writer`
+fn check_inputs<W: Write>(
+    config: &Config,
+    ioconfig: &IOConfig,
+    mut writer: W,
+) -> Result<(), Error> {
+    let stdin = vec![Input::Stdin];
+    let inputs = ioconfig.get_input().unwrap_or(&stdin);
+    let failed = AtomicUsize::new(0);
+    let mut budget = budget(config, ioconfig);
+    let mut count = 0;
+    for arg in inputs {
+        if budget.is_exhausted() {
+            break;
+        }
+        let reader = match arg {
+            Input::Stdin => compress::sniff(io::stdin().lock()),
+            Input::Path(path) => open_input(config, ioconfig, path),
+            Input::Reader(reader) => reader.take().and_then(compress::sniff),
+        };
+        let result = reader
+            .map(|reader| limited(reader, &mut budget))
+            .map_err(|source| Error::OpenInput {
+                path: arg.to_path_buf(),
+                source,
+            })
+            .and_then(|reader| {
+                count += check::check(config, &arg.to_string(), reader, &mut writer)?;
+                Ok(())
+            });
+        skip_failed(config, ioconfig, arg, result, &failed)?;
+    }
+
+    failed_inputs(&failed, inputs.len())?;
+    match count {
+        0 => Ok(()),
+        count => Err(Error::Findings { count }),
+    }
+}
+
+/// Returns the total size of all inputs, `None` unless all inputs are regular files
+fn input_size(ioconfig: &IOConfig) -> Option<u64> {
+    ioconfig.get_input()?.iter().try_fold(0, |total, input| {
+        let path = input.path().filter(|p| !is_remote(p))?;
+        let metadata = fs::metadata(path).ok().filter(fs::Metadata::is_file)?;
+        Some(total + metadata.len())
+    })
+}
+
+/// Anonymizes all inputs of `ioconfig` (Stdin by default) to `writer`, see [`run`]
+fn replace_inputs<W: Write>(
+    config: &Config,
+    ioconfig: &IOConfig,
+    mut writer: W,
+    mut state: Option<&mut checkpoint::State>,
+    progress: Option<&progress::Progress>,
+) -> Result<(), Error> {
+    let stdin = vec![Input::Stdin];
+    let inputs = ioconfig.get_input().unwrap_or(&stdin);
+    let failed = AtomicUsize::new(0);
+    let mut budget = budget(config, ioconfig);
+    for arg in inputs {
+        if budget.is_exhausted() {
+            break;
+        }
+        let result = with_input_stats(config, arg.to_path_buf(), |config| {
+            replace_input(
+                config,
+                ioconfig,
+                arg,
+                &mut writer,
+                state.as_deref_mut(),
+                progress,
+                &mut budget,
+            )
+        });
+        // stop once interrupted, even with `keep_going`
+        if result.is_err() && (config.is_interrupted() || config.is_cancelled()) {
+            return result;
+        }
+        skip_failed(config, ioconfig, arg, result, &failed)?;
+    }
+
+    failed_inputs(&failed, inputs.len())
+}
+
+/// Anonymizes a single input to `writer`, see [`replace_inputs`]
+fn replace_input<W: Write>(
+    config: &Config,
+    ioconfig: &IOConfig,
+    arg: &Input,
+    writer: &mut W,
+    state: Option<&mut checkpoint::State>,
+    progress: Option<&progress::Progress>,
+    budget: &mut limit::Budget,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    config.log(Level::Info, format_args!("reading input '{arg}'"));
+
+    #[cfg(unix)]
+    if let Input::Path(path) = arg {
+        if state.is_none() && !budget.is_limited() && is_mappable(ioconfig, path) {
+            config.log(Level::Debug, format_args!("memory mapping input '{arg}'"));
+            let mut writer = TrackWrites::new(&mut *writer);
+            match File::open(path).and_then(|f| mmap::replace(config, &f, &mut writer, progress)) {
+                Err(source) => return Err(writer.error(ioconfig, arg, source)),
+                Ok(offset) if config.is_interrupted() => return Err(interrupted(arg, offset)),
+                Ok(_) => (),
+            }
+            log_finished(config, arg, start);
+            return Ok(());
+        }
+    }
+
+    let reader = match arg {
+        Input::Stdin => compress::sniff(io::stdin().lock()),
+        Input::Path(path) => open_input(config, ioconfig, path),
+        Input::Reader(reader) => reader.take().and_then(compress::sniff),
+    };
+    match reader {
+        Err(source) => {
+            return Err(Error::OpenInput {
+                path: arg.to_path_buf(),
+                source,
+            })
+        }
+        Ok(reader) => {
+            let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+            let mut reader = signal::Interruptible::new(
+                limited(reader, budget),
+                config.get_cancel().cloned(),
+                delimiter,
+            );
+            let counter = progress::Counter {
+                reader: &mut reader,
+                progress,
+            };
+            let mut writer = TrackWrites::new(&mut *writer);
+            let result = match (state, arg) {
+                (Some(state), Input::Path(path)) => checkpoint::replace(
+                    config,
+                    counter,
+                    &mut writer,
+                    state,
+                    path,
+                    checkpoint::INTERVAL,
+                ),
+                _ => replace_remote_address(config, counter, &mut writer),
+            };
+            if let Err(source) = result {
+                return Err(writer.error(ioconfig, arg, source));
+            }
+            if config.is_interrupted() {
+                return Err(interrupted(arg, reader.consumed));
+            }
+        }
+    }
+    log_finished(config, arg, start);
+    Ok(())
+}
+
+/// Returns the input limits of `ioconfig`
+fn budget(config: &Config, ioconfig: &IOConfig) -> limit::Budget {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    limit::Budget::new(
+        ioconfig.get_max_lines(),
+        ioconfig.get_max_bytes(),
+        delimiter,
+    )
+}
+
+/// Limits `reader` to the lines left in `budget`, unless input isn't limited at all
+fn limited<'r, R: BufRead + 'r>(reader: R, budget: &'r mut limit::Budget) -> Box<dyn BufRead + 'r> {
+    if budget.is_limited() {
+        Box::new(limit::Limited::new(reader, budget))
+    } else {
+        Box::new(reader)
+    }
+}
+
+/// Returns `result`, unless [`IOConfig::set_keep_going`] is set: then failures are logged as
+/// warnings and counted in `failed`
+fn skip_failed(
+    config: &Config,
+    ioconfig: &IOConfig,
+    input: impl fmt::Display,
+    result: Result<(), Error>,
+    failed: &AtomicUsize,
+) -> Result<(), Error> {
+    match result {
+        Err(e) if ioconfig.get_keep_going() && !config.is_cancelled() => {
+            config.log(Level::Warn, format_args!("skipping input '{input}': {e}"));
+            failed.fetch_add(1, AtomicOrdering::Relaxed);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Calls `f` with statistics of its own for the single input `path`, which are added to
+/// [`Config::stats`] afterwards, see [`Summary::files`]
+fn with_input_stats<T>(config: &Config, path: PathBuf, f: impl FnOnce(&Config) -> T) -> T {
+    let Some(stats) = config.get_stats().filter(|stats| stats.is_full()) else {
+        return f(config);
+    };
+    let input_stats = Stats::new();
+    let result = f(&Config {
+        stats: Some(&input_stats),
+        ..config.clone()
+    });
+    stats.merge_input(path, &input_stats);
+    result
+}
+
+/// Returns an error if any of `total` inputs failed, see [`skip_failed`]
+fn failed_inputs(failed: &AtomicUsize, total: usize) -> Result<(), Error> {
+    match failed.load(AtomicOrdering::Relaxed) {
+        0 => Ok(()),
+        failed => Err(Error::Failed { failed, total }),
+    }
+}
+
+fn interrupted(input: &Input, offset: u64) -> Error {
+    Error::Interrupted {
+        path: input.to_path_buf(),
+        offset,
+    }
+}
+
+fn log_finished(config: &Config, input: impl fmt::Display, start: Instant) {
+    config.log(
+        Level::Info,
+        format_args!(
+            "finished input '{input}' in {:.3}s",
+            start.elapsed().as_secs_f64()
+        ),
+    );
+}
+
+/// Opens all output files (defaults to [`std::io::Stdout`]) and wraps them in an [`Encoder`]
+fn open_output(ioconfig: &IOConfig) -> Result<Encoder<Box<dyn Sink>>, Error> {
+    let rotate = ioconfig.get_rotate_size().is_some() || ioconfig.get_rotate_time().is_some();
+    if rotate && ioconfig.get_compression() != Compression::None {
+        return Err(Error::Usage(
+            "Output rotation can not be combined with compression".to_string(),
+        ));
+    }
+
+    let mut sinks = vec![];
+    for output in ioconfig.get_outputs() {
+        match open_sink(ioconfig, output, rotate) {
+            Ok(w) => sinks.push(w),
+            Err(source) => {
+                return Err(Error::OpenOutput {
+                    path: output.to_path_buf(),
+                    source,
+                })
+            }
+        }
+    }
+    if let Some(writer) = &ioconfig.writer {
+        let writer = writer.take().map_err(|source| Error::OpenOutput {
+            path: PathBuf::from("-"),
+            source,
+        })?;
+        sinks.push(Box::new(BufWriter::new(writer)));
+    }
+    if sinks.is_empty() || ioconfig.get_tee() {
+        sinks.push(Box::new(BufWriter::new(io::stdout())));
+    }
+
+    let writer: Box<dyn Sink> = match sinks.len() {
+        1 => sinks.remove(0),
+        _ => Box::new(Tee(sinks)),
+    };
+
+    Ok(Encoder::new(writer, ioconfig.get_compression())?)
+}
+
+/// Opens a single output file (or remote object), rotating it if `rotate` is set
+fn open_sink(ioconfig: &IOConfig, output: &Path, rotate: bool) -> io::Result<Box<dyn Sink>> {
+    let options = ioconfig.get_output_mode().open_options();
+
+    Ok(if is_remote(output) {
+        open_remote_output(output)?
+    } else if reopen_on_hangup(ioconfig) {
+        Box::new(ReopenFile {
+            writer: BufWriter::new(options.open(output)?),
+            path: output.to_path_buf(),
+            hangups: signal::hangups(),
+        })
+    } else if rotate {
+        Box::new(rotate::Rotate::open(
+            output,
+            &options,
+            ioconfig.get_rotate_name(),
+            ioconfig.get_rotate_size(),
+            ioconfig.get_rotate_time(),
+        )?)
+    } else {
+        Box::new(BufWriter::new(options.open(output)?))
+    })
+}
+
+/// Returns `true` if output files are reopened by [`reopen_outputs`]: following an input or listening,
+/// unless output is compressed or rotated by `alog` itself
+fn reopen_on_hangup(ioconfig: &IOConfig) -> bool {
+    (ioconfig.get_follow() || ioconfig.get_listen().is_some())
+        && ioconfig.get_compression() == Compression::None
+        && ioconfig.get_rotate_size().is_none()
+        && ioconfig.get_rotate_time().is_none()
+}
+
+/// Output file reopened after [`reopen_outputs`], e.g. once logrotate moved it away
+///
+/// The file is reopened for appending, with the next write or flush.
+struct ReopenFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    hangups: usize,
+}
+
+impl ReopenFile {
+    fn reopen(&mut self) -> io::Result<()> {
+        let hangups = signal::hangups();
+        if hangups != self.hangups {
+            self.writer.flush()?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.writer = BufWriter::new(file);
+            self.hangups = hangups;
+        }
+        Ok(())
+    }
+}
 
-        if config.get_trim() {
-            let s = buf
-                .iter()
-                .position(|&x| !x.is_ascii_whitespace())
-                .unwrap_or(0);
-            buf.drain(..s);
-        }
-
-        for (i, byte) in buf.iter().enumerate() {
-            if byte.is_ascii_whitespace() {
-                let needle = &String::from_utf8_lossy(&buf[..i]);
-                repl = match needle {
-                    s if s.parse::<net::Ipv4Addr>().is_ok() => config.get_ipv4_value(),
-                    s if s.parse::<net::Ipv6Addr>().is_ok() => config.get_ipv6_value(),
-                    s if s.is_empty() && config.get_skip() => continue 'lines,
-                    _ => config.get_host_value(),
-                };
-
-                write!(&mut writer, "{repl}")?;
-
-                let is_authuser = config.get_authuser();
-                let is_thorough = config.get_thorough();
-                let is_optimized = config.get_optimize() && buf.len() >= i + 6;
-
-                if is_authuser {
-                    if is_optimized && buf[i + 3..i + 6].iter().cmp(b"- [") == Ordering::Equal {
-                        write_or_replace(&buf[i..], needle, repl, is_thorough, &mut writer)?;
-                    } else if let Some(time_field) = RE.find_at(&buf, i) {
-                        write!(&mut writer, " - -")?;
-                        write_or_replace(
-                            &buf[time_field.start()..],
-                            needle,
-                            repl,
-                            is_thorough,
-                            &mut writer,
-                        )?;
-                    } else {
-                        write_or_replace(&buf[i..], needle, repl, is_thorough, &mut writer)?;
-                    }
-                } else if is_thorough {
-                    write_or_replace(&buf[i..], needle, repl, true, &mut writer)?;
-                } else {
-                    writer.write_all(&buf[i..])?;
-                }
+impl Write for ReopenFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.reopen()?;
+        self.writer.write(buf)
+    }
 
-                if config.get_flush() {
-                    writer.flush()?;
-                }
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.reopen()
+    }
+}
+
+impl Sink for ReopenFile {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes all data to every inner writer
+struct Tee(Vec<Box<dyn Sink>>);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for w in &mut self.0 {
+            w.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(Write::flush)
+    }
+}
+
+impl Sink for Tee {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.0.into_iter().try_for_each(Sink::finish)
+    }
+}
+
+/// Remembers whether writing to the inner writer failed, to tell output from input errors
+struct TrackWrites<W> {
+    writer: W,
+    failed: bool,
+}
+
+impl<W: Write> TrackWrites<W> {
+    fn new(writer: W) -> Self {
+        TrackWrites {
+            writer,
+            failed: false,
+        }
+    }
 
-                continue 'lines;
+    /// Returns [`Error::Write`] if writing failed, [`Error::Anonymize`] otherwise
+    fn error(&self, ioconfig: &IOConfig, input: &Input, source: io::Error) -> Error {
+        if self.failed {
+            let output = ioconfig.get_output().unwrap_or(Path::new("-"));
+            Error::Write {
+                input: input.to_path_buf(),
+                output: output.to_path_buf(),
+                source,
+            }
+        } else {
+            Error::Anonymize {
+                path: input.to_path_buf(),
+                source,
             }
         }
     }
+}
 
-    writer.flush()?;
-    Ok(())
+impl<W: Write> Write for TrackWrites<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.writer.write(buf);
+        self.failed |= result.is_err();
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.writer.flush();
+        self.failed |= result.is_err();
+        result
+    }
 }
 
-fn write_or_replace<W: Write>(
-    slice: &[u8],
-    needle: &str,
-    repl: &str,
-    should_replace: bool,
-    writer: &mut W,
+/// Output writer which has to be finished explicitly, e.g. to wait for a child process
+trait Sink: Write + Send {
+    /// Flushes all data and closes the writer
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl<W: Write + Send> Sink for BufWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+/// Returns `true` if `path` is a supported remote URL
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+fn is_remote(path: &Path) -> bool {
+    remote::command(path, false).is_some()
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs", feature = "azure")))]
+fn is_remote(_: &Path) -> bool {
+    false
+}
+
+/// Opens the remote object at `path` for reading, decompressing it like a local file
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+fn open_remote_input(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let command = remote::command(path, false).ok_or(io::ErrorKind::Unsupported)?;
+    compress::reader(path, remote::ChildReader::spawn(command)?)
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs", feature = "azure")))]
+fn open_remote_input(_: &Path) -> io::Result<Box<dyn BufRead>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Opens the remote object at `path` for writing
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+fn open_remote_output(path: &Path) -> io::Result<Box<dyn Sink>> {
+    remote::writer(path)
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs", feature = "azure")))]
+fn open_remote_output(_: &Path) -> io::Result<Box<dyn Sink>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Returns `true` if `path` should be processed via [`mmap::replace`], i.e. memory maps are
+/// enabled and `path` is an uncompressed regular file which is not followed
+#[cfg(unix)]
+fn is_mappable(ioconfig: &IOConfig, path: &Path) -> bool {
+    ioconfig.get_mmap()
+        && !ioconfig.get_follow()
+        && !is_remote(path)
+        && Compression::from_path(path) == Compression::None
+        && fs::metadata(path).is_ok_and(|m| m.is_file())
+}
+
+/// Opens input file `path`, decompressing, following or reopening it
+fn open_input(
+    config: &Config,
+    ioconfig: &IOConfig,
+    path: &Path,
+) -> Result<Box<dyn BufRead>, io::Error> {
+    let cancel = config.get_cancel().cloned();
+    if ioconfig.get_follow() {
+        Ok(Box::new(BufReader::new(follow::Follow::open(
+            path, cancel,
+        )?)))
+    } else if ioconfig.get_reopen_fifo() && follow::is_fifo(path) {
+        Ok(Box::new(BufReader::new(follow::Reopen::open(
+            path, cancel,
+        )?)))
+    } else if is_remote(path) {
+        open_remote_input(path)
+    } else {
+        compress::reader(path, File::open(path)?)
+    }
+}
+
+/// Anonymizes file `input` to file `output` (opened with `options`), compressed like `input`
+///
+/// With `preserve` set to `true` permissions, access / modification times and (if permitted)
+/// ownership of `input` are copied to `output`.
+fn replace_file(
+    config: &Config,
+    input: &Path,
+    output: &Path,
+    options: &OpenOptions,
+    compression: Compression,
+    preserve: bool,
 ) -> Result<(), io::Error> {
-    if should_replace && !needle.is_empty() {
-        writer.write_all(&slice.replace(needle.as_bytes(), repl.as_bytes()))?;
+    let reader = compress::reader(input, File::open(input)?)?;
+    let f = options.open(output)?;
+    let mut writer = Encoder::new(BufWriter::new(f), compression)?;
+    replace_remote_address(config, reader, &mut writer)?;
+    let f = writer
+        .finish()?
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+
+    if preserve {
+        copy_metadata(&fs::metadata(input)?, &f)?;
+    }
+    f.sync_all()
+}
+
+/// Copies permissions, access / modification times and ownership from `metadata` to `file`
+///
+/// Failing to change the ownership (e.g. if not running as root) is not an error.
+fn copy_metadata(metadata: &fs::Metadata, file: &File) -> io::Result<()> {
+    file.set_permissions(metadata.permissions())?;
+    file.set_times(
+        fs::FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?),
+    )?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{fchown, MetadataExt};
+
+        match fchown(file, Some(metadata.uid()), Some(metadata.gid())) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => (),
+            result => result?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Calls `f` for every file in `paths`, processing up to [`Config::jobs`] files concurrently
+///
+/// Every file is processed by a single thread, stops at the first error.
+fn for_each_file<T, F>(config: &Config, paths: &[T], f: F) -> Result<(), Error>
+where
+    T: Sync,
+    F: Fn(&Config, &T) -> Result<(), Error> + Sync,
+{
+    let jobs = config.get_jobs().min(paths.len());
+    if jobs <= 1 {
+        return paths.iter().try_for_each(|path| f(config, path));
+    }
+
+    let file_config = Config {
+        jobs: 1,
+        ..config.clone()
+    };
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                s.spawn(|| {
+                    while let Some(path) = paths.get(next.fetch_add(1, AtomicOrdering::Relaxed)) {
+                        if let Err(e) = f(&file_config, path) {
+                            next.store(paths.len(), AtomicOrdering::Relaxed);
+                            return Err(e);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        workers.into_iter().try_for_each(|worker| {
+            worker
+                .join()
+                .unwrap_or_else(|_| Err(Error::Other("Worker thread panicked".to_string())))
+        })
+    })
+}
+
+/// Anonymizes every input added with [`IOConfig::add_pair`] to its own output, see [`run`]
+fn replace_pairs(config: &Config, ioconfig: &IOConfig) -> Result<(), Error> {
+    let pairs = ioconfig.get_pairs();
+    let message = if ioconfig.get_input().is_some_and(|i| i.len() > pairs.len()) {
+        "Input / output pairs can not be combined with other inputs"
+    } else if ioconfig.get_output().is_some() || ioconfig.writer.is_some() {
+        "Input / output pairs can not be combined with an output file"
+    } else if ioconfig.get_in_place() || ioconfig.get_output_dir().is_some() {
+        "Input / output pairs can not be combined with in-place editing or an output directory"
+    } else if ioconfig.get_follow() {
+        "Input / output pairs can not be combined with follow mode"
+    } else if budget(config, ioconfig).is_limited() {
+        "Input / output pairs can not be combined with input limits"
     } else {
-        writer.write_all(slice)?;
+        let failed = AtomicUsize::new(0);
+        for_each_file(config, pairs, |config, (path, output)| {
+            let start = Instant::now();
+            config.log(
+                Level::Info,
+                format_args!("reading input '{}'", path.display()),
+            );
+            let result = with_input_stats(config, path.to_path_buf(), |config| {
+                let compression = Compression::from_path(output);
+                replace_to_path(config, ioconfig, path, output.to_path_buf(), compression)
+            });
+            if result.is_ok() {
+                log_finished(config, path.display(), start);
+            }
+            skip_failed(config, ioconfig, path.display(), result, &failed)
+        })?;
+        return failed_inputs(&failed, pairs.len());
+    };
+    Err(Error::Usage(message.to_string()))
+}
+
+/// Anonymizes `path` to a file with the same name in `dir`, see [`run`]
+fn replace_to_dir(
+    config: &Config,
+    ioconfig: &IOConfig,
+    path: &Path,
+    dir: &Path,
+) -> Result<(), Error> {
+    let Some(name) = path.file_name() else {
+        return Err(Error::Other(format!(
+            "Can not get file name of input '{}'",
+            path.display()
+        )));
+    };
+    replace_to_path(
+        config,
+        ioconfig,
+        path,
+        dir.join(name),
+        Compression::from_path(path),
+    )
+}
+
+/// Anonymizes `path` to `output`, compressed with `compression`, see [`run`]
+fn replace_to_path(
+    config: &Config,
+    ioconfig: &IOConfig,
+    path: &Path,
+    output: PathBuf,
+    compression: Compression,
+) -> Result<(), Error> {
+    if fs::canonicalize(&output).is_ok_and(|o| fs::canonicalize(path).is_ok_and(|i| i == o)) {
+        return Err(Error::Usage(format!(
+            "Output '{}' would overwrite its input",
+            output.display()
+        )));
+    }
+
+    if let Err(source) = replace_file(
+        config,
+        path,
+        &output,
+        &ioconfig.get_output_mode().open_options(),
+        compression,
+        ioconfig.get_preserve(),
+    ) {
+        return Err(Error::Write {
+            input: path.to_path_buf(),
+            output,
+            source,
+        });
     }
+
     Ok(())
 }
 
-/// Creates a reader (defaults to [`std::io::Stdin`]) and writer (defaults to [`std::io::Stdout`])
-/// from [`alog::IOConfig`] and uses both along with [`alog::Config`] to actually replace
-/// any first *word* in `reader` with strings stored in [`alog::Config`].
+/// Anonymizes `path` in place, see [`run`]
+fn replace_in_place(config: &Config, ioconfig: &IOConfig, path: &Path) -> Result<(), Error> {
+    let tmp = temp_path(path);
+
+    let result = replace_file(
+        config,
+        path,
+        &tmp,
+        OpenOptions::new().write(true).create_new(true),
+        Compression::from_path(path),
+        ioconfig.get_preserve(),
+    )
+    .and_then(|()| match ioconfig.get_backup_suffix() {
+        Some(suffix) => backup(path, suffix),
+        None => Ok(()),
+    })
+    .and_then(|()| fs::rename(&tmp, path));
+
+    if let Err(source) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(Error::Write {
+            input: path.to_path_buf(),
+            output: path.to_path_buf(),
+            source,
+        });
+    }
+
+    Ok(())
+}
+
+/// Keeps a copy of `path` as `<path><suffix>`, replacing any existing backup
+///
+/// The backup is a hard link if possible, so `path` is never missing.
+fn backup(path: &Path, suffix: &str) -> io::Result<()> {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    let backup = PathBuf::from(name);
+
+    match fs::remove_file(&backup) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    if fs::hard_link(path, &backup).is_err() {
+        fs::copy(path, &backup)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the config file at `path` with `parse`, adding `path` to parse errors
+fn read_config<T>(path: &Path, parse: impl FnOnce(&str) -> Result<T, Error>) -> Result<T, Error> {
+    let content = fs::read_to_string(path).map_err(|source| Error::ReadConfig {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&content).map_err(|e| match e {
+        Error::Parse {
+            line_no, message, ..
+        } => Error::Parse {
+            path: Some(path.to_path_buf()),
+            line_no,
+            message,
+        },
+        e => e,
+    })
+}
+
+/// Returns the path of a temporary file in the same directory as `path`
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.alog-{}", process::id()))
+}
+
+/// Reads lines from `reader`, if there is a '*first word*' (any String separated from the
+/// remainder of the line by a b' ' (Space) byte) this word will be replaced
+///
+/// Any word, that can be parsed as
+/// * [`std::net::Ipv4Addr`] will be replaced with [`Config::ipv4`],
+/// * [`std::net::Ipv6Addr`] will be replaced with [`Config::ipv6`],
+/// * any other *word* will be replaced with [`Config::host`].
+///
+/// Any line without a 'first word' will be written as is if [`Config::skip`] is `false`
+/// (default), or will be skipped otherwise.
+///
+/// Lines longer than [`Config::max_line_len`] are handled according to [`Config::oversized`],
+/// without buffering more than `max_line_len` bytes.
+///
+/// Lines can be terminated by `"\n"` or `"\r\n"` (NUL with [`Config::null_data`] set), the
+/// terminator is never part of the last field and written as is.
+///
+/// Lines are processed with [`Config::jobs`] threads or in blocks of [`Config::block_size`]
+/// bytes if set, [`Config::stats`], [`Config::observer`] and [`Config::logger`] are used just
+/// like by [`run_raw`]. `writer` is flushed after the last line.
 ///
-/// Appends data if the writer points to an existing, writeable file.
+/// Unlike [`run_raw`] all errors are returned as the `io::Error` of the reader or writer, e.g.
+/// to keep its [`io::ErrorKind`].
 ///
 /// ## Errors
 ///
-/// Returns an error if the new reader / writer retruns an error.
+/// This function will return an I/O error if the underlying reader or writer returns an error.
+/// Lines exceeding [`Config::max_line_len`] with [`Oversized::Error`] are reported as
+/// [`io::ErrorKind::InvalidData`], a run stopped by a [`CancellationToken`] as
+/// [`io::ErrorKind::Other`] with the message `Cancelled`.
 ///
 /// ## Example
 ///
-/// ```no_run
-/// alog::run(
-///     &alog::Config {
-///         host: "XXX",
-///         ..Default::default()
-///     },
-///     &alog::IOConfig::default()
-/// ).unwrap();
 /// ```
+/// use std::io::{self, Write};
 ///
-/// [`alog::Config`]: ./struct.Config.html
-/// [`alog::IOConfig`]: ./struct.IOConfig.html
-/// [`alog::IOConfig::get_output()`]: ./struct.IOConfig.html#method.get_output
-/// [`std::io::Stdin`]: https://doc.rust-lang.org/std/io/struct.Stdin.html
-/// [`std::io::Stdout`]: https://doc.rust-lang.org/std/io/struct.Stdout.html
-/// [`std::net::Ipv4Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv4Addr.html
-/// [`std::net::Ipv6Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv6Addr.html
-pub fn run(config: &Config, ioconfig: &IOConfig) -> Result<(), IOError> {
-    // Set writer
-    let stdout = io::stdout();
-    let mut writer: Box<dyn Write> = match ioconfig.get_output() {
-        Some(output) => {
-            let f = match OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(Path::new(output))
-            {
-                Ok(f) => f,
-                Err(e) => {
-                    return Err(IOError {
-                        message: format!("Can not open output '{}': {e}", output.display()),
-                    })
-                }
-            };
-            Box::new(BufWriter::new(f)) as _
-        }
-        None => Box::new(BufWriter::new(stdout.lock())),
-    };
-
-    // Set reader
-    if let Some(input) = ioconfig.get_input() {
-        for arg in input {
-            match File::open(Path::new(arg)) {
-                Err(e) => {
-                    return Err(IOError {
-                        message: format!("Can not open input '{}': {e}", arg.display()),
-                    })
-                }
-                Ok(f) => {
-                    let reader: Box<dyn BufRead> = Box::new(BufReader::new(f));
-                    if let Err(e) = replace_remote_address(config, reader, &mut writer) {
-                        return Err(IOError {
-                            message: e.to_string(),
-                        });
-                    }
-                }
-            }
-        }
-    } else {
-        let stdin = io::stdin();
-        let reader: Box<dyn BufRead> = Box::new(stdin.lock());
-        if let Err(e) = replace_remote_address(config, reader, &mut writer) {
-            return Err(IOError {
-                message: e.to_string(),
-            });
-        }
-    }
-
-    Ok(())
+/// struct Full;
+/// impl Write for Full {
+///     fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+///         Err(io::ErrorKind::StorageFull.into())
+///     }
+///     fn flush(&mut self) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let config = alog::Config::default();
+/// let mut buffer = vec![];
+/// alog::process(&config, &b"8.8.8.8 XxX\n"[..], &mut buffer).unwrap();
+/// assert_eq!(buffer, b"127.0.0.1 XxX\n");
+///
+/// let error = alog::process(&config, &b"8.8.8.8 XxX\n"[..], Full).unwrap_err();
+/// assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+/// ```
+pub fn process<'c, R: BufRead, W: Write>(
+    config: &impl AsRef<Config<'c>>,
+    reader: R,
+    writer: W,
+) -> io::Result<()> {
+    replace_remote_address(config.as_ref(), reader, writer)
 }
 
 /// Like [`alog::run`] but will let you pass your own `reader` and `writer`. Replacement strings
@@ -663,7 +3582,8 @@ pub fn run(config: &Config, ioconfig: &IOConfig) -> Result<(), IOError> {
 ///
 /// ## Errors
 ///
-/// Returns an error if the new reader or writer retruns an error.
+/// Returns an error if the new reader or writer retruns an error, see [`process()`] to get the
+/// plain `io::Error`.
 ///
 /// ## Example
 ///
@@ -690,11 +3610,195 @@ pub fn run(config: &Config, ioconfig: &IOConfig) -> Result<(), IOError> {
 ///
 /// [`alog::run`]: ./fn.run.html
 /// [`alog::Config`]: ./struct.Config.html
-pub fn run_raw<R: BufRead, W: Write>(
-    config: &Config,
+pub fn run_raw<'c, R: BufRead, W: Write>(
+    config: &impl AsRef<Config<'c>>,
     reader: R,
     mut writer: W,
-) -> Result<(), IOError> {
-    replace_remote_address(config, reader, &mut writer)?;
-    Ok(())
+) -> Result<(), Error> {
+    let config = config.as_ref();
+    let result = replace_remote_address(config, reader, &mut writer);
+    if result.is_err() && config.is_cancelled() {
+        writer.flush()?;
+    }
+    cancelled(config, result.map_err(Error::from))
+}
+
+/// Like [`run`], returns the [`Summary`] of this run
+///
+/// Lines are counted separately from any earlier run, the counters are added to
+/// [`Config::stats`] as well if set.
+///
+/// ## Errors
+///
+/// See [`run`], the counters are still added to [`Config::stats`] on error.
+pub fn run_summary<'c>(
+    config: &impl AsRef<Config<'c>>,
+    ioconfig: &IOConfig,
+) -> Result<Summary, Error> {
+    with_summary(config.as_ref(), |config| run(config, ioconfig))
+}
+
+/// Like [`run_raw`], returns the [`Summary`] of this run, see [`run_summary`]
+///
+/// ## Example
+///
+/// ```
+/// let config = alog::Config::default();
+/// let mut buffer = vec![];
+/// let summary = alog::run_raw_summary(&config, &b"8.8.8.8 a\n::1 b\n"[..], &mut buffer).unwrap();
+/// assert_eq!(summary.lines_read, 2);
+/// assert_eq!(summary.lines_modified, 1);
+/// assert_eq!((summary.ipv4, summary.ipv6), (1, 0));
+/// ```
+///
+/// ## Errors
+///
+/// See [`run_raw`].
+pub fn run_raw_summary<'c, R: BufRead, W: Write>(
+    config: &impl AsRef<Config<'c>>,
+    reader: R,
+    writer: W,
+) -> Result<Summary, Error> {
+    with_summary(config.as_ref(), |config| run_raw(config, reader, writer))
+}
+
+/// Calls `f` with statistics of its own, which are added to [`Config::stats`] afterwards
+fn with_summary(
+    config: &Config,
+    f: impl FnOnce(&Config) -> Result<(), Error>,
+) -> Result<Summary, Error> {
+    let stats = Stats::new();
+    let result = f(&Config {
+        stats: Some(&stats),
+        ..config.clone()
+    });
+    if let Some(config_stats) = config.get_stats() {
+        config_stats.merge(&stats);
+    }
+    result.map(|()| stats.summary())
+}
+
+/// Returns an iterator over the anonymized lines of `reader`, see [`run_raw`]
+///
+/// Every item is a single line including its terminator, dropped and skipped lines are left out.
+/// Lines are always read completely and processed one at a time, [`Config::jobs`] and
+/// [`Config::block_size`] are ignored. Iteration ends after the first error.
+///
+/// ## Example
+///
+/// ```
+/// let config = alog::Config::default();
+/// let lines: Vec<_> = alog::lines(&b"8.8.8.8 a\n::2 b\n"[..], &config)
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(lines, [b"127.0.0.1 a\n".to_vec(), b"::1 b\n".to_vec()]);
+/// ```
+pub fn lines<'c, R: BufRead + 'c>(
+    mut reader: R,
+    config: &'c impl AsRef<Config<'c>>,
+) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'c {
+    let config = config.as_ref();
+    let mut buf = vec![];
+    let mut scratch = vec![];
+    let mut done = false;
+
+    iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let mut line = vec![];
+        match next_line(config, &mut reader, &mut buf, &mut scratch, &mut line) {
+            Ok(true) => Some(Ok(line)),
+            Ok(false) => {
+                done = true;
+                None
+            }
+            Err(e) => {
+                done = true;
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+/// Anonymizes a single `line`, returns `None` if it is skipped or dropped
+///
+/// `line` may include its terminator, which is kept. [`Config::max_line_len`] is not applied.
+///
+/// ## Example
+///
+/// ```
+/// let config = alog::Config::default();
+/// assert_eq!(
+///     alog::anonymize_line(&config, b"8.8.8.8 GET /\n").unwrap(),
+///     Some(b"127.0.0.1 GET /\n".to_vec())
+/// );
+/// ```
+///
+/// ## Errors
+///
+/// Returns an error if `line` is rejected with [`Config::set_strict`] or the run was cancelled,
+/// see [`Config::set_cancel`].
+pub fn anonymize_line<'c>(
+    config: &impl AsRef<Config<'c>>,
+    line: &[u8],
+) -> io::Result<Option<Vec<u8>>> {
+    let mut out = vec![];
+    replace_record(config.as_ref(), line, &mut vec![], &mut out)?;
+    Ok((!out.is_empty()).then_some(out))
+}
+
+/// Reads records from `reader` until one is written to `out`, see [`lines`]
+///
+/// Returns `false` at EOF.
+fn next_line<R: BufRead>(
+    config: &Config,
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    scratch: &mut Vec<u8>,
+    out: &mut Vec<u8>,
+) -> io::Result<bool> {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    while out.is_empty() {
+        buf.clear();
+        match reader.read_until(delimiter, buf)? {
+            0 => return Ok(false),
+            n if config.get_max_line_len().is_some_and(|max| n > max) => {
+                oversized(config, buf, out)?;
+            }
+            _ => replace_record(config, buf, scratch, out)?,
+        }
+    }
+    Ok(true)
+}
+
+/// Scans `reader` for IPv4, IPv6 and email addresses left after anonymizing and writes one
+/// report line per finding to `writer`, e.g. `-:3: IPv4 address '8.8.8.8'`
+///
+/// The replacement values of `config` and unspecified addresses are not reported, neither are
+/// addresses directly following a `/` (product versions like `Chrome/120.0.0.0`).
+///
+/// Returns the number of findings.
+///
+/// ## Example
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// let lines = Cursor::new(b"127.0.0.1 - - [x] \"GET /?ip=8.8.8.8\"\n");
+/// let mut report = vec![];
+///
+/// assert_eq!(alog::check_raw(&alog::Config::default(), lines, &mut report).unwrap(), 1);
+/// assert_eq!(report, b"-:1: IPv4 address '8.8.8.8'\n");
+/// ```
+///
+/// ## Errors
+///
+/// This function will return an I/O error if the underlying reader or writer returns an error.
+pub fn check_raw<R: BufRead, W: Write>(
+    config: &Config,
+    reader: R,
+    writer: W,
+) -> Result<u64, Error> {
+    Ok(check::check(config, "-", reader, writer)?)
 }
@@ -0,0 +1,86 @@
+use std::io::{self, BufRead, Read};
+
+/// Input left to read with [`IOConfig::set_max_lines`](crate::IOConfig::set_max_lines) and
+/// [`IOConfig::set_max_bytes`](crate::IOConfig::set_max_bytes), shared by all inputs
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    lines: Option<u64>,
+    bytes: Option<u64>,
+    delimiter: u8,
+}
+
+impl Budget {
+    pub(crate) fn new(lines: Option<u64>, bytes: Option<u64>, delimiter: u8) -> Self {
+        Budget {
+            lines,
+            bytes,
+            delimiter,
+        }
+    }
+
+    /// Returns `true` unless neither lines nor bytes are limited
+    pub(crate) fn is_limited(&self) -> bool {
+        self.lines.is_some() || self.bytes.is_some()
+    }
+
+    /// Returns `true` once no further line may be read
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.lines == Some(0) || self.bytes == Some(0)
+    }
+
+    fn take(&mut self, n: usize) {
+        if n > 0 {
+            self.lines = self.lines.map(|l| l.saturating_sub(1));
+            self.bytes = self.bytes.map(|b| b.saturating_sub(n as u64));
+        }
+    }
+}
+
+/// Reader returning EOF once the [`Budget`] is used up
+///
+/// Input is read line by line, the line exceeding the byte limit is still read completely.
+pub(crate) struct Limited<'b, R> {
+    reader: R,
+    budget: &'b mut Budget,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<'b, R: BufRead> Limited<'b, R> {
+    pub(crate) fn new(reader: R, budget: &'b mut Budget) -> Self {
+        Limited {
+            reader,
+            budget,
+            line: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Limited<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Limited<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if !self.budget.is_exhausted() {
+                let n = self
+                    .reader
+                    .read_until(self.budget.delimiter, &mut self.line)?;
+                self.budget.take(n);
+            }
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.line.len());
+    }
+}
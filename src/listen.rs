@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(unix)]
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use crate::{open_output, replace_remote_address, signal, Config, Error, IOConfig, Level};
+
+/// Maximum size of a single UDP datagram
+const MAX_DATAGRAM: usize = 65_535;
+
+/// Time to wait for a socket to become readable before checking [`Config::cancel`] again
+#[cfg(unix)]
+const POLL_INTERVAL: i32 = 250;
+
+/// Receives syslog messages on `addr`, writing anonymized messages to the output
+///
+/// Supported addresses are `udp://<addr>:<port>`, `tcp://<addr>:<port>` and on Unix
+/// `unixgram://<path>` (datagram) or `unix://<path>` (stream) sockets. Existing sockets at
+/// `<path>` are removed before binding.
+///
+/// * every datagram is a single message,
+/// * streams can use newline delimited or octet-counted framing (RFC 6587), every connection is
+///   handled in a thread of its own. Frames are at most [`MAX_DATAGRAM`] bytes long, read errors
+///   and invalid frames close the connection and are logged as warnings.
+///
+/// Messages are wrapped in a syslog envelope, [`Config::syslog`] is always set. Output write
+/// errors and rejections in strict mode stop listening and are returned.
+///
+/// Output files are reopened by [`reopen_outputs`](crate::reopen_outputs). Returns once
+/// [`Config::cancel`] is interrupted or cancelled, all open connections are read and the output
+/// is finished, otherwise only if an error occurs.
+pub(crate) fn listen(config: &Config, ioconfig: &IOConfig, addr: &str) -> Result<(), Error> {
+    // messages are anonymized on their own, no worker threads per message
+    let config = &Config {
+        jobs: 1,
+        syslog: true,
+        ..config.clone()
+    };
+    let writer = Mutex::new(open_output(ioconfig)?);
+
+    let result = match addr.split_once("://") {
+        Some(("udp", addr)) => UdpSocket::bind(addr).and_then(|s| listen_udp(config, &s, &writer)),
+        Some(("tcp", addr)) => TcpListener::bind(addr)
+            .and_then(|l| listen_stream(config, &l, || l.accept().map(|(s, _)| s), &writer)),
+        #[cfg(unix)]
+        Some(("unixgram", path)) => remove_socket(path)
+            .and_then(|()| UnixDatagram::bind(path))
+            .and_then(|s| listen_unixgram(config, &s, &writer)),
+        #[cfg(unix)]
+        Some(("unix", path)) => remove_socket(path)
+            .and_then(|()| UnixListener::bind(path))
+            .and_then(|l| listen_stream(config, &l, || l.accept().map(|(s, _)| s), &writer)),
+        _ => return Err(Error::Usage(format!("Unsupported listen address '{addr}'"))),
+    };
+
+    result.map_err(|source| Error::Listen {
+        addr: addr.to_string(),
+        source,
+    })?;
+
+    writer
+        .into_inner()
+        .map_err(|_| io::Error::other("output writer poisoned"))?
+        .finish()?
+        .finish()?;
+    Ok(())
+}
+
+/// Calls `recv` once `socket` is readable, returns `None` once [`Config::cancel`] is
+/// interrupted or cancelled or `stop` returns `true`
+///
+/// Waiting is interrupted by any signal (unlike `accept`, which the standard library restarts)
+/// and times out every [`POLL_INTERVAL`] to check the token.
+fn receive<S, T, F>(
+    config: &Config,
+    socket: &S,
+    stop: impl Fn() -> bool,
+    mut recv: F,
+) -> io::Result<Option<T>>
+where
+    S: Socket,
+    F: FnMut() -> io::Result<T>,
+{
+    while !config.is_interrupted() && !config.is_cancelled() && !stop() {
+        match wait_readable(socket) {
+            Ok(true) => match recv() {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                result => return result.map(Some),
+            },
+            Ok(false) => (),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+fn listen_udp<W: Write>(config: &Config, socket: &UdpSocket, writer: &Mutex<W>) -> io::Result<()> {
+    let mut buf = vec![0; MAX_DATAGRAM + 1];
+
+    while let Some(n) = receive(
+        config,
+        socket,
+        || false,
+        || socket.recv(&mut buf[..MAX_DATAGRAM]),
+    )? {
+        write_message(config, &mut buf, n, writer)?;
+        buf.resize(MAX_DATAGRAM + 1, 0);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn listen_unixgram<W: Write>(
+    config: &Config,
+    socket: &UnixDatagram,
+    writer: &Mutex<W>,
+) -> io::Result<()> {
+    let mut buf = vec![0; MAX_DATAGRAM + 1];
+
+    while let Some(n) = receive(
+        config,
+        socket,
+        || false,
+        || socket.recv(&mut buf[..MAX_DATAGRAM]),
+    )? {
+        write_message(config, &mut buf, n, writer)?;
+        buf.resize(MAX_DATAGRAM + 1, 0);
+    }
+    Ok(())
+}
+
+/// Removes the Unix socket at `path`, if there is one
+#[cfg(unix)]
+fn remove_socket(path: &str) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Socket which can be waited on with [`wait_readable`]
+#[cfg(unix)]
+trait Socket: AsRawFd {}
+#[cfg(unix)]
+impl<T: AsRawFd> Socket for T {}
+#[cfg(not(unix))]
+trait Socket {}
+#[cfg(not(unix))]
+impl<T> Socket for T {}
+
+/// Waits until `socket` is readable, returns `false` after [`POLL_INTERVAL`] and fails with
+/// EINTR if a signal arrives meanwhile
+#[cfg(unix)]
+fn wait_readable<S: Socket>(socket: &S) -> io::Result<bool> {
+    let mut fd = libc::pollfd {
+        fd: socket.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `fd` is a single valid pollfd
+    match unsafe { libc::poll(&mut fd, 1, POLL_INTERVAL) } {
+        -1 => Err(io::Error::last_os_error()),
+        n => Ok(n > 0),
+    }
+}
+
+#[cfg(not(unix))]
+fn wait_readable<S: Socket>(_: &S) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Stream connection which can be shut down from another thread
+trait Connection: Read + Send + Sized {
+    fn try_clone(&self) -> io::Result<Self>;
+
+    /// Shuts down the reading half, pending reads return EOF
+    fn shutdown_read(&self) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown_read(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Read)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+
+    fn shutdown_read(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Read)
+    }
+}
+
+fn listen_stream<L, S, A, W>(
+    config: &Config,
+    listener: &L,
+    mut accept: A,
+    writer: &Mutex<W>,
+) -> io::Result<()>
+where
+    L: Socket,
+    S: Connection,
+    A: FnMut() -> io::Result<S>,
+    W: Write + Send,
+{
+    // clones of all open connections, shut down once interrupted
+    let open = Mutex::new(HashMap::new());
+    let lock = || open.lock().unwrap_or_else(PoisonError::into_inner);
+    // first write error or strict rejection of any connection, stops accepting connections
+    let failed = Mutex::new(None);
+    let lock_failed = || failed.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let result = thread::scope(|s| {
+        let mut id = 0_u64;
+        let result = loop {
+            let stream = match receive(config, listener, || lock_failed().is_some(), &mut accept) {
+                Ok(Some(stream)) => stream,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            };
+            match stream.try_clone() {
+                Ok(clone) => lock().insert(id, clone),
+                Err(e) => break Err(e),
+            };
+
+            s.spawn(move || {
+                // leave SIGINT / SIGTERM to the thread accepting connections
+                signal::block();
+                if let Err(e) = read_messages(config, stream, writer) {
+                    lock_failed().get_or_insert(e);
+                }
+                lock().remove(&id);
+            });
+            id += 1;
+        };
+
+        for stream in lock().values() {
+            let _ = stream.shutdown_read();
+        }
+        result
+    });
+
+    match failed.into_inner().unwrap_or_else(PoisonError::into_inner) {
+        Some(e) => result.and(Err(e)),
+        None => result,
+    }
+}
+
+/// Anonymizes all messages read from `stream` until EOF
+///
+/// Read errors and invalid frames are logged and end the connection only, fails if writing a
+/// message fails.
+fn read_messages<S: Read, W: Write>(
+    config: &Config,
+    stream: S,
+    writer: &Mutex<W>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut buf = vec![];
+    loop {
+        match read_frame(&mut reader, &mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => write_message(config, &mut buf, n, writer)?,
+            Err(e) => {
+                config.log(Level::Warn, format_args!("closing connection: {e}"));
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Anonymizes the message in `buf[..len]`, appending a newline (or NUL) if needed
+fn write_message<W: Write>(
+    config: &Config,
+    buf: &mut Vec<u8>,
+    len: usize,
+    writer: &Mutex<W>,
+) -> io::Result<()> {
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    buf.truncate(len);
+    if buf.last() != Some(&delimiter) {
+        buf.push(delimiter);
+    }
+
+    let mut writer = writer
+        .lock()
+        .map_err(|_| io::Error::other("output writer poisoned"))?;
+    replace_remote_address(config, &buf[..], &mut *writer)
+}
+
+/// Reads a single syslog frame from `reader` into `buf`, returns the length of the message
+///
+/// Frames starting with a length followed by a syslog priority are octet-counted
+/// (`<length> <<priority>>...`), any other frame ends with a newline. Frames longer than
+/// [`MAX_DATAGRAM`] bytes fail with [`io::ErrorKind::InvalidData`].
+pub(crate) fn read_frame<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<usize> {
+    let too_long = || io::Error::new(io::ErrorKind::InvalidData, "frame too long");
+    buf.clear();
+
+    let peek = reader.fill_buf()?;
+    let digits = peek.iter().take_while(|b| b.is_ascii_digit()).count();
+    let is_counted = digits > 0 && peek[digits..].starts_with(b" <");
+    if !is_counted {
+        let n = reader
+            .take(MAX_DATAGRAM as u64 + 1)
+            .read_until(b'\n', buf)?;
+        if n > MAX_DATAGRAM {
+            return Err(too_long());
+        }
+        return Ok(n);
+    }
+
+    reader.read_until(b' ', buf)?;
+    let len = std::str::from_utf8(buf)
+        .ok()
+        .and_then(|s| s.trim_end().parse::<u64>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid frame length"))?;
+    if len > MAX_DATAGRAM as u64 {
+        return Err(too_long());
+    }
+
+    buf.clear();
+    let n = reader.take(len).read_to_end(buf)?;
+    if (n as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(n)
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Importance of a diagnostic event, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Input that could not be handled as expected, e.g. oversized lines
+    Warn,
+    /// Inputs and outputs opened and closed, with timing
+    Info,
+    /// Processing details, e.g. skipped lines and the processing mode of an input
+    Debug,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Receiver of diagnostic events, see [`Config::set_logger`](crate::Config::set_logger)
+///
+/// Events are reported from worker threads as well.
+pub trait Log: Send + Sync {
+    /// Returns `true` if events of `level` should be formatted and passed to [`Log::log`]
+    fn enabled(&self, level: Level) -> bool;
+
+    /// Handles a single event
+    fn log(&self, level: Level, message: fmt::Arguments<'_>);
+}
+
+impl fmt::Debug for dyn Log + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Log")
+    }
+}
+
+/// Logger printing all events up to a maximum level to stderr
+#[derive(Debug, Clone, Copy)]
+pub struct StderrLog {
+    level: Level,
+}
+
+impl StderrLog {
+    #[must_use]
+    pub fn new(level: Level) -> Self {
+        StderrLog { level }
+    }
+}
+
+impl Log for StderrLog {
+    fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
+
+    fn log(&self, level: Level, message: fmt::Arguments<'_>) {
+        eprintln!("alog: {level}: {message}");
+    }
+}
@@ -1,30 +1,537 @@
-use std::{ffi::OsString, path::Path, process};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::{env, ffi::OsString, process};
 
 const HELP: &str = "\
 Mangle common / combined logs
 
 USAGE:
     alog [FLAGS] [OPTIONS] [INPUT]...
+    alog completions <bash|zsh|fish>      Prints a shell completion script
+    alog formats                          Lists the log formats supported by --format
+    alog rotate-hook [OPTIONS] <FILE>...  Anonymizes rotated files, e.g. in a logrotate
+                                          postrotate script, keeps mode and timestamps
 
 FLAGS:
     -a, --authuser        Clear authuser
+        --check           Report IP and email addresses left in the input, exit with 4 if any
+        --daemon          Run in the background, stderr goes to --log-file
     -f, --flush-line      Flush output on every line
+        --follow          Keep reading the input file as it grows, reopen output files on SIGHUP
+    -i, --in-place[=SUF]  Edit input files in place, keep originals as <INPUT>SUF (e.g. .bak)
+        --ip-only         Replace IPv4 / IPv6 addresses only, keep lines starting with others
+        --journal         Read the systemd journal instead of input files, written in export
+                          format (requires feature `journald`)
+        --keep-dash       Keep a first word of `-` (unknown host) instead of replacing it
+        --keep-zone       Keep the zone ID of scoped IPv6 addresses, e.g. %eth0 of fe80::1%eth0
+    -k, --keep-going      Skip inputs which can not be read, exit with 1 once all others are done
         --no-optimize     Don't try to reduce performance hit with `--authuser`
+        --mmap            Memory map input files (inputs must not be truncated meanwhile)
     -n, --notrim          Don't remove Space and Tab from the start of every line
+    -p, --preserve        Keep mode, ownership and timestamps with --in-place / --output-dir
+    -q, --quiet           Don't print warnings
+        --reopen-fifo     Reopen FIFO inputs when the writer closes them
+        --no-clobber      Don't write to existing output files
+        --no-tty-check    Don't print a hint when reading from a terminal
+        --no-progress     Don't draw a progress bar when stderr is a terminal
     -t, --thorough        Replace all occurrences on every line
+    -v, --verbose         Print opened inputs and timing, -vv prints processing details
+        --verify-hosts    Replace valid host names only, keep junk in the first word unchanged
+        --tee             Write to Stdout in addition to --output files
     -s, --skip-invalid    Skip invalid lines
+        --strip-bom       Remove a UTF-8 byte order mark instead of keeping it
+        --strict          Fail on the first line which can not be parsed, instead of writing it
+        --truncate        Truncate existing output files instead of appending
+    -w, --watch           Watch input directories, anonymize new files to --output-dir
+                          (inotify on Linux, other systems scan the directories every second)
+        --syslog          Keep syslog envelope, only replace in the payload
+        --stats           Print statistics to stderr at the end, see --stats-format
+    -z, --null-data       Records are terminated by NUL instead of newline
 
     -h, --help            Print this message
     -V, --version         Print version information
 
 OPTIONS:
+        --archive-dir <DIR>                      Moves files anonymized by rotate-hook to DIR
+                                                 instead of replacing them where they are
+        --block-size <SIZE>                      Reads input in blocks of SIZE bytes, e.g. 1M
+                                                 [possible suffixes: K, M, G]
+        --comment-prefix <PREFIX>...             Writes lines starting with PREFIX unchanged, e.g.
+                                                 '#', can be given multiple times
+        --compress <compression>                 Sets output compression [default: none]
+                                                 [possible values: none, gzip, zstd, bzip2, xz]
+        --config <FILE>                          Reads replacements and options from a TOML file,
+                                                 ALOG_ variables and options take precedence
+        --drop-pattern <REGEX>...                Drops lines matching REGEX, can be given multiple
+                                                 times
+        --dry-run[=<mode>]                       Prints the changes instead of writing any output
+                                                 [default: diff] [possible values: diff, changed]
+        --format <format>                        Sets log format [default: combined]
+                                                 [possible values: combined, sshd, mail, varnish]
         --host-replacement <host-replacement>    Sets host replacement string [default: localhost]
     -4, --ipv4-replacement <ipv4-replacement>    Sets IPv4 replacement string [default: 127.0.0.1]
     -6, --ipv6-replacement <ipv6-replacement>    Sets IPv6 replacement string [default: ::1]
-    -o, --output <FILE>                          Sets output file
+    -j, --jobs <N>                               Sets number of worker threads
+                                                 [default: 0, uses all CPUs]
+        --journal-field <FIELD>...               Anonymizes FIELD of journal entries instead of
+                                                 MESSAGE, can be given multiple times
+        --journal-match <MATCH>...               Reads journal entries matching MATCH only, e.g.
+                                                 _SYSTEMD_UNIT=sshd.service, see journalctl(1)
+        --listen <URL>                           Receives syslog messages instead of reading input,
+                                                 e.g. udp://0.0.0.0:5514, tcp://0.0.0.0:5514,
+                                                 unixgram:///dev/log or unix:///run/alog.sock
+                                                 (implies --syslog, output files are reopened on
+                                                 SIGHUP)
+        --log-file <FILE>                        Appends stderr to FILE with --daemon
+                                                 [default: /dev/null]
+        --match <REGEX>...                       Keeps only lines matching REGEX, can be given
+                                                 multiple times
+        --max-bytes <SIZE>                       Stops after the line in which SIZE bytes of input
+                                                 were read [possible suffixes: K, M, G]
+        --max-line-len <SIZE>                    Sets maximum line length, e.g. 64K
+                                                 [possible suffixes: K, M, G]
+        --max-lines <N>                          Stops after reading N lines of input
+        --oversized <handling>                   Handles lines longer than --max-line-len
+                                                 [default: pass] [possible values: pass, skip, error]
+        --pidfile <FILE>                         Writes the process ID to FILE, removed at exit
+        --progress-interval <DURATION>           Reports throughput to stderr every DURATION and at
+                                                 the end, e.g. 5s [possible suffixes: ms, s, m]
+        --rejects <FILE>                         Writes lines rejected by --skip-invalid or --strict
+                                                 to FILE, --strict does not fail on them
+        --resume <FILE>                          Records processed input offsets (or the journal
+                                                 cursor) in FILE, resumes from there on the next run
+        --rotate-name <PATTERN>                  Sets rotated output file names, {path} and {n} are
+                                                 replaced [default: {path}.{n}]
+        --rotate-size <SIZE>                     Rotates the output file after SIZE bytes,
+                                                 e.g. 100M [possible suffixes: K, M, G]
+        --rotate-time <interval>                 Starts a new output file every interval, %Y, %m, %d,
+                                                 %H, %M and %S in the output path are replaced (UTC)
+                                                 [possible values: hourly, daily]
+        --separators <CHARS>                     Ends the first word at any of CHARS instead of
+                                                 whitespace, e.g. '\\t' for tab-delimited logs
+        --skip-fields <N>                        Keeps the first N fields (e.g. vhost:port) and
+                                                 replaces the field after them
+        --stats-file <FILE>                      Writes statistics to FILE instead of stderr
+        --stats-format <format>                  Sets statistics format [default: text]
+                                                 [possible values: text, json]
+    -o, --output <FILE>...                       Sets output file(s), can be given multiple times
+                                                 (s3://, gs:// or az:// URLs with features `s3`,
+                                                 `gcs` or `azure`)
+        --output-dir <DIR>                       Sets output directory, one output file per input
 
 ARGS:
-    <INPUT>...    The input file(s) to use";
+    <INPUT>...    The input file(s) to use, `-` reads from Stdin
+
+ENVIRONMENT:
+    Every flag and option can be set with an ALOG_ variable, e.g. ALOG_THOROUGH=1 or
+    ALOG_IPV4_REPLACEMENT=0.0.0.0, the command line takes precedence. Both take precedence
+    over the config file. Flags are off for empty values, 0 and false.
+
+EXIT STATUS:
+    0    Success
+    1    Reading input or writing output failed
+    2    Invalid arguments or configuration
+    3    All input was processed, but lines were skipped (e.g. with --skip-invalid)
+    4    --check found IP or email addresses";
+
+/// Exit status if reading input or writing output failed
+const EXIT_IO: i32 = 1;
+/// Exit status for invalid arguments or configuration
+const EXIT_USAGE: i32 = 2;
+/// Exit status if all input was processed, but some lines were skipped
+const EXIT_SKIPPED: i32 = 3;
+/// Exit status if `--check` found personal data
+const EXIT_FINDINGS: i32 = 4;
+
+fn exit_status(error: &alog::Error) -> i32 {
+    match error.kind() {
+        alog::ErrorKind::Usage => EXIT_USAGE,
+        alog::ErrorKind::Findings => EXIT_FINDINGS,
+        _ => EXIT_IO,
+    }
+}
+
+/// Flags which can be set with `ALOG_*` environment variables
+const ENV_FLAGS: &[&str] = &[
+    "authuser",
+    "check",
+    "daemon",
+    "flush-line",
+    "follow",
+    "ip-only",
+    "journal",
+    "keep-dash",
+    "keep-zone",
+    "keep-going",
+    "no-optimize",
+    "mmap",
+    "notrim",
+    "preserve",
+    "reopen-fifo",
+    "no-clobber",
+    "no-tty-check",
+    "no-progress",
+    "thorough",
+    "tee",
+    "skip-invalid",
+    "strip-bom",
+    "strict",
+    "truncate",
+    "watch",
+    "syslog",
+    "null-data",
+    "stats",
+    "quiet",
+    "verbose",
+    "verify-hosts",
+];
+
+/// Options which can be set with `ALOG_*` environment variables
+const ENV_OPTIONS: &[&str] = &[
+    "config",
+    "archive-dir",
+    "block-size",
+    "drop-pattern",
+    "match",
+    "comment-prefix",
+    "compress",
+    "format",
+    "host-replacement",
+    "ipv4-replacement",
+    "ipv6-replacement",
+    "jobs",
+    "journal-field",
+    "journal-match",
+    "listen",
+    "log-file",
+    "max-bytes",
+    "max-line-len",
+    "max-lines",
+    "oversized",
+    "pidfile",
+    "progress-interval",
+    "rejects",
+    "resume",
+    "rotate-name",
+    "rotate-size",
+    "rotate-time",
+    "separators",
+    "skip-fields",
+    "stats-file",
+    "stats-format",
+    "output",
+    "output-dir",
+];
+
+/// Returns the command line arguments equivalent to the `ALOG_*` environment variables
+fn env_args() -> Vec<OsString> {
+    let var = |name: &str| env::var_os(format!("ALOG_{}", name.to_uppercase().replace('-', "_")));
+    let is_off = |v: &OsString| v.is_empty() || v == "0" || v.eq_ignore_ascii_case("false");
+    let mut args = vec![];
+
+    for name in ENV_FLAGS {
+        if var(name).is_some_and(|v| !is_off(&v)) {
+            args.push(format!("--{name}").into());
+        }
+    }
+
+    // --in-place and --dry-run take an optional value
+    for name in ["in-place", "dry-run"] {
+        match var(name) {
+            Some(v) if is_off(&v) => (),
+            Some(v) if v == "1" || v.eq_ignore_ascii_case("true") => {
+                args.push(format!("--{name}").into());
+            }
+            Some(v) => {
+                let mut arg = OsString::from(format!("--{name}="));
+                arg.push(v);
+                args.push(arg);
+            }
+            None => (),
+        }
+    }
+
+    for name in ENV_OPTIONS {
+        if let Some(v) = var(name) {
+            args.push(format!("--{name}").into());
+            args.push(v);
+        }
+    }
+
+    args
+}
+
+/// Detaches from the terminal: forks twice with a new session in between, redirects Stdin and
+/// Stdout to `/dev/null` and stderr to `log_file`
+///
+/// The working directory is kept, so relative paths still work. Must be called before any other
+/// thread is started.
+#[cfg(unix)]
+fn daemonize(log_file: Option<&Path>) -> io::Result<()> {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    // opened first, errors are still printed to the terminal
+    let null = File::options().read(true).write(true).open("/dev/null")?;
+    let log = match log_file {
+        Some(path) => File::options().create(true).append(true).open(path)?,
+        None => null.try_clone()?,
+    };
+
+    let fork = || {
+        // SAFETY: single threaded, the parent exits without running any Rust code
+        match unsafe { ::libc::fork() } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(()),
+            _ => unsafe { ::libc::_exit(0) },
+        }
+    };
+
+    fork()?;
+    // SAFETY: plain system call, the child is not a process group leader
+    if unsafe { ::libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // the session leader exits, so no terminal is acquired again
+    fork()?;
+
+    for (fd, target) in [(0, &null), (1, &null), (2, &log)] {
+        // SAFETY: both file descriptors are valid
+        if unsafe { ::libc::dup2(target.as_raw_fd(), fd) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn daemonize(_: Option<&Path>) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Flag or option as listed in `HELP`
+#[derive(Debug, Default)]
+struct HelpEntry {
+    short: Option<char>,
+    long: &'static str,
+    /// Value name, e.g. `FILE`
+    value: Option<&'static str>,
+    /// The value is optional, e.g. `--in-place[=SUF]`
+    optional: bool,
+    /// Can be given multiple times
+    multiple: bool,
+    /// Description without defaults and possible values
+    help: String,
+    possible_values: Vec<&'static str>,
+}
+
+impl HelpEntry {
+    fn takes_files(&self) -> bool {
+        self.value.is_some_and(|v| v.contains("FILE"))
+    }
+
+    fn takes_dir(&self) -> bool {
+        self.value.is_some_and(|v| v.contains("DIR"))
+    }
+}
+
+/// Returns all flags and options of `HELP`, so completions are generated from the same table
+fn help_entries() -> Vec<HelpEntry> {
+    let mut entries: Vec<HelpEntry> = vec![];
+    let mut in_table = false;
+
+    for line in HELP.lines() {
+        if !line.starts_with(' ') {
+            in_table = line == "FLAGS:" || line == "OPTIONS:";
+            continue;
+        }
+        let line = line.trim_start();
+        if !in_table || line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('-') {
+            // continuation of the previous description
+            if let Some(entry) = entries.last_mut() {
+                entry.help = format!("{} {}", entry.help, strip_brackets(line))
+                    .trim()
+                    .to_string();
+                entry.possible_values.extend(possible_values(line));
+            }
+            continue;
+        }
+
+        let (spec, help) = line.split_once("  ").unwrap_or((line, ""));
+        let mut entry = HelpEntry {
+            help: strip_brackets(help),
+            possible_values: possible_values(help),
+            ..HelpEntry::default()
+        };
+        let spec = match spec.split_once(", ") {
+            Some((short, long)) => {
+                entry.short = short.strip_prefix('-').and_then(|s| s.chars().next());
+                long
+            }
+            None => spec,
+        };
+        let spec = spec.trim_start_matches('-');
+        let end = spec
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(spec.len());
+        entry.long = &spec[..end];
+        let value = &spec[end..];
+        entry.multiple = value.ends_with("...");
+        entry.optional = value.starts_with("[=");
+        entry.value = value
+            .split(['<', '>', '='])
+            .find(|v| !v.trim_matches(['[', ']', ' ', '.']).is_empty())
+            .map(|v| v.trim_matches(['[', ']', ' ', '.']));
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Returns `text` without `[...]` groups like `[default: none]`
+fn strip_brackets(text: &str) -> String {
+    let mut stripped = String::new();
+    let padded = format!(" {text}");
+    let mut rest = padded.as_str();
+    while let Some((before, after)) = rest.split_once(" [") {
+        stripped.push_str(before);
+        rest = after.split_once(']').map_or("", |(_, after)| after);
+    }
+    stripped.push_str(rest);
+    stripped.trim().to_string()
+}
+
+/// Returns the values of `[possible values: a, b]` in `text`
+fn possible_values(text: &'static str) -> Vec<&'static str> {
+    text.split_once("[possible values: ")
+        .and_then(|(_, values)| values.split_once(']'))
+        .map(|(values, _)| values.split(", ").collect())
+        .unwrap_or_default()
+}
+
+fn bash_completions() -> String {
+    let entries = help_entries();
+    let mut words = vec![];
+    let mut cases = String::new();
+
+    for entry in &entries {
+        let mut names = vec![format!("--{}", entry.long)];
+        names.extend(entry.short.map(|c| format!("-{c}")));
+        words.extend(names.iter().cloned());
+        if entry.value.is_none() || entry.optional {
+            continue;
+        }
+        let reply = if !entry.possible_values.is_empty() {
+            format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                entry.possible_values.join(" ")
+            )
+        } else if entry.takes_dir() {
+            "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string()
+        } else if entry.takes_files() {
+            "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string()
+        } else {
+            "COMPREPLY=()".to_string()
+        };
+        cases.push_str(&format!(
+            "        {})\n            {reply}\n            return\n            ;;\n",
+            names.join(" | ")
+        ));
+    }
+
+    format!(
+        r#"_alog() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    case "$prev" in
+{cases}    esac
+
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "{}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+
+complete -o filenames -F _alog alog
+"#,
+        words.join(" ")
+    )
+}
+
+fn zsh_completions() -> String {
+    let mut args = String::new();
+
+    for entry in help_entries() {
+        let help = entry
+            .help
+            .replace('\'', r"'\''")
+            .replace('[', r"\[")
+            .replace(']', r"\]");
+        // repeated options can't exclude each other
+        let exclusive = match entry.short {
+            Some(c) if !entry.multiple => format!("'(-{c} --{})'", entry.long),
+            _ => String::new(),
+        };
+        let repeat = if entry.multiple { "'*'" } else { "" };
+        let names = match entry.short {
+            Some(c) => format!("{{-{c},--{}}}'", entry.long),
+            None => format!("'--{}", entry.long),
+        };
+        let value = match entry.value {
+            Some(value) if !entry.optional => {
+                let action = if !entry.possible_values.is_empty() {
+                    format!("({})", entry.possible_values.join(" "))
+                } else if entry.takes_dir() {
+                    "_files -/".to_string()
+                } else if entry.takes_files() {
+                    "_files".to_string()
+                } else {
+                    " ".to_string()
+                };
+                format!(":{value}:{action}")
+            }
+            _ => String::new(),
+        };
+        args.push_str(&format!(
+            "    {exclusive}{repeat}{names}[{help}]{value}' \\\n"
+        ));
+    }
+
+    format!("#compdef alog\n\n_arguments -s \\\n{args}    '*:input:_files'\n")
+}
+
+fn fish_completions() -> String {
+    let mut script = String::new();
+
+    for entry in help_entries() {
+        script.push_str("complete -c alog");
+        if let Some(c) = entry.short {
+            script.push_str(&format!(" -s {c}"));
+        }
+        script.push_str(&format!(" -l {}", entry.long));
+        if entry.value.is_some() && !entry.optional {
+            if !entry.possible_values.is_empty() {
+                script.push_str(&format!(" -x -a '{}'", entry.possible_values.join(" ")));
+            } else if entry.takes_dir() {
+                script.push_str(" -x -a '(__fish_complete_directories)'");
+            } else if entry.takes_files() {
+                script.push_str(" -r -F");
+            } else {
+                script.push_str(" -x");
+            }
+        }
+        script.push_str(&format!(" -d '{}'\n", entry.help.replace('\'', r"\'")));
+    }
+
+    script
+}
 
 #[cfg(unix)]
 pub fn pipe_reset() {
@@ -36,36 +543,325 @@ pub fn pipe_reset() {
 #[cfg(not(unix))]
 pub fn pipe_reset() {}
 
-fn main() -> Result<(), lexopt::Error> {
+/// Token of the running command, interrupted by the SIGINT / SIGTERM handler
+static INTERRUPT: OnceLock<alog::CancellationToken> = OnceLock::new();
+
+#[cfg(unix)]
+extern "C" fn handle_signal(signal: ::libc::c_int) {
+    if signal == ::libc::SIGHUP {
+        alog::reopen_outputs();
+    } else if let Some(token) = INTERRUPT.get() {
+        token.interrupt();
+    }
+}
+
+/// Stops reading input on SIGINT / SIGTERM (the output is still completed) and, with `hangup`
+/// set, reopens output files on SIGHUP (e.g. after logrotate moved them away)
+///
+/// Blocking reads are not restarted after a signal, they fail with `EINTR`.
+#[cfg(unix)]
+fn handle_signals(token: alog::CancellationToken, hangup: bool) {
+    let _ = INTERRUPT.set(token);
+
+    let mut signals = vec![::libc::SIGINT, ::libc::SIGTERM];
+    if hangup {
+        signals.push(::libc::SIGHUP);
+    }
+    for signal in signals {
+        // SAFETY: the handler only updates atomics, all pointers are valid for the call
+        unsafe {
+            let mut action: ::libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction =
+                handle_signal as extern "C" fn(::libc::c_int) as ::libc::sighandler_t;
+            ::libc::sigemptyset(&mut action.sa_mask);
+            ::libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn handle_signals(_: alog::CancellationToken, _: bool) {}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, factor) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid size '{s}'"))
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (digits, factor) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1)
+    } else if let Some(m) = s.strip_suffix('m') {
+        (m, 60_000)
+    } else {
+        (s.strip_suffix('s').unwrap_or(s), 1000)
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .filter(|&n| n > 0)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("invalid duration '{s}'"))
+}
+
+/// File rotated by logrotate, anonymized to `temp` and then renamed to `output`
+struct Rotation {
+    input: PathBuf,
+    temp: PathBuf,
+    output: PathBuf,
+}
+
+/// Returns where `alog rotate-hook` writes each of the `files`
+///
+/// Files stay in their directory unless `archive_dir` is set, --compress appends its extension to
+/// files which are not compressed yet.
+fn rotate_targets(
+    ioconfig: &alog::IOConfig,
+    files: &[PathBuf],
+    archive_dir: Option<&Path>,
+) -> Result<Vec<Rotation>, String> {
+    if files.is_empty() {
+        return Err("Usage: alog rotate-hook [OPTIONS] <FILE>...".to_string());
+    }
+    if ioconfig.get_input().is_some()
+        || !ioconfig.get_outputs().is_empty()
+        || ioconfig.get_output_dir().is_some()
+        || ioconfig.get_in_place()
+    {
+        return Err(
+            "rotate-hook can not be combined with --output, --output-dir or --in-place".to_string(),
+        );
+    }
+
+    let mut rotations = vec![];
+    for input in files {
+        let Some(name) = input.file_name() else {
+            return Err(format!("Can not get file name of '{}'", input.display()));
+        };
+        let mut name = name.to_os_string();
+        if alog::Compression::from_path(input) == alog::Compression::None {
+            if let Some(extension) = ioconfig.get_compression().extension() {
+                name.push(format!(".{extension}"));
+            }
+        }
+        let dir = match archive_dir {
+            Some(dir) => dir,
+            None => input.parent().unwrap_or(Path::new("")),
+        };
+        let output = dir.join(&name);
+        if &output != input && output.exists() {
+            return Err(format!("Output '{}' already exists", output.display()));
+        }
+        // keeps the extension, the temporary file is compressed like the output
+        let mut temp = OsString::from(format!(".alog-{}.", process::id()));
+        temp.push(&name);
+        rotations.push(Rotation {
+            input: input.clone(),
+            temp: dir.join(temp),
+            output,
+        });
+    }
+    Ok(rotations)
+}
+
+/// Moves the anonymized files of `alog rotate-hook` in place, removes the originals they replace
+fn finish_rotations(rotations: &[Rotation]) -> io::Result<()> {
+    for rotation in rotations {
+        std::fs::rename(&rotation.temp, &rotation.output)?;
+        if rotation.output != rotation.input {
+            std::fs::remove_file(&rotation.input)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = cli() {
+        eprintln!("Error: {e}");
+        process::exit(EXIT_USAGE);
+    }
+}
+
+fn cli() -> Result<(), lexopt::Error> {
     use lexopt::prelude::*;
 
     pipe_reset();
 
-    let mut config = alog::Config::default();
+    // declared before the builder borrowing it
+    let rejects: Option<alog::Rejects>;
+    let mut builder = alog::Config::builder();
     let mut ioconfig = alog::IOConfig::default();
+    let mut config_path: Option<OsString> = None;
 
-    let mut host_replacement = config.get_host_value().to_string();
-    let mut ipv4_replacement = config.get_ipv4_value().to_string();
-    let mut ipv6_replacement = config.get_ipv6_value().to_string();
+    let mut output: Vec<OsString> = vec![];
+    let mut backup_suffix: Option<String> = None;
+    let mut output_dir: Option<OsString> = None;
+    let mut daemon = false;
+    let mut pidfile: Option<OsString> = None;
+    let mut log_file: Option<OsString> = None;
+    let mut tty_check = true;
+    let mut print_stats = false;
+    let mut stats_json = false;
+    let mut stats_file: Option<OsString> = None;
+    let mut rejects_file: Option<OsString> = None;
+    let mut verbosity = 0;
+    let mut progress_bar = true;
+    let mut jobs: Option<usize> = None;
+    let mut archive_dir: Option<OsString> = None;
+    let mut rotated: Vec<PathBuf> = vec![];
+    let rotate_hook = env::args_os().nth(1).is_some_and(|a| a == "rotate-hook");
 
-    let mut output: Option<OsString> = None;
-    let mut input: Vec<OsString> = vec![];
+    if env::args_os().nth(1).is_some_and(|a| a == "formats") {
+        for format in alog::Format::ALL {
+            println!("{:<12}{}", format.to_string(), format.description());
+            println!("{:<12}anonymizes: {}", "", format.fields().join(", "));
+        }
+        return Ok(());
+    }
 
-    let mut parser = lexopt::Parser::from_env();
+    if env::args_os().nth(1).is_some_and(|a| a == "completions") {
+        let script = match env::args_os().nth(2).as_ref().and_then(|s| s.to_str()) {
+            Some("bash") => bash_completions(),
+            Some("zsh") => zsh_completions(),
+            Some("fish") => fish_completions(),
+            _ => {
+                eprintln!("Error: Usage: alog completions <bash|zsh|fish>");
+                process::exit(EXIT_USAGE);
+            }
+        };
+        print!("{script}");
+        return Ok(());
+    }
+
+    let env_args = env_args();
+    let env_outputs = env_args.iter().filter(|a| *a == "--output").count();
+    let args = env::args_os().skip(1 + usize::from(rotate_hook));
+    let mut parser = lexopt::Parser::from_args(env_args.into_iter().chain(args));
 
     while let Some(arg) = parser.next()? {
         match arg {
-            Short('a') | Long("authuser") => config.set_authuser(true),
-            Short('f') | Long("flush-line") => config.set_flush(true),
-            Long("no-optimize") => config.set_optimize(false),
-            Short('n') | Long("notrim") => config.set_trim(false),
-            Short('t') | Long("thorough") => config.set_thorough(true),
-            Short('s') | Long("skip-invalid") => config.set_skip(true),
-            Long("host-replacement") => host_replacement = parser.value()?.string()?,
-            Short('4') | Long("ipv4-replacement") => ipv4_replacement = parser.value()?.string()?,
-            Short('6') | Long("ipv6-replacement") => ipv6_replacement = parser.value()?.string()?,
-            Short('o') | Long("output") => output = Some(parser.value()?.parse()?),
-            Value(f) => input.push(f),
+            Long("config") => config_path = Some(parser.value()?),
+            Short('a') | Long("authuser") => builder = builder.authuser(true),
+            Short('f') | Long("flush-line") => builder = builder.flush(true),
+            Long("follow") => ioconfig.set_follow(true),
+            Short('i') | Long("in-place") => {
+                ioconfig.set_in_place(true);
+                backup_suffix = parser.optional_value().map(|s| s.string()).transpose()?;
+            }
+            Long("ip-only") => builder = builder.ip_only(true),
+            Long("keep-zone") => builder = builder.keep_zone(true),
+            Long("verify-hosts") => builder = builder.verify_hosts(true),
+            Long("keep-dash") => builder = builder.keep_dash(true),
+            Long("strip-bom") => builder = builder.strip_bom(true),
+            Long("strict") => builder = builder.strict(true),
+            Short('k') | Long("keep-going") => ioconfig.set_keep_going(true),
+            Long("no-optimize") => builder = builder.optimize(false),
+            Long("dry-run") => ioconfig.set_dry_run(
+                parser
+                    .optional_value()
+                    .map(|v| v.parse())
+                    .transpose()?
+                    .unwrap_or_default(),
+            ),
+            Long("stats") => print_stats = true,
+            Long("stats-format") => {
+                print_stats = true;
+                stats_json = match parser.value()?.string()?.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    s => {
+                        eprintln!("Error: unknown statistics format '{s}'");
+                        process::exit(EXIT_USAGE);
+                    }
+                };
+            }
+            Long("stats-file") => {
+                print_stats = true;
+                stats_file = Some(parser.value()?);
+            }
+            Long("daemon") => daemon = true,
+            Long("pidfile") => pidfile = Some(parser.value()?),
+            Long("log-file") => log_file = Some(parser.value()?),
+            Long("rejects") => rejects_file = Some(parser.value()?),
+            Long("check") => ioconfig.set_check(true),
+            Long("match") => builder = builder.match_pattern(&parser.value()?.string()?),
+            Long("comment-prefix") => builder = builder.comment_prefix(parser.value()?.string()?),
+            Long("drop-pattern") => builder = builder.drop_pattern(&parser.value()?.string()?),
+            Long("no-progress") => progress_bar = false,
+            Short('q') | Long("quiet") => verbosity = -1,
+            Short('v') | Long("verbose") => verbosity += 1,
+            Long("mmap") => ioconfig.set_mmap(true),
+            Short('p') | Long("preserve") => ioconfig.set_preserve(true),
+            Long("reopen-fifo") => ioconfig.set_reopen_fifo(true),
+            Short('n') | Long("notrim") => builder = builder.trim(false),
+            Short('t') | Long("thorough") => builder = builder.thorough(true),
+            Long("tee") => ioconfig.set_tee(true),
+            Short('s') | Long("skip-invalid") => builder = builder.skip(true),
+            Long("truncate") => ioconfig.set_output_mode(alog::OutputMode::Truncate),
+            Short('w') | Long("watch") => ioconfig.set_watch(true),
+            Long("no-clobber") => ioconfig.set_output_mode(alog::OutputMode::NoClobber),
+            Long("no-tty-check") => tty_check = false,
+            Long("syslog") => builder = builder.syslog(true),
+            Short('z') | Long("null-data") => builder = builder.null_data(true),
+            Long("host-replacement") => builder = builder.host(parser.value()?.string()?),
+            Short('4') | Long("ipv4-replacement") => {
+                builder = builder.ipv4(parser.value()?.string()?)
+            }
+            Short('6') | Long("ipv6-replacement") => {
+                builder = builder.ipv6(parser.value()?.string()?)
+            }
+            Short('o') | Long("output") => output.push(parser.value()?.parse()?),
+            Long("output-dir") => output_dir = Some(parser.value()?.parse()?),
+            Long("resume") => ioconfig.set_resume(PathBuf::from(parser.value()?)),
+            Long("listen") => ioconfig.set_listen(parser.value()?.string()?),
+            Long("journal") => ioconfig.set_journal(true),
+            Long("journal-field") => ioconfig.push_journal_field(parser.value()?.string()?),
+            Long("journal-match") => ioconfig.push_journal_match(parser.value()?.string()?),
+            Long("rotate-name") => ioconfig.set_rotate_name(parser.value()?.string()?),
+            Long("block-size") => {
+                builder = builder.block_size(
+                    usize::try_from(parser.value()?.parse_with(parse_size)?).unwrap_or(usize::MAX),
+                );
+            }
+            Long("max-line-len") => {
+                builder = builder.max_line_len(
+                    usize::try_from(parser.value()?.parse_with(parse_size)?).unwrap_or(usize::MAX),
+                );
+            }
+            Long("max-lines") => ioconfig.set_max_lines(parser.value()?.parse()?),
+            Long("max-bytes") => ioconfig.set_max_bytes(parser.value()?.parse_with(parse_size)?),
+            Long("skip-fields") => builder = builder.skip_fields(parser.value()?.parse()?),
+            Short('j') | Long("jobs") => jobs = Some(parser.value()?.parse()?),
+            Long("progress-interval") => {
+                ioconfig.set_progress_interval(parser.value()?.parse_with(parse_duration)?)
+            }
+            Long("oversized") => builder = builder.oversized(parser.value()?.parse()?),
+            Long("rotate-time") => ioconfig.set_rotate_time(parser.value()?.parse()?),
+            Long("rotate-size") => {
+                ioconfig.set_rotate_size(parser.value()?.parse_with(parse_size)?)
+            }
+            Long("format") => builder = builder.format(parser.value()?.parse()?),
+            Long("separators") => {
+                builder = builder.separators(parser.value()?.string()?.replace("\\t", "\t"))
+            }
+            Long("compress") => ioconfig.set_compression(parser.value()?.parse()?),
+            Long("archive-dir") => archive_dir = Some(parser.value()?),
+            Value(f) if rotate_hook => rotated.push(PathBuf::from(f)),
+            Value(f) if f == "-" => ioconfig.push_stdin(),
+            Value(f) => ioconfig.push_input_owned(f),
             Short('h') | Long("help") => {
                 println!("{HELP}");
                 process::exit(0);
@@ -78,23 +874,206 @@ fn main() -> Result<(), lexopt::Error> {
         }
     }
 
-    config.set_host_value(&host_replacement);
-    config.set_ipv4_value(&ipv4_replacement);
-    config.set_ipv6_value(&ipv6_replacement);
+    // The config file is the lowest layer, below the environment and the command line
+    if let Some(path) = config_path {
+        let file = alog::ConfigBuilder::from_path(&path).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            process::exit(exit_status(&e));
+        });
+        builder = builder.over(file);
+    }
 
-    let opath = output.unwrap_or_default();
-    if !opath.is_empty() {
-        ioconfig.set_output(Path::new(opath.as_os_str()));
+    // Use all CPUs unless set on the command line or in a config file, `0` uses all CPUs as well
+    let jobs = match jobs.or(builder.get_jobs()) {
+        None | Some(0) => std::thread::available_parallelism().map_or(1, usize::from),
+        Some(n) => n,
+    };
+    builder = builder.jobs(jobs);
+
+    let logger = match verbosity {
+        ..=-1 => None,
+        0 => Some(alog::StderrLog::new(alog::Level::Warn)),
+        1 => Some(alog::StderrLog::new(alog::Level::Info)),
+        _ => Some(alog::StderrLog::new(alog::Level::Debug)),
+    };
+    if let Some(logger) = &logger {
+        builder = builder.logger(logger);
     }
 
-    for i in &input {
-        ioconfig.push_input(i);
+    // Reading interactively, hint at usage and write every line immediately
+    if ioconfig.get_input().is_none()
+        && ioconfig.get_listen().is_none()
+        && !ioconfig.get_journal()
+        && io::stdin().is_terminal()
+    {
+        if tty_check && logger.is_some() {
+            eprintln!(
+                "Reading from terminal, see `alog --help` or use --no-tty-check to hide this hint"
+            );
+        }
+        builder = builder.flush(true);
     }
 
-    if let Err(e) = alog::run(&config, &ioconfig) {
-        eprintln!("Error: {e}");
-        process::exit(1);
+    // Skipped lines are always counted for the exit status
+    let stats = if print_stats {
+        alog::Stats::new()
+    } else {
+        alog::Stats::skips_only()
     };
+    rejects = rejects_file.as_ref().map(|path| {
+        let file = std::fs::File::create(path).unwrap_or_else(|e| {
+            eprintln!(
+                "Error: Can not open rejects file '{}': {e}",
+                Path::new(path).display()
+            );
+            process::exit(EXIT_IO);
+        });
+        alog::Rejects::new(io::BufWriter::new(file))
+    });
+    if let Some(rejects) = &rejects {
+        builder = builder.rejects(rejects);
+    }
+    let interrupt = alog::CancellationToken::new();
+    let config = builder
+        .stats(&stats)
+        .cancel(interrupt.clone())
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            process::exit(exit_status(&e));
+        });
 
-    Ok(())
+    // Outputs on the command line replace the output from the environment
+    if output.len() > env_outputs {
+        output.drain(..env_outputs);
+    }
+
+    for o in output.into_iter().filter(|o| !o.is_empty()) {
+        ioconfig.push_output(PathBuf::from(o));
+    }
+
+    if let Some(odir) = output_dir.filter(|d| !d.is_empty()) {
+        ioconfig.set_output_dir(PathBuf::from(odir));
+    }
+
+    if let Some(suffix) = backup_suffix {
+        ioconfig.set_backup_suffix(suffix);
+    }
+
+    // Every rotated file is anonymized to a temporary file next to its destination, which is
+    // only renamed once all files are done
+    let archive_dir = archive_dir.filter(|d| !d.is_empty()).map(PathBuf::from);
+    let rotations = if rotate_hook {
+        let rotations =
+            rotate_targets(&ioconfig, &rotated, archive_dir.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                process::exit(EXIT_USAGE);
+            });
+        for rotation in &rotations {
+            ioconfig.add_pair(rotation.input.clone(), rotation.temp.clone());
+        }
+        ioconfig.set_preserve(true);
+        ioconfig.set_output_mode(alog::OutputMode::NoClobber);
+        rotations
+    } else if archive_dir.is_some() {
+        eprintln!("Error: --archive-dir requires `alog rotate-hook`");
+        process::exit(EXIT_USAGE);
+    } else {
+        vec![]
+    };
+
+    if daemon {
+        let reads_stdin = match ioconfig.get_input() {
+            Some(input) => input.contains(&alog::Input::Stdin),
+            None => {
+                ioconfig.get_listen().is_none() && !ioconfig.get_watch() && !ioconfig.get_journal()
+            }
+        };
+        let error = if reads_stdin {
+            Some((EXIT_USAGE, "--daemon can not read from Stdin".to_string()))
+        } else if ioconfig.get_outputs().is_empty() && ioconfig.get_output_dir().is_none() {
+            Some((
+                EXIT_USAGE,
+                "--daemon requires --output or --output-dir".to_string(),
+            ))
+        } else {
+            daemonize(log_file.as_deref().map(Path::new))
+                .err()
+                .map(|e| (EXIT_IO, format!("Can not start daemon: {e}")))
+        };
+        if let Some((status, e)) = error {
+            eprintln!("Error: {e}");
+            process::exit(status);
+        }
+    }
+
+    if let Some(path) = &pidfile {
+        if let Err(e) = std::fs::write(path, format!("{}\n", process::id())) {
+            eprintln!(
+                "Error: Can not write pidfile '{}': {e}",
+                Path::new(path).display()
+            );
+            process::exit(EXIT_IO);
+        }
+    }
+
+    // Inputs are checked to be regular files by the library, anonymized lines must not be
+    // written to the same terminal
+    ioconfig.set_progress_bar(
+        progress_bar
+            && logger.is_some()
+            && io::stderr().is_terminal()
+            && (!ioconfig.get_outputs().is_empty() || !io::stdout().is_terminal()),
+    );
+
+    handle_signals(
+        interrupt,
+        ioconfig.get_follow() || ioconfig.get_listen().is_some(),
+    );
+    let result = alog::run(&config, &ioconfig).and_then(|()| {
+        if let Some(rejects) = &rejects {
+            rejects.flush()?;
+        }
+        Ok(())
+    });
+    let result = match result {
+        Ok(()) => finish_rotations(&rotations).map_err(alog::Error::from),
+        Err(e) => {
+            // originals are left untouched, drop partial results
+            for rotation in &rotations {
+                let _ = std::fs::remove_file(&rotation.temp);
+            }
+            Err(e)
+        }
+    };
+    if let Some(path) = &pidfile {
+        let _ = std::fs::remove_file(path);
+    }
+    if print_stats {
+        let report = if stats_json {
+            stats.to_json()
+        } else {
+            stats.summary().to_string()
+        };
+        match &stats_file {
+            Some(path) => {
+                if let Err(e) = std::fs::write(path, format!("{report}\n")) {
+                    eprintln!(
+                        "Error: Can not write statistics to '{}': {e}",
+                        Path::new(path).display()
+                    );
+                    process::exit(EXIT_IO);
+                }
+            }
+            None => eprintln!("{report}"),
+        }
+    }
+    match result {
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(exit_status(&e));
+        }
+        Ok(()) if stats.skipped() > 0 => process::exit(EXIT_SKIPPED),
+        Ok(()) => Ok(()),
+    }
 }
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Pseudonyms assigned to original values, e.g. by a [`LineScrubber`](crate::LineScrubber)
+/// replacing every value by the same pseudonym
+///
+/// Clones share the same mapping, so a store can be used by worker threads (see
+/// [`Config::jobs`](crate::Config::jobs)) and by several [`run_raw`](crate::run_raw) calls, all
+/// of them assigning the same pseudonym to the same value.
+///
+/// ```
+/// use alog::{LineCtx, MappingStore};
+///
+/// let users = MappingStore::new();
+/// let scrubber = {
+///     let users = users.clone();
+///     move |line: &mut Vec<u8>, _ctx: &LineCtx| {
+///         let user = line.split_off(line.iter().rposition(|&b| b == b' ').unwrap() + 1);
+///         line.extend(users.get_or_insert_with(&user, |n| format!("user{n}").into_bytes()));
+///     }
+/// };
+/// let config = alog::Config::builder().scrubber(scrubber).build().unwrap();
+///
+/// let mut buffer = vec![];
+/// alog::run_raw(&config, &b"::1 alice\n::1 bob\n"[..], &mut buffer).unwrap();
+/// alog::run_raw(&config, &b"::1 alice\n"[..], &mut buffer).unwrap();
+/// assert_eq!(buffer, b"::1 user0\n::1 user1\n::1 user0\n");
+/// assert_eq!(users.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MappingStore(Arc<RwLock<HashMap<Vec<u8>, Vec<u8>>>>);
+
+impl MappingStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pseudonym of `original`, `None` if none was assigned yet
+    #[must_use]
+    pub fn get(&self, original: &[u8]) -> Option<Vec<u8>> {
+        let map = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        map.get(original).cloned()
+    }
+
+    /// Returns the pseudonym of `original`, assigns the one returned by `f` if there is none yet
+    ///
+    /// `f` is called with the number of values mapped so far, e.g. to number pseudonyms. If
+    /// several threads map the same value concurrently all of them get the first pseudonym.
+    pub fn get_or_insert_with(&self, original: &[u8], f: impl FnOnce(usize) -> Vec<u8>) -> Vec<u8> {
+        if let Some(pseudonym) = self.get(original) {
+            return pseudonym;
+        }
+        let mut map = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let n = map.len();
+        map.entry(original.to_vec()).or_insert_with(|| f(n)).clone()
+    }
+
+    /// Returns the number of values mapped
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets all pseudonyms, in every clone of this store
+    pub fn clear(&self) {
+        self.0
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
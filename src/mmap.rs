@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::{ptr, slice};
+
+use crate::progress::Progress;
+use crate::{oversized, replace_record, Config, Malformed};
+
+/// Read-only memory map of a whole file
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        if len == 0 {
+            return Ok(Mmap {
+                ptr: ptr::null_mut(),
+                len,
+            });
+        }
+
+        // SAFETY: mapping a file descriptor we own read-only, the result is checked below
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap { ptr, len })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: `ptr` points to `len` mapped, readable bytes until the map is dropped
+        unsafe { slice::from_raw_parts(self.ptr.cast::<u8>(), self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: `ptr` and `len` are the result of a successful `mmap` call
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+/// Anonymizes the regular file `file`, scanning for line boundaries in a memory map instead of
+/// copying every line
+///
+/// The file must not be truncated while it is being processed, this will terminate the process
+/// (`SIGBUS`) instead of returning an error.
+///
+/// Returns the number of bytes processed, less than the file size if a signal was received.
+pub(crate) fn replace<W: Write>(
+    config: &Config,
+    file: &File,
+    mut writer: W,
+    progress: Option<&Progress>,
+) -> io::Result<u64> {
+    let map = Mmap::map(file)?;
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+    let mut scratch = vec![];
+    let max = config.get_max_line_len().unwrap_or(usize::MAX);
+
+    let mut offset = 0;
+
+    for (line_no, line) in (1..).zip(map.split_inclusive(|&b| b == delimiter)) {
+        if config.is_interrupted() {
+            break;
+        }
+        if let Some(progress) = progress {
+            progress.count(line);
+        }
+        if line.len() > max {
+            oversized(config, line, &mut writer)?;
+        } else {
+            replace_record(config, line, &mut scratch, &mut writer)
+                .map_err(|e| Malformed::shift(e, line_no))?;
+        }
+        offset += line.len() as u64;
+    }
+
+    writer.flush()?;
+    Ok(offset)
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use crate::Address;
+
+/// Receiver of every processed line, see [`Config::set_observer`](crate::Config::set_observer)
+///
+/// Lines are reported from worker threads as well, in no particular order.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use alog::{Address, LineInfo};
+///
+/// let hosts = AtomicUsize::new(0);
+/// let observer = |line: &LineInfo| {
+///     if line.replaced() == Some(Address::Host) {
+///         hosts.fetch_add(1, Ordering::Relaxed);
+///     }
+/// };
+/// let config = alog::Config::builder().observer(&observer).build().unwrap();
+/// alog::run_raw(&config, &b"example.com a\n8.8.8.8 b\n"[..], &mut vec![]).unwrap();
+/// assert_eq!(hosts.load(Ordering::Relaxed), 1);
+/// ```
+pub trait LineObserver: Send + Sync {
+    /// Handles a single line, after it was written, skipped or dropped
+    fn observe(&self, line: &LineInfo);
+}
+
+impl<F: Fn(&LineInfo) + Send + Sync> LineObserver for F {
+    fn observe(&self, line: &LineInfo) {
+        self(line);
+    }
+}
+
+impl fmt::Debug for dyn LineObserver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineObserver")
+    }
+}
+
+/// What happened to a line, see [`LineInfo::outcome`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Written,
+    /// Not written, e.g. no first *word* or an oversized line
+    Skipped,
+    /// Not written on purpose, by a pattern or a [`LineScrubber`](crate::LineScrubber)
+    Dropped,
+}
+
+/// Metadata of a processed line passed to [`LineObserver::observe`]
+#[derive(Debug, Clone, Copy)]
+pub struct LineInfo<'l> {
+    pub(crate) original: &'l [u8],
+    pub(crate) outcome: Outcome,
+    pub(crate) modified: bool,
+    pub(crate) replaced: Option<Address>,
+    pub(crate) word_len: usize,
+}
+
+impl<'l> LineInfo<'l> {
+    /// Returns the line as read from the input, including its terminator
+    #[must_use]
+    pub fn original(&self) -> &'l [u8] {
+        self.original
+    }
+
+    #[must_use]
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Returns `true` if the line was written with any change
+    #[must_use]
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Returns the kind of the first *word* if it was replaced
+    ///
+    /// Always `None` for formats without a leading `$remote_addr`, e.g.
+    /// [`Format::Sshd`](crate::Format::Sshd).
+    #[must_use]
+    pub fn replaced(&self) -> Option<Address> {
+        self.replaced
+    }
+
+    /// Returns the length of the original first *word* in bytes, after any syslog envelope
+    #[must_use]
+    pub fn word_len(&self) -> usize {
+        self.word_len
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+
+use crate::{replace_remote_address, Config, Malformed, Rejects};
+
+/// Lines are passed to the workers in chunks of at least this many bytes
+const CHUNK_SIZE: usize = 256 << 10;
+
+/// Anonymized chunk of lines
+struct Done {
+    out: Vec<u8>,
+    /// Lines for [`Config::rejects`]
+    rejected: Vec<u8>,
+    /// Number of lines in `rejected`
+    count: u64,
+}
+
+/// Anonymizes `reader` with [`Config::jobs`] worker threads, output and rejected lines are
+/// written in input order
+///
+/// The current thread reads chunks of complete lines and writes the results, at most two chunks
+/// per worker are in flight at any time.
+pub(crate) fn replace<R: BufRead, W: Write>(
+    config: &Config,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()> {
+    let jobs = config.get_jobs();
+    let worker_config = Config {
+        jobs: 1,
+        ..config.clone()
+    };
+    let delimiter = if config.get_null_data() { b'\0' } else { b'\n' };
+
+    let (chunk_tx, chunk_rx) = mpsc::channel::<(usize, Vec<u8>)>();
+    let (result_tx, result_rx) = mpsc::channel::<(usize, io::Result<Done>)>();
+    let chunk_rx = Mutex::new(chunk_rx);
+
+    thread::scope(|s| {
+        for _ in 0..jobs {
+            let result_tx = result_tx.clone();
+            let (chunk_rx, config) = (&chunk_rx, &worker_config);
+            s.spawn(move || loop {
+                let Ok(Ok((seq, chunk))) = chunk_rx.lock().map(|rx| rx.recv()) else {
+                    break;
+                };
+                let result = replace_chunk(config, &chunk);
+                if result_tx.send((seq, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_tx);
+
+        let mut pending = BTreeMap::new();
+        // number of lines before each chunk, only counted for the line numbers of strict mode
+        let mut lines_before = BTreeMap::new();
+        let mut lines = 0;
+        let (mut sent, mut written) = (0, 0);
+        let mut eof = false;
+
+        let result = loop {
+            // keep the workers busy
+            while !eof && sent - written < 2 * jobs {
+                let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+                while chunk.len() < CHUNK_SIZE {
+                    match reader.read_until(delimiter, &mut chunk) {
+                        Ok(0) => {
+                            eof = true;
+                            break;
+                        }
+                        Ok(_) => (),
+                        Err(e) => {
+                            drop(chunk_tx);
+                            return Err(e);
+                        }
+                    }
+                }
+                if chunk.is_empty() {
+                    break;
+                }
+                if config.get_strict() {
+                    lines_before.insert(sent, lines);
+                    lines += memchr::memchr_iter(delimiter, &chunk).count() as u64;
+                }
+                if chunk_tx.send((sent, chunk)).is_err() {
+                    break;
+                }
+                sent += 1;
+            }
+
+            if written == sent {
+                break Ok(());
+            }
+
+            // write results in input order
+            let Ok((seq, out)) = result_rx.recv() else {
+                break Err(io::Error::other("worker thread stopped"));
+            };
+            pending.insert(seq, out);
+            while let Some(out) = pending.remove(&written) {
+                let lines = lines_before.remove(&written).unwrap_or_default();
+                let out = out.map_err(|e| Malformed::shift(e, lines));
+                if let Err(e) = out.and_then(|done| write_chunk(config, &mut writer, &done)) {
+                    drop(chunk_tx);
+                    return Err(e);
+                }
+                written += 1;
+            }
+        };
+
+        drop(chunk_tx);
+        result?;
+        writer.flush()
+    })
+}
+
+/// Anonymizes `chunk`, rejected lines are collected to be written in input order
+fn replace_chunk(config: &Config, chunk: &[u8]) -> io::Result<Done> {
+    let mut out = Vec::with_capacity(chunk.len());
+    if config.get_rejects().is_none() {
+        replace_remote_address(config, chunk, &mut out)?;
+        return Ok(Done {
+            out,
+            rejected: vec![],
+            count: 0,
+        });
+    }
+
+    let buffer = Buffer::default();
+    let rejects = Rejects::new(buffer.clone());
+    let config = Config {
+        rejects: Some(&rejects),
+        ..config.clone()
+    };
+    replace_remote_address(&config, chunk, &mut out)?;
+    let rejected = mem::take(&mut *buffer.0.lock().unwrap_or_else(PoisonError::into_inner));
+    Ok(Done {
+        out,
+        rejected,
+        count: rejects.count(),
+    })
+}
+
+/// Writes the output and rejected lines of a chunk
+fn write_chunk<W: Write>(config: &Config, writer: &mut W, done: &Done) -> io::Result<()> {
+    writer.write_all(&done.out)?;
+    match config.get_rejects() {
+        Some(rejects) if done.count > 0 => rejects.write_lines(&done.rejected, done.count),
+        _ => Ok(()),
+    }
+}
+
+/// Rejected lines of a single chunk
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::{Config, LineCtx, LineScrubber};
+
+type Filter<'a> = Box<dyn Fn(&[u8], &LineCtx) -> bool + Send + Sync + 'a>;
+
+enum Kind<'a> {
+    Scrub(Box<dyn LineScrubber + 'a>),
+    Filter(Filter<'a>),
+}
+
+/// Named step of a [`Pipeline`], counting the lines passing through it
+pub struct Stage<'a> {
+    name: String,
+    kind: Kind<'a>,
+    lines: AtomicU64,
+    modified: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Stage<'_> {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of lines which reached this stage
+    #[must_use]
+    pub fn lines(&self) -> u64 {
+        self.lines.load(Ordering::Relaxed)
+    }
+
+    /// Number of lines changed by this stage
+    #[must_use]
+    pub fn modified(&self) -> u64 {
+        self.modified.load(Ordering::Relaxed)
+    }
+
+    /// Number of lines dropped by this stage, later stages never see them
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for Stage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stage")
+            .field("name", &self.name)
+            .field("lines", &self.lines())
+            .field("modified", &self.modified())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl LineScrubber for Stage<'_> {
+    fn scrub(&self, line: &mut Vec<u8>, ctx: &LineCtx) {
+        self.process(line, ctx);
+    }
+
+    fn process(&self, line: &mut Vec<u8>, ctx: &LineCtx) -> bool {
+        self.lines.fetch_add(1, Ordering::Relaxed);
+        let keep = match &self.kind {
+            Kind::Scrub(scrubber) => {
+                let before = line.clone();
+                let keep = scrubber.process(line, ctx);
+                if keep && *line != before {
+                    self.modified.fetch_add(1, Ordering::Relaxed);
+                }
+                keep
+            }
+            Kind::Filter(filter) => filter(line, ctx),
+        };
+        if !keep {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        keep
+    }
+}
+
+/// [`Config`] followed by an ordered list of [`Stage`]s, accepted by [`run`](crate::run) and
+/// [`run_raw`](crate::run_raw) in place of a plain `Config`
+///
+/// Every line is anonymized by `config` (including its own
+/// [`scrubbers`](Config::push_scrubber)) first, then passed through the stages in the order
+/// they were added. A stage dropping a line ends the pipeline for that line.
+///
+/// ```
+/// use alog::{LineCtx, Pipeline};
+///
+/// let pipeline = Pipeline::new(alog::Config::default())
+///     .filter("health checks", |line: &[u8], _: &LineCtx| !line.ends_with(b"/health"))
+///     .scrubber("query strings", |line: &mut Vec<u8>, _: &LineCtx| {
+///         if let Some(p) = line.iter().position(|&b| b == b'?') {
+///             line.truncate(p);
+///         }
+///     });
+///
+/// let mut buffer = vec![];
+/// let input = &b"8.8.8.8 GET /health\n8.8.4.4 GET /?id=1\n"[..];
+/// alog::run_raw(&pipeline, input, &mut buffer).unwrap();
+/// assert_eq!(buffer, b"127.0.0.1 GET /\n");
+/// assert_eq!(pipeline.stages()[0].dropped(), 1);
+/// assert_eq!(pipeline.stages()[1].modified(), 1);
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Pipeline<'a> {
+    config: Config<'a>,
+    stages: Vec<Arc<Stage<'a>>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(config: Config<'a>) -> Self {
+        Pipeline {
+            config,
+            stages: vec![],
+        }
+    }
+
+    /// Appends a stage modifying lines with `scrubber`
+    pub fn scrubber(self, name: &str, scrubber: impl LineScrubber + 'a) -> Self {
+        self.stage(name, Kind::Scrub(Box::new(scrubber)))
+    }
+
+    /// Appends a stage dropping all lines for which `keep` returns `false`
+    pub fn filter(
+        self,
+        name: &str,
+        keep: impl Fn(&[u8], &LineCtx) -> bool + Send + Sync + 'a,
+    ) -> Self {
+        self.stage(name, Kind::Filter(Box::new(keep)))
+    }
+
+    fn stage(mut self, name: &str, kind: Kind<'a>) -> Self {
+        let stage = Arc::new(Stage {
+            name: name.to_string(),
+            kind,
+            lines: AtomicU64::new(0),
+            modified: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        self.config.scrubbers.push(stage.clone());
+        self.stages.push(stage);
+        self
+    }
+
+    /// Returns all stages in order, e.g. to read their counters after a run
+    #[must_use]
+    pub fn stages(&self) -> &[Arc<Stage<'a>>] {
+        &self.stages
+    }
+
+    /// Returns the config including all stages
+    #[must_use]
+    pub fn config(&self) -> &Config<'a> {
+        &self.config
+    }
+}
+
+impl<'a> AsRef<Config<'a>> for Pipeline<'a> {
+    fn as_ref(&self) -> &Config<'a> {
+        &self.config
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Progress bar redraw interval
+const BAR_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Width of the progress bar in characters
+const BAR_WIDTH: usize = 30;
+
+/// Bytes and lines read from all inputs of a run
+#[derive(Debug)]
+pub(crate) struct Progress {
+    bytes: AtomicU64,
+    lines: AtomicU64,
+    delimiter: u8,
+    start: Instant,
+}
+
+impl Progress {
+    pub(crate) fn new(delimiter: u8) -> Self {
+        Progress {
+            bytes: AtomicU64::new(0),
+            lines: AtomicU64::new(0),
+            delimiter,
+            start: Instant::now(),
+        }
+    }
+
+    /// Records `buf` as read
+    pub(crate) fn count(&self, buf: &[u8]) {
+        let lines = memchr::memchr_iter(self.delimiter, buf).count();
+        self.bytes.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        self.lines.fetch_add(lines as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> (u64, u64) {
+        (
+            self.bytes.load(Ordering::Relaxed),
+            self.lines.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Prints a report to stderr every `interval` until `stop` is dropped or sent to
+    pub(crate) fn report(&self, interval: Duration, stop: &mpsc::Receiver<()>) {
+        let (mut last, mut last_time) = ((0, 0), Instant::now());
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+            let (now, now_time) = (self.get(), Instant::now());
+            eprintln!(
+                "alog: {} ({})",
+                totals(now.0, now.1),
+                rates(now.0 - last.0, now.1 - last.1, now_time - last_time)
+            );
+            (last, last_time) = (now, now_time);
+        }
+    }
+
+    /// Draws a progress bar with ETA on stderr until `stop` is dropped or sent to, `total` being
+    /// the size of all inputs
+    ///
+    /// Compressed inputs are counted decompressed, so the bar stops at 100 % early.
+    pub(crate) fn bar(&self, total: u64, stop: &mpsc::Receiver<()>) {
+        let mut stderr = io::stderr();
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop.recv_timeout(BAR_INTERVAL) {
+            let (bytes, _) = self.get();
+            let fraction = (bytes as f64 / total.max(1) as f64).min(1.0);
+            let filled = (fraction * BAR_WIDTH as f64) as usize;
+            let elapsed = self.start.elapsed().as_secs_f64();
+            let eta = match bytes {
+                0 => "--:--:--".to_string(),
+                _ => {
+                    let secs = elapsed * total.saturating_sub(bytes) as f64 / bytes as f64;
+                    let secs = secs as u64;
+                    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+                }
+            };
+            let _ = write!(
+                stderr,
+                "\r\x1b[K[{}{}] {:3.0} % {} / {} ETA {eta}",
+                "=".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled),
+                fraction * 100.0,
+                human_size(bytes as f64),
+                human_size(total as f64)
+            );
+            let _ = stderr.flush();
+        }
+        let _ = write!(stderr, "\r\x1b[K");
+    }
+
+    /// Prints the totals and average throughput to stderr
+    pub(crate) fn summary(&self) {
+        let (bytes, lines) = self.get();
+        let elapsed = self.start.elapsed();
+        eprintln!(
+            "alog: {} in {:.1}s ({})",
+            totals(bytes, lines),
+            elapsed.as_secs_f64(),
+            rates(bytes, lines, elapsed)
+        );
+    }
+}
+
+fn totals(bytes: u64, lines: u64) -> String {
+    format!("{}, {lines} lines", human_size(bytes as f64))
+}
+
+fn rates(bytes: u64, lines: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    format!(
+        "{}/s, {:.0} lines/s",
+        human_size(bytes as f64 / secs),
+        lines as f64 / secs
+    )
+}
+
+fn human_size(mut size: f64) -> String {
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TiB")
+}
+
+/// Reader recording all consumed bytes in `progress`, if any
+pub(crate) struct Counter<'p, R> {
+    pub(crate) reader: R,
+    pub(crate) progress: Option<&'p Progress>,
+}
+
+impl<R: BufRead> Read for Counter<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counter<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(progress) = self.progress.filter(|_| amt > 0) {
+            if let Ok(buf) = self.reader.fill_buf() {
+                progress.count(&buf[..amt]);
+            }
+        }
+        self.reader.consume(amt);
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyIterator, PyString};
+
+use crate::writer::write_line;
+use crate::{run_summary, Config, Error, ErrorKind, IOConfig, Summary};
+
+/// Raises `ValueError` for invalid configs and `OSError` otherwise
+fn py_err(error: Error) -> PyErr {
+    match error.kind() {
+        ErrorKind::Usage => PyValueError::new_err(error.to_string()),
+        _ => PyOSError::new_err(error.to_string()),
+    }
+}
+
+/// Anonymization settings, see `alog::Config`
+#[pyclass(name = "Config", module = "alog", frozen)]
+struct PyConfig(Config<'static>);
+
+impl PyConfig {
+    fn validated(config: Result<Config<'static>, Error>) -> PyResult<Self> {
+        let config = config.map_err(py_err)?;
+        config.validate().map_err(|e| py_err(Error::Invalid(e)))?;
+        Ok(PyConfig(config))
+    }
+}
+
+#[pymethods]
+impl PyConfig {
+    /// Reads the settings from a TOML document, missing keys keep their default value
+    #[new]
+    #[pyo3(signature = (toml = ""))]
+    fn new(toml: &str) -> PyResult<Self> {
+        Self::validated(Config::from_toml(toml))
+    }
+
+    /// Reads the settings from a TOML file
+    #[staticmethod]
+    fn from_path(path: PathBuf) -> PyResult<Self> {
+        Self::validated(Config::from_path(path))
+    }
+}
+
+/// Returns the anonymized `line`, `None` if it was skipped or dropped
+fn anonymize(config: &Config, line: &[u8], scratch: &mut Vec<u8>) -> PyResult<Option<Vec<u8>>> {
+    let mut output = vec![];
+    write_line(config, line, scratch, &mut output).map_err(|e| py_err(e.into()))?;
+    Ok((!output.is_empty()).then_some(output))
+}
+
+/// Anonymizes a `str` or `bytes` line, returns `None` if it was skipped or dropped
+///
+/// `str` lines are returned as `str`, all others as `bytes`.
+fn anonymize_object<'py>(
+    config: &Config,
+    line: &Bound<'py, PyAny>,
+    scratch: &mut Vec<u8>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let py = line.py();
+    if let Ok(line) = line.downcast::<PyString>() {
+        let output = anonymize(config, line.to_str()?.as_bytes(), scratch)?;
+        return Ok(output.map(|o| PyString::new(py, &String::from_utf8_lossy(&o)).into_any()));
+    }
+    let output = anonymize(config, line.extract()?, scratch)?;
+    Ok(output.map(|o| PyBytes::new(py, &o).into_any()))
+}
+
+/// Anonymizes a single line
+#[pyfunction]
+#[pyo3(signature = (line, config = None))]
+fn anonymize_line<'py>(
+    line: &Bound<'py, PyAny>,
+    config: Option<PyRef<PyConfig>>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let default = Config::default();
+    let config = config.as_ref().map_or(&default, |c| &c.0);
+    anonymize_object(config, line, &mut vec![])
+}
+
+/// Anonymizes the file `input` to `output` (stdout if `None`), returns the counters as a dict
+#[pyfunction]
+#[pyo3(signature = (input, output = None, config = None))]
+fn anonymize_file<'py>(
+    py: Python<'py>,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    config: Option<PyRef<PyConfig>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let default = Config::default();
+    let config = config.as_ref().map_or(&default, |c| &c.0);
+    let summary = py.allow_threads(|| {
+        let mut ioconfig = IOConfig::default();
+        ioconfig.push_input(&input);
+        if let Some(output) = &output {
+            ioconfig.set_output(output);
+        }
+        run_summary(config, &ioconfig)
+    });
+    summary_dict(py, &summary.map_err(py_err)?)
+}
+
+fn summary_dict<'py>(py: Python<'py>, summary: &Summary) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("lines_read", summary.lines_read)?;
+    dict.set_item("lines_modified", summary.lines_modified)?;
+    dict.set_item("skipped", summary.skipped)?;
+    dict.set_item("dropped", summary.dropped)?;
+    dict.set_item("ipv4", summary.ipv4)?;
+    dict.set_item("ipv6", summary.ipv6)?;
+    dict.set_item("hosts", summary.hosts)?;
+    dict.set_item("authuser", summary.authuser)?;
+    dict.set_item("distinct_addresses", summary.distinct_addresses)?;
+    dict.set_item("distinct_users", summary.distinct_users)?;
+    dict.set_item("bytes_read", summary.bytes_read)?;
+    dict.set_item("bytes_written", summary.bytes_written)?;
+    Ok(dict)
+}
+
+/// Iterator over the anonymized lines of another iterator, see `anonymize_lines`
+#[pyclass(module = "alog")]
+struct Lines {
+    lines: Py<PyIterator>,
+    config: Config<'static>,
+    scratch: Vec<u8>,
+}
+
+#[pymethods]
+impl Lines {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let mut lines = self.lines.bind(py).clone();
+        for line in &mut lines {
+            if let Some(output) = anonymize_object(&self.config, &line?, &mut self.scratch)? {
+                return Ok(Some(output));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Returns an iterator over the anonymized `str` or `bytes` lines of `lines`, e.g. a file
+///
+/// Skipped and dropped lines are left out.
+#[pyfunction]
+#[pyo3(signature = (lines, config = None))]
+fn anonymize_lines(lines: &Bound<PyAny>, config: Option<PyRef<PyConfig>>) -> PyResult<Lines> {
+    Ok(Lines {
+        lines: lines.try_iter()?.unbind(),
+        config: config.map(|c| c.0.clone()).unwrap_or_default(),
+        scratch: vec![],
+    })
+}
+
+#[pymodule]
+pub(crate) fn alog(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<PyConfig>()?;
+    m.add_class::<Lines>()?;
+    m.add_function(wrap_pyfunction!(anonymize_line, m)?)?;
+    m.add_function(wrap_pyfunction!(anonymize_file, m)?)?;
+    m.add_function(wrap_pyfunction!(anonymize_lines, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::io::{self, BufRead, Read};
+
+use crate::{next_line, Config};
+
+/// Reader anonymizing `reader` line by line as it is read, see [`lines`](crate::lines)
+///
+/// Only a single anonymized line is buffered at a time.
+///
+/// ## Example
+///
+/// ```
+/// use std::io::Read;
+///
+/// let config = alog::Config::default();
+/// let mut reader = alog::AnonymizingReader::new(&b"8.8.8.8 a\n::2 b\n"[..], &config);
+/// let mut output = String::new();
+/// reader.read_to_string(&mut output).unwrap();
+/// assert_eq!(output, "127.0.0.1 a\n::1 b\n");
+/// ```
+#[derive(Debug)]
+pub struct AnonymizingReader<'c, R> {
+    reader: R,
+    config: &'c Config<'c>,
+    buf: Vec<u8>,
+    scratch: Vec<u8>,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<'c, R: BufRead> AnonymizingReader<'c, R> {
+    pub fn new(reader: R, config: &'c impl AsRef<Config<'c>>) -> Self {
+        AnonymizingReader {
+            reader,
+            config: config.as_ref(),
+            buf: vec![],
+            scratch: vec![],
+            line: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Returns the wrapped reader, any buffered line is lost
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Read for AnonymizingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for AnonymizingReader<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if let Err(e) = next_line(
+                self.config,
+                &mut self.reader,
+                &mut self.buf,
+                &mut self.scratch,
+                &mut self.line,
+            ) {
+                self.line.clear();
+                return Err(e);
+            }
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.line.len());
+    }
+}
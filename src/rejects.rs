@@ -0,0 +1,77 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Receiver of lines rejected by [`Config::skip`](crate::Config::skip) or
+/// [`Config::strict`](crate::Config::strict), see [`Config::set_rejects`](crate::Config::set_rejects)
+///
+/// Lines are written as read from the input, including their terminator, in input order.
+/// Inputs processed concurrently share the writer, so lines of different inputs may be
+/// interleaved.
+///
+/// ```
+/// let rejects = alog::Rejects::new(vec![]);
+/// let config = alog::Config::builder()
+///     .skip(true)
+///     .rejects(&rejects)
+///     .build()
+///     .unwrap();
+/// let mut buffer = vec![];
+/// alog::run_raw(&config, &b"8.8.8.8 a\n\n"[..], &mut buffer).unwrap();
+/// assert_eq!(buffer, b"127.0.0.1 a\n");
+/// assert_eq!(rejects.count(), 1);
+/// ```
+pub struct Rejects {
+    writer: Mutex<Box<dyn Write + Send>>,
+    count: AtomicU64,
+}
+
+impl Rejects {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Rejects {
+            writer: Mutex::new(Box::new(writer)),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of lines rejected so far
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Flushes the writer, e.g. once all inputs are done
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error of the writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .flush()
+    }
+
+    pub(crate) fn write(&self, line: &[u8]) -> io::Result<()> {
+        self.write_lines(line, 1)
+    }
+
+    /// Writes `count` lines at once, e.g. all rejects of a chunk processed by a worker thread
+    pub(crate) fn write_lines(&self, lines: &[u8], count: u64) -> io::Result<()> {
+        self.writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_all(lines)?;
+        self.count.fetch_add(count, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Rejects {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rejects")
+            .field("count", &self.count())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(feature = "azure")]
+use std::env;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::Sink;
+
+/// Environment variable holding the Azure storage account of `az://` URLs
+#[cfg(feature = "azure")]
+const AZURE_ACCOUNT: &str = "AZURE_STORAGE_ACCOUNT";
+
+/// Returns the command streaming the object at `url` to its Stdout (or from its Stdin with
+/// `write` set to `true`), if the URL scheme is supported
+///
+/// * `s3://<bucket>/<key>` uses the AWS CLI (`aws s3 cp`), requires feature `s3`,
+/// * `gs://<bucket>/<object>` uses the Google Cloud CLI (`gcloud storage`), requires feature
+///   `gcs`,
+/// * `az://<container>/<blob>` uses the Azure CLI (`az storage blob download`) to read and
+///   `azcopy copy --from-to PipeBlob` to write, as `az` can not upload from Stdin. Requires
+///   feature `azure`, the storage account is read from the environment
+///   (`AZURE_STORAGE_ACCOUNT`), uploads return `None` if it is not set.
+pub(crate) fn command(url: &Path, write: bool) -> Option<Command> {
+    let url = url.to_str()?;
+    #[cfg_attr(not(feature = "azure"), allow(unused_variables))]
+    let (scheme, path) = url.split_once("://")?;
+
+    let command = match scheme {
+        #[cfg(feature = "s3")]
+        "s3" => {
+            let mut command = Command::new("aws");
+            command.args(["s3", "cp"]);
+            if write {
+                command.args(["-", url]);
+            } else {
+                command.args([url, "-"]);
+            }
+            command
+        }
+        #[cfg(feature = "gcs")]
+        "gs" => {
+            let mut command = Command::new("gcloud");
+            if write {
+                command.args(["storage", "cp", "-", url]);
+            } else {
+                command.args(["storage", "cat", url]);
+            }
+            command
+        }
+        #[cfg(feature = "azure")]
+        "az" if write => {
+            path.split_once('/')?;
+            let account = env::var(AZURE_ACCOUNT).ok()?;
+            let mut command = Command::new("azcopy");
+            command.args(["copy", "--from-to", "PipeBlob"]);
+            command.arg(format!("https://{account}.blob.core.windows.net/{path}"));
+            command
+        }
+        #[cfg(feature = "azure")]
+        "az" => {
+            let (container, blob) = path.split_once('/')?;
+            let mut command = Command::new("az");
+            // downloads without `--file` are written to Stdout
+            command.args(["storage", "blob", "download"]);
+            command.args([
+                "--container-name",
+                container,
+                "--name",
+                blob,
+                "--no-progress",
+            ]);
+            command
+        }
+        _ => return None,
+    };
+
+    Some(command)
+}
+
+/// Returns a writer streaming to the Stdin of the command uploading to `url`, see [`command`]
+pub(crate) fn writer(url: &Path) -> io::Result<Box<dyn Sink>> {
+    #[cfg(feature = "azure")]
+    if url.to_str().is_some_and(|url| url.starts_with("az://"))
+        && env::var_os(AZURE_ACCOUNT).is_none()
+    {
+        return Err(io::Error::other(format!(
+            "uploads to az:// URLs require {AZURE_ACCOUNT}"
+        )));
+    }
+    let command = command(url, true).ok_or(io::ErrorKind::Unsupported)?;
+
+    Ok(Box::new(ChildWriter::spawn(command)?))
+}
+
+/// Reads the Stdout of a child process, returns an error if the process did not exit
+/// successfully
+pub(crate) struct ChildReader {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl ChildReader {
+    pub(crate) fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command.stdout(Stdio::piped()).spawn()?;
+        let stdout = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+
+        Ok(ChildReader { child, stdout })
+    }
+}
+
+impl Read for ChildReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("download failed ({status})")));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Writes to the Stdin of a child process
+///
+/// [`Sink::finish`] closes Stdin and waits for the child process to exit successfully.
+pub(crate) struct ChildWriter {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+}
+
+impl ChildWriter {
+    pub(crate) fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+
+        Ok(ChildWriter {
+            child,
+            stdin: BufWriter::new(stdin),
+        })
+    }
+}
+
+impl Write for ChildWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdin.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdin.flush()
+    }
+}
+
+impl Sink for ChildWriter {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let ChildWriter { mut child, stdin } = *self;
+        drop(stdin.into_inner().map_err(io::IntoInnerError::into_error)?);
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("upload failed ({status})")))
+        }
+    }
+}
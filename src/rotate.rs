@@ -0,0 +1,225 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Sink;
+
+/// Time-based output rotation interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    /// Start a new output file every hour
+    Hourly,
+    /// Start a new output file every day
+    Daily,
+}
+
+impl Interval {
+    fn seconds(self) -> u64 {
+        match self {
+            Interval::Hourly => 3600,
+            Interval::Daily => 86400,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Interval::Hourly => "hourly",
+            Interval::Daily => "daily",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" => Ok(Interval::Hourly),
+            "daily" => Ok(Interval::Daily),
+            _ => Err(format!("unknown rotation interval '{s}'")),
+        }
+    }
+}
+
+/// Output file writer, rotating the file once it exceeds a size limit or a new time interval
+/// starts
+///
+/// Rotation is only checked on [`Write::flush`], so files are rotated on line boundaries.
+pub(crate) struct Rotate {
+    template: PathBuf,
+    path: PathBuf,
+    options: OpenOptions,
+    pattern: String,
+    limit: Option<u64>,
+    interval: Option<Interval>,
+    period: u64,
+    size: u64,
+    file: BufWriter<File>,
+}
+
+impl Rotate {
+    /// Opens `template` (expanded with [`strftime`] if `interval` is set) with `options`
+    ///
+    /// Full files are renamed to `pattern`, see [`rotated_path`].
+    pub(crate) fn open(
+        template: &Path,
+        options: &OpenOptions,
+        pattern: &str,
+        limit: Option<u64>,
+        interval: Option<Interval>,
+    ) -> io::Result<Self> {
+        let now = now();
+        let path = expand(template, interval, now);
+        let file = options.open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Rotate {
+            template: template.to_path_buf(),
+            path,
+            options: options.clone(),
+            pattern: pattern.to_string(),
+            limit,
+            interval,
+            period: interval.map_or(0, |i| now / i.seconds()),
+            size,
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Renames the current file (if needed) and opens the output file for the current period
+    fn rotate(&mut self, now: u64) -> io::Result<()> {
+        let path = expand(&self.template, self.interval, now);
+        let file = if path == self.path {
+            fs::rename(&self.path, rotated_path(&self.path, &self.pattern))?;
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&path)?
+        } else {
+            self.options.open(&path)?
+        };
+
+        self.size = file.metadata()?.len();
+        self.file = BufWriter::new(file);
+        self.path = path;
+        Ok(())
+    }
+}
+
+impl Write for Rotate {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let now = now();
+        let period = self.interval.map_or(0, |i| now / i.seconds());
+        if period != self.period || self.limit.is_some_and(|l| self.size >= l) {
+            self.period = period;
+            self.rotate(now)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Rotate {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Returns the name a full output file `path` is rotated to
+///
+/// `{path}` in `pattern` is replaced with `path`, `{n}` with the lowest number (starting at
+/// `1`) not used by an existing file.
+pub(crate) fn rotated_path(path: &Path, pattern: &str) -> PathBuf {
+    let name = |n: usize| {
+        PathBuf::from(
+            pattern
+                .replace("{path}", &path.to_string_lossy())
+                .replace("{n}", &n.to_string()),
+        )
+    };
+
+    if pattern.contains("{n}") {
+        (1..).map(name).find(|p| !p.exists()).unwrap_or_default()
+    } else {
+        name(0)
+    }
+}
+
+/// Returns seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Expands `template` with [`strftime`] if time-based rotation is enabled
+fn expand(template: &Path, interval: Option<Interval>, now: u64) -> PathBuf {
+    match interval {
+        Some(_) => PathBuf::from(strftime(&template.to_string_lossy(), now)),
+        None => template.to_path_buf(),
+    }
+}
+
+/// Formats `secs` since the Unix epoch (UTC) according to `template`
+///
+/// Supports `%Y`, `%m`, `%d`, `%H`, `%M`, `%S`, `%s` and `%%`, any other sequence is kept
+/// as is.
+pub(crate) fn strftime(template: &str, secs: u64) -> String {
+    let (year, month, day) = civil_from_days(secs / 86400);
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{year:04}")),
+            Some('m') => out.push_str(&format!("{month:02}")),
+            Some('d') => out.push_str(&format!("{day:02}")),
+            Some('H') => out.push_str(&format!("{hour:02}")),
+            Some('M') => out.push_str(&format!("{minute:02}")),
+            Some('S') => out.push_str(&format!("{second:02}")),
+            Some('s') => out.push_str(&secs.to_string()),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
+/// Converts days since the Unix epoch to a (proleptic Gregorian) date
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
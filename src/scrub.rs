@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::Format;
+
+/// Additional anonymizer run on every line after the built-in `$remote_addr` replacement, see
+/// [`Config::push_scrubber`](crate::Config::push_scrubber)
+///
+/// Scrubbers are called from worker threads as well.
+///
+/// ```
+/// use alog::{LineCtx, LineScrubber};
+///
+/// /// Replaces every digit with `#`
+/// struct Digits;
+///
+/// impl LineScrubber for Digits {
+///     fn scrub(&self, line: &mut Vec<u8>, _ctx: &LineCtx) {
+///         line.iter_mut()
+///             .filter(|b| b.is_ascii_digit())
+///             .for_each(|b| *b = b'#');
+///     }
+/// }
+///
+/// let config = alog::Config::builder().scrubber(Digits).build().unwrap();
+/// let mut buffer = vec![];
+/// alog::run_raw(&config, &b"8.8.8.8 GET /1"[..], &mut buffer).unwrap();
+/// assert_eq!(buffer, b"###.#.#.# GET /#");
+/// ```
+pub trait LineScrubber: Send + Sync {
+    /// Modifies `line`, the anonymized line without its terminator
+    fn scrub(&self, line: &mut Vec<u8>, ctx: &LineCtx);
+
+    /// Modifies `line` like [`LineScrubber::scrub`], returns `false` to drop the line instead
+    ///
+    /// Scrubbers following in [`Config::scrubbers`](crate::Config::scrubbers) are not run for
+    /// dropped lines. By default `scrub` is called and every line is kept.
+    fn process(&self, line: &mut Vec<u8>, ctx: &LineCtx) -> bool {
+        self.scrub(line, ctx);
+        true
+    }
+}
+
+impl<F: Fn(&mut Vec<u8>, &LineCtx) + Send + Sync> LineScrubber for F {
+    fn scrub(&self, line: &mut Vec<u8>, ctx: &LineCtx) {
+        self(line, ctx);
+    }
+}
+
+impl fmt::Debug for dyn LineScrubber + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineScrubber")
+    }
+}
+
+/// Information about the line passed to [`LineScrubber::scrub`]
+#[derive(Debug, Clone, Copy)]
+pub struct LineCtx<'l> {
+    pub(crate) original: &'l [u8],
+    pub(crate) format: Format,
+}
+
+impl<'l> LineCtx<'l> {
+    /// Returns the line as read from the input, including its terminator
+    #[must_use]
+    pub fn original(&self) -> &'l [u8] {
+        self.original
+    }
+
+    /// Returns the log format of the line
+    #[must_use]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::{self, BufRead, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::CancellationToken;
+
+/// Number of times [`reopen_outputs`] was called
+static HANGUPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Reopens output files of runs following an input or listening, with their next write
+///
+/// Meant to be called from a SIGHUP handler once logrotate moved the output files away, the
+/// library does not install any signal handlers itself. Only updates an atomic counter, so it is
+/// safe to call from a signal handler.
+pub fn reopen_outputs() {
+    HANGUPS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the number of [`reopen_outputs`] calls, output files are reopened whenever it changes
+pub(crate) fn hangups() -> usize {
+    HANGUPS.load(Ordering::Relaxed)
+}
+
+/// Blocks SIGINT, SIGTERM and SIGHUP in the calling thread, leaving them to other threads
+#[cfg(unix)]
+pub(crate) fn block() {
+    // SAFETY: the signal set is initialized by `sigemptyset` before use
+    unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            libc::sigaddset(&mut set, signal);
+        }
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn block() {}
+
+/// Returns `true` if `cancel` was interrupted, see [`CancellationToken::interrupt`]
+pub(crate) fn interrupted(cancel: Option<&CancellationToken>) -> bool {
+    cancel.is_some_and(CancellationToken::is_interrupted)
+}
+
+/// Reader returning EOF at the next `delimiter` once `cancel` was interrupted, keeping track of
+/// the bytes consumed
+///
+/// Lines are only cut if reading blocks after the interrupt, e.g. waiting for more input from a
+/// pipe.
+pub(crate) struct Interruptible<R> {
+    pub(crate) reader: R,
+    pub(crate) consumed: u64,
+    cancel: Option<CancellationToken>,
+    delimiter: u8,
+    /// Last byte consumed
+    last: u8,
+}
+
+impl<R> Interruptible<R> {
+    pub(crate) fn new(reader: R, cancel: Option<CancellationToken>, delimiter: u8) -> Self {
+        Interruptible {
+            reader,
+            consumed: 0,
+            cancel,
+            delimiter,
+            last: delimiter,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Interruptible<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Interruptible<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.last == self.delimiter && interrupted(self.cancel.as_ref()) {
+            return Ok(&[]);
+        }
+        if let Err(e) = self.reader.fill_buf() {
+            if e.kind() == io::ErrorKind::Interrupted && interrupted(self.cancel.as_ref()) {
+                return Ok(&[]);
+            }
+            return Err(e);
+        }
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt > 0 {
+            if let Ok(buf) = self.reader.fill_buf() {
+                self.last = buf[amt - 1];
+            }
+        }
+        self.reader.consume(amt);
+        self.consumed += amt as u64;
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hasher;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Counters collected while anonymizing, see [`Config::set_stats`](crate::Config::set_stats)
+///
+/// All counters can be read while other threads are still updating them.
+#[derive(Debug, Default)]
+pub struct Stats {
+    lines_read: AtomicU64,
+    lines_modified: AtomicU64,
+    ipv4: AtomicU64,
+    ipv6: AtomicU64,
+    hosts: AtomicU64,
+    authuser: AtomicU64,
+    skipped: AtomicU64,
+    dropped: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    /// Hashes of the replaced addresses and cleared users, the originals are not kept
+    addresses: Mutex<HashSet<u64>>,
+    users: Mutex<HashSet<u64>>,
+    /// Totals of every finished input, see [`Stats::summary`]
+    files: Mutex<Vec<(PathBuf, Summary)>>,
+    /// Count skipped lines only, see [`Stats::skips_only`]
+    skips_only: bool,
+}
+
+/// Kind of a replaced `$remote_addr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    Ipv4,
+    Ipv6,
+    Host,
+}
+
+fn add(counter: &AtomicU64, n: u64) {
+    counter.fetch_add(n, Ordering::Relaxed);
+}
+
+fn insert(set: &Mutex<HashSet<u64>>, value: &[u8]) {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(value);
+    let hash = hasher.finish();
+    set.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(hash);
+}
+
+fn len(set: &Mutex<HashSet<u64>>) -> u64 {
+    set.lock().unwrap_or_else(PoisonError::into_inner).len() as u64
+}
+
+fn union(set: &Mutex<HashSet<u64>>, other: &Mutex<HashSet<u64>>) {
+    let other = other.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    set.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .extend(other);
+}
+
+impl Stats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts skipped lines only, all other counters (including dropped lines) stay 0
+    ///
+    /// Unlike full statistics this doesn't slow down anonymizing, e.g. to tell whether any line
+    /// was skipped.
+    #[must_use]
+    pub fn skips_only() -> Self {
+        Stats {
+            skips_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns `false` for [`Stats::skips_only`]
+    pub(crate) fn is_full(&self) -> bool {
+        !self.skips_only
+    }
+
+    /// Lines read, including skipped lines
+    #[must_use]
+    pub fn lines_read(&self) -> u64 {
+        self.lines_read.load(Ordering::Relaxed)
+    }
+
+    /// Lines written with any change
+    #[must_use]
+    pub fn lines_modified(&self) -> u64 {
+        self.lines_modified.load(Ordering::Relaxed)
+    }
+
+    /// IPv4 addresses replaced
+    #[must_use]
+    pub fn ipv4(&self) -> u64 {
+        self.ipv4.load(Ordering::Relaxed)
+    }
+
+    /// IPv6 addresses replaced
+    #[must_use]
+    pub fn ipv6(&self) -> u64 {
+        self.ipv6.load(Ordering::Relaxed)
+    }
+
+    /// Host names (anything not an IP address) replaced
+    #[must_use]
+    pub fn hosts(&self) -> u64 {
+        self.hosts.load(Ordering::Relaxed)
+    }
+
+    /// `$remote_user` fields cleared
+    #[must_use]
+    pub fn authuser(&self) -> u64 {
+        self.authuser.load(Ordering::Relaxed)
+    }
+
+    /// Lines not written at all, except lines dropped on purpose
+    #[must_use]
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Lines dropped by [`Config::push_drop_pattern`](crate::Config::push_drop_pattern) or
+    /// [`Config::push_match_pattern`](crate::Config::push_match_pattern)
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Distinct addresses replaced, e.g. the number of clients in an access log
+    ///
+    /// Addresses are told apart by a 64 bit hash, so the count is exact unless two of several
+    /// billion addresses collide.
+    #[must_use]
+    pub fn distinct_addresses(&self) -> u64 {
+        len(&self.addresses)
+    }
+
+    /// Distinct `$remote_user`s cleared, `-` is not counted
+    #[must_use]
+    pub fn distinct_users(&self) -> u64 {
+        len(&self.users)
+    }
+
+    /// Bytes read from the input, including skipped and dropped lines
+    #[must_use]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Bytes written to the output
+    #[must_use]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Returns a copy of all counters, including the totals of every input finished so far
+    #[must_use]
+    pub fn summary(&self) -> Summary {
+        Summary {
+            files: self.files.lock().map(|f| f.clone()).unwrap_or_default(),
+            ..self.totals()
+        }
+    }
+
+    /// Returns all counters as a single line JSON object, see [`Summary::to_json`]
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        self.summary().to_json()
+    }
+
+    /// Returns a copy of all counters without the inputs
+    fn totals(&self) -> Summary {
+        Summary {
+            lines_read: self.lines_read(),
+            lines_modified: self.lines_modified(),
+            skipped: self.skipped(),
+            dropped: self.dropped(),
+            ipv4: self.ipv4(),
+            ipv6: self.ipv6(),
+            hosts: self.hosts(),
+            authuser: self.authuser(),
+            distinct_addresses: self.distinct_addresses(),
+            distinct_users: self.distinct_users(),
+            bytes_read: self.bytes_read(),
+            bytes_written: self.bytes_written(),
+            files: vec![],
+        }
+    }
+
+    /// Adds all counters and inputs of `other`
+    pub(crate) fn merge(&self, other: &Stats) {
+        self.add_totals(&other.totals());
+        if self.skips_only {
+            return;
+        }
+        union(&self.addresses, &other.addresses);
+        union(&self.users, &other.users);
+        if let (Ok(mut files), Ok(other)) = (self.files.lock(), other.files.lock()) {
+            files.extend(other.iter().cloned());
+        }
+    }
+
+    /// Adds all counters of `other`, which were collected for the single input `path`
+    pub(crate) fn merge_input(&self, path: PathBuf, other: &Stats) {
+        let totals = other.totals();
+        self.add_totals(&totals);
+        if !self.skips_only {
+            union(&self.addresses, &other.addresses);
+            union(&self.users, &other.users);
+        }
+        if let Ok(mut files) = self.files.lock() {
+            files.push((path, totals));
+        }
+    }
+
+    fn add_totals(&self, totals: &Summary) {
+        add(&self.skipped, totals.skipped);
+        if self.skips_only {
+            return;
+        }
+        add(&self.lines_read, totals.lines_read);
+        add(&self.lines_modified, totals.lines_modified);
+        add(&self.dropped, totals.dropped);
+        add(&self.ipv4, totals.ipv4);
+        add(&self.ipv6, totals.ipv6);
+        add(&self.hosts, totals.hosts);
+        add(&self.authuser, totals.authuser);
+        add(&self.bytes_read, totals.bytes_read);
+        add(&self.bytes_written, totals.bytes_written);
+    }
+
+    /// Records the line `input` written as `output`
+    pub(crate) fn line(&self, input: &[u8], output: &[u8]) {
+        if self.skips_only {
+            return;
+        }
+        add(&self.lines_read, 1);
+        add(&self.lines_modified, u64::from(input != output));
+        add(&self.bytes_read, input.len() as u64);
+        add(&self.bytes_written, output.len() as u64);
+    }
+
+    /// Records the line `input` which was not written
+    pub(crate) fn skip(&self, input: &[u8]) {
+        if !self.skips_only {
+            add(&self.lines_read, 1);
+            add(&self.bytes_read, input.len() as u64);
+        }
+        add(&self.skipped, 1);
+    }
+
+    /// Records the line `input` dropped by a pattern
+    pub(crate) fn drop_line(&self, input: &[u8]) {
+        if self.skips_only {
+            return;
+        }
+        add(&self.lines_read, 1);
+        add(&self.bytes_read, input.len() as u64);
+        add(&self.dropped, 1);
+    }
+
+    /// Records `n` bytes read without any processing
+    pub(crate) fn read(&self, n: usize) {
+        if self.skips_only {
+            return;
+        }
+        add(&self.bytes_read, n as u64);
+    }
+
+    /// Records `n` bytes written without any processing
+    pub(crate) fn written(&self, n: usize) {
+        if self.skips_only {
+            return;
+        }
+        add(&self.bytes_written, n as u64);
+    }
+
+    /// Records the replaced address `addr` of `kind`
+    pub(crate) fn address(&self, kind: Address, addr: &[u8]) {
+        if self.skips_only {
+            return;
+        }
+        match kind {
+            Address::Ipv4 => add(&self.ipv4, 1),
+            Address::Ipv6 => add(&self.ipv6, 1),
+            Address::Host => add(&self.hosts, 1),
+        }
+        insert(&self.addresses, addr);
+    }
+
+    /// Records a cleared `$remote_user` field containing `user`
+    pub(crate) fn clear_authuser(&self, user: &[u8]) {
+        if self.skips_only {
+            return;
+        }
+        add(&self.authuser, 1);
+        if !user.is_empty() && user != b"-" {
+            insert(&self.users, user);
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.totals().fmt(f)
+    }
+}
+
+/// Counters of a finished run, returned by [`run_summary`](crate::run_summary) and
+/// [`run_raw_summary`](crate::run_raw_summary), see [`Stats`] for their meaning
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Summary {
+    pub lines_read: u64,
+    pub lines_modified: u64,
+    pub skipped: u64,
+    pub dropped: u64,
+    pub ipv4: u64,
+    pub ipv6: u64,
+    pub hosts: u64,
+    pub authuser: u64,
+    pub distinct_addresses: u64,
+    pub distinct_users: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// Counters of every input in the order they were finished, `-` for stdin
+    ///
+    /// Empty for [`run_raw_summary`](crate::run_raw_summary) and for inputs not read from files
+    /// or stdin, e.g. in [`IOConfig::set_listen`](crate::IOConfig::set_listen) mode.
+    pub files: Vec<(PathBuf, Summary)>,
+}
+
+impl Summary {
+    /// Returns all counters as a single line JSON object, keys are the names of the fields
+    ///
+    /// `files` is a list of objects with the input as `path`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let files: Vec<String> = self
+            .files
+            .iter()
+            .map(|(path, summary)| {
+                format!(
+                    "{{\"path\":{},{}}}",
+                    json_string(&path.to_string_lossy()),
+                    summary.counters_json()
+                )
+            })
+            .collect();
+        format!(
+            "{{{},\"files\":[{}]}}",
+            self.counters_json(),
+            files.join(",")
+        )
+    }
+
+    fn counters_json(&self) -> String {
+        format!(
+            "\"lines_read\":{},\"lines_modified\":{},\"skipped\":{},\"dropped\":{},\"ipv4\":{},\"ipv6\":{},\"hosts\":{},\"authuser\":{},\"distinct_addresses\":{},\"distinct_users\":{},\"bytes_read\":{},\"bytes_written\":{}",
+            self.lines_read,
+            self.lines_modified,
+            self.skipped,
+            self.dropped,
+            self.ipv4,
+            self.ipv6,
+            self.hosts,
+            self.authuser,
+            self.distinct_addresses,
+            self.distinct_users,
+            self.bytes_read,
+            self.bytes_written,
+        )
+    }
+}
+
+/// Quotes `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Display for Summary {
+    /// Writes the totals, followed by the counters of every input if there is more than one
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "lines read:      {}", self.lines_read)?;
+        writeln!(f, "lines modified:  {}", self.lines_modified)?;
+        writeln!(f, "lines skipped:   {}", self.skipped)?;
+        writeln!(f, "lines dropped:   {}", self.dropped)?;
+        writeln!(f, "IPv4 replaced:   {}", self.ipv4)?;
+        writeln!(f, "IPv6 replaced:   {}", self.ipv6)?;
+        writeln!(f, "hosts replaced:  {}", self.hosts)?;
+        writeln!(f, "authuser clears: {}", self.authuser)?;
+        writeln!(f, "distinct addrs:  {}", self.distinct_addresses)?;
+        writeln!(f, "distinct users:  {}", self.distinct_users)?;
+        writeln!(f, "bytes read:      {}", self.bytes_read)?;
+        write!(f, "bytes written:   {}", self.bytes_written)?;
+        if self.files.len() > 1 {
+            for (path, summary) in &self.files {
+                write!(f, "\n\n{}:\n{summary}", path.display())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+/// Input reader added with [`IOConfig::push_reader`](crate::IOConfig::push_reader)
+///
+/// The reader is taken by the first [`run`](crate::run) reading it, clones share the same
+/// reader. Compressed data is decompressed like files and Stdin.
+#[derive(Clone)]
+pub struct InputReader {
+    name: Arc<str>,
+    reader: Arc<Mutex<Option<Box<dyn BufRead + Send>>>>,
+}
+
+impl InputReader {
+    pub(crate) fn new(name: &str, reader: impl BufRead + Send + 'static) -> Self {
+        InputReader {
+            name: name.into(),
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+        }
+    }
+
+    /// Returns the name used in log messages, errors and [`Summary::files`](crate::Summary)
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Takes the reader, fails if it was already read
+    pub(crate) fn take(&self) -> io::Result<Box<dyn BufRead + Send>> {
+        self.reader
+            .lock()
+            .ok()
+            .and_then(|mut reader| reader.take())
+            .ok_or_else(|| io::Error::other("input was already read"))
+    }
+}
+
+/// Compares the shared reader, not the name
+impl PartialEq for InputReader {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.reader, &other.reader)
+    }
+}
+
+impl Eq for InputReader {}
+
+impl fmt::Debug for InputReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("InputReader").field(&self.name).finish()
+    }
+}
+
+/// Output writer set with [`IOConfig::set_writer`](crate::IOConfig::set_writer), taken by the
+/// first [`run`](crate::run)
+pub(crate) struct OutputWriter(Mutex<Option<Box<dyn Write + Send>>>);
+
+impl OutputWriter {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        OutputWriter(Mutex::new(Some(Box::new(writer))))
+    }
+
+    /// Takes the writer, fails if it was already used
+    pub(crate) fn take(&self) -> io::Result<Box<dyn Write + Send>> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut writer| writer.take())
+            .ok_or_else(|| io::Error::other("output writer was already used"))
+    }
+}
+
+impl fmt::Debug for OutputWriter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("OutputWriter")
+    }
+}
@@ -134,6 +134,17 @@ fn valid_utf8() {
     assert_eq!(buffer, b"localhost XxX");
 }
 
+#[test]
+fn memmemsearch() {
+    let hay = b"8.8.8.8 - frank proxy 8.8.8.8 direct 8.8.8.8";
+    let mat = hay.memmemsearch(b"8.8.8.8");
+
+    assert_eq!(Some(vec![0, 22, 37]), mat);
+    assert_eq!(None, hay.memmemsearch(b""));
+    assert_eq!(b"aaa".replace(b"aa", b"b"), b"ba");
+}
+
+#[cfg(feature = "legacy-search")]
 #[test]
 fn bmsearch() {
     let hay = b"8.8.8.8 - frank proxy 8.8.8.8 direct 8.8.8.8";
@@ -142,6 +153,7 @@ fn bmsearch() {
     assert_eq!(Some(vec![0, 22, 37]), mat);
 }
 
+#[cfg(feature = "legacy-search")]
 #[test]
 fn kmpsearch() {
     let hay = b"8.8.8.8 - frank proxy 8.8.8.8 direct 8.8.8.8";
@@ -150,6 +162,7 @@ fn kmpsearch() {
     assert_eq!(Some(vec![0, 22, 37]), mat);
 }
 
+#[cfg(feature = "legacy-search")]
 #[test]
 fn research() {
     let hay = b"8.8.8.8 - frank proxy 8.8.8.8 direct 8.8.8.8";
@@ -158,6 +171,7 @@ fn research() {
     assert_eq!(Some(vec![0, 22, 37]), mat);
 }
 
+#[cfg(feature = "legacy-search")]
 #[test]
 fn windowsearch() {
     let hay = b"8.8.8.8 - frank proxy 8.8.8.8 direct 8.8.8.8";
@@ -166,6 +180,16 @@ fn windowsearch() {
     assert_eq!(Some(vec![0, 22, 37]), mat);
 }
 
+#[test]
+fn time_local() {
+    let line = b"8.8.8.8 - frank [x] [1x [10/Oct/2000:13:55:36 -0700]";
+    assert_eq!(find_time_local(line, 0), Some(23));
+    assert_eq!(find_time_local(b"8.8.8.8 - - [1/Oct", 7), Some(11));
+    assert_eq!(find_time_local(b"8.8.8.8 - - [1/Oct", 12), None);
+    assert_eq!(find_time_local(b"8.8.8.8 - - [100/Oct", 0), None);
+    assert_eq!(find_time_local(b"8.8.8.8 - - [1", 0), None);
+}
+
 #[test]
 fn thorough() {
     use std::io::Cursor;
@@ -193,3 +217,2595 @@ fn thorough_non_overlapping() {
     replace_remote_address(&conf, log, &mut buffer).unwrap();
     assert_eq!(&buffer.into_inner(), &local_log);
 }
+
+#[test]
+fn sshd() {
+    use std::io::Cursor;
+    let mut buffer = Cursor::new(vec![]);
+    let log = Box::new("Oct 16 10:00:00 host sshd[42]: Failed password for invalid user admin from 8.8.8.8 port 22 ssh2\nOct 16 10:00:01 host sshd[42]: Accepted publickey for frank from 2a00:1450:4001:81b::2004 port 22 ssh2\nOct 16 10:00:02 host sshd[43]: Invalid user frank from google.com port 22\nOct 16 10:00:03 host sshd[44]: Disconnected from user alice 1.2.3.4 port 22\nOct 16 10:00:04 host sshd[45]: Disconnected from invalid user bob 1.2.3.4 port 22 [preauth]\n".as_bytes());
+    let local_log = b"Oct 16 10:00:00 host sshd[42]: Failed password for invalid user - from 127.0.0.1 port 22 ssh2\nOct 16 10:00:01 host sshd[42]: Accepted publickey for - from ::1 port 22 ssh2\nOct 16 10:00:02 host sshd[43]: Invalid user - from localhost port 22\nOct 16 10:00:03 host sshd[44]: Disconnected from user - 127.0.0.1 port 22\nOct 16 10:00:04 host sshd[45]: Disconnected from invalid user - 127.0.0.1 port 22 [preauth]\n";
+
+    let mut conf = Config::default();
+    conf.set_format(Format::Sshd);
+
+    replace_remote_address(&conf, log, &mut buffer).unwrap();
+    assert_eq!(&buffer.into_inner(), &local_log);
+
+    let mut buffer = vec![];
+    let log = "Oct 16 10:00:05 host sshd[46]: pam_unix(sshd:auth): authentication failure; logname= uid=0 euid=0 tty=ssh ruser=bob rhost=mail.example.com  user=alice\nOct 16 10:00:06 host sshd[47]: pam_unix(sshd:auth): check pass; user unknown rhost=203.0.113.7\nOct 16 10:00:07 host sshd[48]: pam_unix(sshd:session): session opened for user alice(uid=1000) by (uid=0)\nOct 16 10:00:08 host su[49]: pam_unix(su:session): session opened for user root(uid=0) by alice(uid=1000)\nOct 16 10:00:09 host sshd[48]: pam_unix(sshd:session): session closed for user alice\n";
+    replace_remote_address(&conf, log.as_bytes(), &mut buffer).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "Oct 16 10:00:05 host sshd[46]: pam_unix(sshd:auth): authentication failure; logname= uid=0 euid=0 tty=ssh ruser=- rhost=localhost  user=-\nOct 16 10:00:06 host sshd[47]: pam_unix(sshd:auth): check pass; user unknown rhost=127.0.0.1\nOct 16 10:00:07 host sshd[48]: pam_unix(sshd:session): session opened for user -(uid=1000) by (uid=0)\nOct 16 10:00:08 host su[49]: pam_unix(su:session): session opened for user -(uid=0) by -(uid=1000)\nOct 16 10:00:09 host sshd[48]: pam_unix(sshd:session): session closed for user -\n"
+    );
+}
+
+#[test]
+fn format_presets() {
+    let cases = [
+        (
+            Config::combined(),
+            "8.8.8.8 - frank [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+        ),
+        (
+            Config::vhost_combined(),
+            "www.example.com:443 8.8.8.8 - frank [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+            "www.example.com:443 127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+        ),
+        (
+            Config::varnish(),
+            "8.8.8.8, ::2 - frank [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+            "127.0.0.1, ::1 - - [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200 2326",
+        ),
+        (
+            Config::haproxy(),
+            "Oct 16 10:00:00 8.8.8.8 haproxy[42]: 8.8.8.8:33317 [16/Oct/2000:10:00:00.655] http-in",
+            "Oct 16 10:00:00 8.8.8.8 haproxy[42]: localhost [16/Oct/2000:10:00:00.655] http-in",
+        ),
+        (
+            Config::sshd(),
+            "Oct 16 10:00:00 8.8.8.8 sshd[42]: Accepted publickey for frank from 8.8.8.8 port 22",
+            "Oct 16 10:00:00 8.8.8.8 sshd[42]: Accepted publickey for - from 127.0.0.1 port 22",
+        ),
+        (
+            Config::mail(),
+            "imap-login: Login: user=<frank>, rip=8.8.8.8, lip=10.0.0.1",
+            "imap-login: Login: user=<->, rip=127.0.0.1, lip=10.0.0.1",
+        ),
+    ];
+
+    for (conf, line, expected) in cases {
+        assert_eq!(conf.validate(), Ok(()));
+        let mut buffer = vec![];
+        run_raw(&conf, line.as_bytes(), &mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+}
+
+#[test]
+fn merge_layers() {
+    let stats = Stats::new();
+    let mut conf =
+        Config::from_toml("host = \"file\"\nipv4 = \"0.0.0.0\"\nformat = \"varnish\"\njobs = 4")
+            .unwrap();
+    conf.set_stats(&stats);
+    conf.push_scrubber(|line: &mut Vec<u8>, _: &LineCtx| line.push(b'1'));
+
+    let mut env = Config::default();
+    env.set_host_value("env");
+    env.set_trim(false);
+    conf.merge(env);
+
+    let mut cli = Config::default();
+    cli.set_host_value("cli");
+    cli.push_scrubber(|line: &mut Vec<u8>, _: &LineCtx| line.push(b'2'));
+    conf.merge(cli);
+
+    assert_eq!(conf.get_host_value(), "cli");
+    assert_eq!(conf.get_ipv4_value(), "0.0.0.0");
+    assert_eq!(conf.get_format(), Format::Varnish);
+    assert_eq!(conf.get_jobs(), 4);
+    assert!(!conf.get_trim());
+    assert!(conf.get_stats().is_some());
+
+    let mut buffer = vec![];
+    run_raw(&conf, &b"example.com x\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"cli x12\n");
+}
+
+#[test]
+fn builder_layers_reset_defaults() {
+    let path = std::env::temp_dir().join(format!("alog-layers-{}.toml", std::process::id()));
+    fs::write(
+        &path,
+        "authuser = true\nskip_fields = 1\nhost = \"file\"\nthorough = true\njobs = 1\n",
+    )
+    .unwrap();
+    let file = ConfigBuilder::from_path(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(file.get_jobs(), Some(1));
+    let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+    let all = ConfigBuilder::from_toml("jobs = 0").unwrap();
+    assert_eq!(all.get_jobs(), Some(cpus));
+
+    let cli = Config::builder()
+        .authuser(false)
+        .skip_fields(0)
+        .host("localhost");
+    assert_eq!(cli.get_jobs(), None);
+    let conf = cli.over(file).build().unwrap();
+    assert!(!conf.get_authuser());
+    assert_eq!(conf.get_skip_fields(), 0);
+    assert_eq!(conf.get_host_value(), "localhost");
+    assert!(conf.get_thorough());
+
+    let mut buffer = vec![];
+    run_raw(&conf, &b"example.com - alice x\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"localhost - alice x\n");
+}
+
+#[test]
+fn field_separators() {
+    let conf = Config::from_toml("separators = '\\t'").unwrap();
+    assert_eq!(conf.get_separators(), Some("\t"));
+    let mut buffer = vec![];
+    let input = &b"8.8.8.8 x\tGET /\n2001:db8::1\ty z\nno-tab here\n"[..];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(buffer, b"localhost\tGET /\n::1\ty z\nlocalhost\n");
+
+    let conf = Config::builder().separators(",;").build().unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, &b"8.8.8.8;a,b\n::2,c d\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1;a,b\n::1,c d\n");
+
+    let mut conf = Config::default();
+    for separators in ["", "\n", "\u{a0}"] {
+        conf.set_separators(separators);
+        assert_eq!(
+            conf.validate().unwrap_err(),
+            [ConfigError::Separators {
+                value: separators.to_string()
+            }]
+        );
+    }
+}
+
+#[test]
+fn comment_prefixes() {
+    let conf = Config::from_toml("comment_prefixes = '# ;'").unwrap();
+    assert_eq!(conf.get_comment_prefixes(), ["#", ";"]);
+    let stats = Stats::new();
+    let mut conf = conf;
+    conf.set_stats(&stats);
+    let mut buffer = vec![];
+    let input = &b"#Fields: c-ip cs-method\n  ; 8.8.8.8 note\n8.8.8.8 GET\n"[..];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"#Fields: c-ip cs-method\n  ; 8.8.8.8 note\n127.0.0.1 GET\n"
+    );
+    assert_eq!(stats.lines_read(), 3);
+    assert_eq!(stats.lines_modified(), 1);
+
+    let conf = Config::builder().comment_prefix("").build().unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, &b"#8.8.8.8\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"localhost\n");
+}
+
+#[test]
+fn byte_order_mark() {
+    let input = &b"\xEF\xBB\xBF8.8.8.8 GET\n\xEF\xBB\xBF#Fields: c-ip\n::2 GET\n"[..];
+    let conf = Config::builder().comment_prefix("#").build().unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"\xEF\xBB\xBF127.0.0.1 GET\n\xEF\xBB\xBF#Fields: c-ip\n::1 GET\n"
+    );
+
+    let conf = Config::builder()
+        .comment_prefix("#")
+        .strip_bom(true)
+        .build()
+        .unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 GET\n#Fields: c-ip\n::1 GET\n");
+}
+
+#[test]
+fn strict_mode() {
+    let input = &b"8.8.8.8 - - [16/Oct/2026:10:00:00 +0000] GET\nexample.com GET\n[oops] GET\n"[..];
+    let mut conf = Config::builder().strict(true).build().unwrap();
+    for (jobs, block_size) in [(1, None), (1, Some(8)), (4, None)] {
+        conf.set_jobs(jobs);
+        conf.block_size = block_size;
+        let error = run_raw(&conf, input, &mut vec![]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .ends_with("malformed line 3: first word is not an IP address or host name"),
+            "{error}"
+        );
+    }
+
+    let conf = Config::builder().strict(true).skip(true).build().unwrap();
+    let error = run_raw(&conf, &b"::2 a\n\n"[..], &mut vec![]).unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("malformed line 2: missing first word"));
+
+    let conf = Config::builder()
+        .strict(true)
+        .authuser(true)
+        .build()
+        .unwrap();
+    let mut buffer = vec![];
+    let line = &b"8.8.8.8 - - [16/Oct/2026:10:00:00 +0000] GET\n"[..];
+    run_raw(&conf, line, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 - - [16/Oct/2026:10:00:00 +0000] GET\n");
+    assert!(run_raw(&conf, &b"::2 GET\n"[..], &mut vec![]).is_err());
+
+    let conf = Config::builder().strict(true).syslog(true).build().unwrap();
+    let error = run_raw(&conf, &b"8.8.8.8 GET\n"[..], &mut vec![]).unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("malformed line 1: missing syslog header"));
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_reader("access.log", &b"::2 a\n8.8.8.8 b\n[x] c\n"[..]);
+    ioconf.set_writer(io::sink());
+    let conf = Config::builder().strict(true).build().unwrap();
+    let Err(Error::Anonymize { path, source }) = run(&conf, &ioconf) else {
+        panic!("strict mode did not fail");
+    };
+    assert_eq!(path, Path::new("access.log"));
+    let malformed = source.get_ref().unwrap().downcast_ref::<Malformed>();
+    assert_eq!(malformed.map(Malformed::line_no), Some(3));
+    assert_eq!(
+        malformed.map(Malformed::reason),
+        Some("first word is not an IP address or host name")
+    );
+}
+
+#[test]
+fn rejects_file() {
+    let path = std::env::temp_dir().join(format!("alog-rejects-{}.log", std::process::id()));
+    let rejects = Rejects::new(File::create(&path).unwrap());
+    let stats = Stats::new();
+    let conf = Config::builder()
+        .strict(true)
+        .rejects(&rejects)
+        .stats(&stats)
+        .build()
+        .unwrap();
+    let mut buffer = vec![];
+    let input = &b"8.8.8.8 a\n[x] b\n::2 c\n\n"[..];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    rejects.flush().unwrap();
+    assert_eq!(buffer, b"127.0.0.1 a\n::1 c\n");
+    assert_eq!(fs::read(&path).unwrap(), b"[x] b\n\n");
+    assert_eq!(rejects.count(), 2);
+    assert_eq!(stats.skipped(), 2);
+    fs::remove_file(&path).unwrap();
+
+    // rejects of several chunks processed in parallel are written in input order
+    let rejects = Rejects::new(File::create(&path).unwrap());
+    let conf = Config::builder()
+        .strict(true)
+        .rejects(&rejects)
+        .jobs(4)
+        .build()
+        .unwrap();
+    let (mut input, mut expected) = (vec![], vec![]);
+    for n in 0..100_000 {
+        if n % 1000 == 0 {
+            let line = format!("[{n}] b\n");
+            input.extend_from_slice(line.as_bytes());
+            expected.extend_from_slice(line.as_bytes());
+        } else {
+            input.extend_from_slice(b"8.8.8.8 a\n");
+        }
+    }
+    run_raw(&conf, &input[..], io::sink()).unwrap();
+    rejects.flush().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), expected);
+    assert_eq!(rejects.count(), 100);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn ipv6_zone() {
+    let input = &b"fe80::1%eth0 GET\nfe80::1% GET\n"[..];
+    let mut buffer = vec![];
+    run_raw(&Config::default(), input, &mut buffer).unwrap();
+    assert_eq!(buffer, b"::1 GET\nlocalhost GET\n");
+
+    let conf = Config::from_toml("keep_zone = true\nthorough = true").unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, &b"fe80::1%eth0 fe80::1\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"::1%eth0 ::1\n");
+
+    let conf = Config::builder()
+        .format(Format::Sshd)
+        .keep_zone(true)
+        .build()
+        .unwrap();
+    let mut buffer = vec![];
+    let line = &b"Accepted publickey for root from fe80::2%en0 port 22\n"[..];
+    run_raw(&conf, line, &mut buffer).unwrap();
+    assert_eq!(buffer, b"Accepted publickey for - from ::1%en0 port 22\n");
+}
+
+#[test]
+fn verify_hosts() {
+    let conf = Config::builder().verify_hosts(true).build().unwrap();
+    let input = &b"www.example.com. a\n\x01\xff\x02 b\n-bad-.example c\n8.8.8.8 d\nnode-1 e\n"[..];
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"localhost a\n\x01\xff\x02 b\n-bad-.example c\n127.0.0.1 d\nlocalhost e\n"
+    );
+    assert!(!is_hostname(&[b'a'; 64]));
+    assert!(!is_hostname(b"a..b"));
+}
+
+#[test]
+fn skip_fields() {
+    let conf = Config::from_toml("skip_fields = 1\nauthuser = true").unwrap();
+    let input =
+        &b"www.example.com:443  8.8.8.8 - bob [16/Oct/2026:10:00:00 +0000] GET\nweb-1\n"[..];
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"www.example.com:443  127.0.0.1 - - [16/Oct/2026:10:00:00 +0000] GET\nweb-1\n"
+    );
+
+    let conf = Config::builder()
+        .separators("\t")
+        .skip_fields(2)
+        .build()
+        .unwrap();
+    let mut buffer = vec![];
+    run_raw(&conf, &b"a\t\t::2\tGET /\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"a\t\t::1\tGET /\n");
+
+    let mut conf = Config::builder().format(Format::Sshd).build().unwrap();
+    conf.set_skip_fields(1);
+    assert_eq!(
+        conf.validate().unwrap_err(),
+        [ConfigError::Unsupported {
+            field: "skip_fields",
+            format: Format::Sshd
+        }]
+    );
+}
+
+#[cfg(feature = "journald")]
+#[test]
+fn journal_export() {
+    let cursor = std::env::temp_dir().join(format!("alog-cursor-{}", std::process::id()));
+    let mut input = b"__CURSOR=s=1\nMESSAGE=8.8.8.8 a\n_HOSTNAME=web\n\n".to_vec();
+    input.extend_from_slice(b"__CURSOR=s=2\nMESSAGE\n");
+    input.extend_from_slice(&15u64.to_le_bytes());
+    input.extend_from_slice(b"::1 b\n1.1.1.1 c\n\n");
+
+    let mut buffer = vec![];
+    journal::copy_entries(
+        &Config::default(),
+        &[],
+        Some(&cursor),
+        io::BufReader::new(&input[..]),
+        &mut buffer,
+    )
+    .unwrap();
+
+    let mut expected = b"__CURSOR=s=1\nMESSAGE=127.0.0.1 a\n_HOSTNAME=web\n\n".to_vec();
+    expected.extend_from_slice(b"__CURSOR=s=2\nMESSAGE\n");
+    expected.extend_from_slice(&17u64.to_le_bytes());
+    expected.extend_from_slice(b"::1 b\n127.0.0.1 c\n\n");
+    assert_eq!(buffer, expected);
+    assert_eq!(std::fs::read(&cursor).unwrap(), b"s=2\n");
+    std::fs::remove_file(cursor).unwrap();
+
+    let fields = [Cow::Borrowed("_HOSTNAME")];
+    buffer.clear();
+    journal::copy_entries(
+        &Config::default(),
+        &fields,
+        None,
+        io::BufReader::new(&input[..46]),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        buffer,
+        b"__CURSOR=s=1\nMESSAGE=8.8.8.8 a\n_HOSTNAME=localhost\n\n"
+    );
+
+    // entries cut off by journalctl are not written
+    let truncated = io::BufReader::new(&input[..50]);
+    buffer.clear();
+    assert!(journal::copy_entries(&Config::default(), &[], None, truncated, &mut buffer).is_err());
+
+    // messages are free text, the format is chosen by the identifier
+    let input = b"SYSLOG_IDENTIFIER=sshd\nMESSAGE=Accepted password for bob from 8.8.8.8 port 22 ssh2\n\nSYSLOG_IDENTIFIER=kernel\nMESSAGE=[UFW BLOCK] IN=eth0 SRC=8.8.8.8 DST=10.0.0.1 PROTO=TCP\n\nSYSLOG_IDENTIFIER=dovecot\nMESSAGE=imap-login: Login: user=<bob>, rip=8.8.8.8, lip=10.0.0.1\n\nSYSLOG_IDENTIFIER=systemd\nMESSAGE=Connection from 2001:db8::1 port 22 closed\n\n";
+    buffer.clear();
+    journal::copy_entries(
+        &Config::default(),
+        &[],
+        None,
+        io::BufReader::new(&input[..]),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(buffer.clone()).unwrap(),
+        "SYSLOG_IDENTIFIER=sshd\nMESSAGE=Accepted password for - from 127.0.0.1 port 22 ssh2\n\nSYSLOG_IDENTIFIER=kernel\nMESSAGE=[UFW BLOCK] IN=eth0 SRC=127.0.0.1 DST=127.0.0.1 PROTO=TCP\n\nSYSLOG_IDENTIFIER=dovecot\nMESSAGE=imap-login: Login: user=<->, rip=127.0.0.1, lip=10.0.0.1\n\nSYSLOG_IDENTIFIER=systemd\nMESSAGE=Connection from ::1 port 22 closed\n\n"
+    );
+
+    // binary field lengths are not trusted
+    let mut input = b"__CURSOR=s=1\nMESSAGE\n".to_vec();
+    input.extend_from_slice(&u64::MAX.to_le_bytes());
+    let err = journal::copy_entries(
+        &Config::default(),
+        &[],
+        None,
+        io::BufReader::new(&input[..]),
+        &mut buffer,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("too long"));
+}
+
+#[test]
+fn thorough_authuser() {
+    let conf = Config::builder()
+        .authuser(true)
+        .thorough(true)
+        .build()
+        .unwrap();
+    let input = &b"8.8.8.8 - frank [10/Oct/2000:13:55:36 -0700] \"GET /~frank/ HTTP/1.0\" \"http://8.8.8.8/\"\n::2 - - [10/Oct/2000:13:55:36 -0700] \"GET /-/ HTTP/1.0\"\n"[..];
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /~-/ HTTP/1.0\" \"http://127.0.0.1/\"\n::1 - - [10/Oct/2000:13:55:36 -0700] \"GET /-/ HTTP/1.0\"\n"
+    );
+}
+
+#[test]
+fn ip_only() {
+    let stats = Stats::new();
+    let mut conf = Config::combined();
+    conf.set_ip_only(true);
+    conf.set_thorough(true);
+    conf.set_stats(&stats);
+    let input = &b"8.8.8.8 - frank [10/Oct/2000:13:55:36 -0700] \"GET /\"\napp - frank [10/Oct/2000:13:55:36 -0700] 8.8.8.8\nINFO listening on ::2\n::2 x\n"[..];
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /\"\napp - frank [10/Oct/2000:13:55:36 -0700] 8.8.8.8\nINFO listening on ::2\n::1 x\n"
+    );
+    assert_eq!((stats.ipv4(), stats.ipv6(), stats.hosts()), (1, 1, 0));
+    assert_eq!(stats.lines_modified(), 2);
+}
+
+#[test]
+fn keep_dash() {
+    let stats = Stats::new();
+    let input = &b"- - frank [10/Oct/2000:13:55:36 -0700] \"GET /\"\n-x a\n"[..];
+    let mut conf = Config::combined();
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"localhost - - [10/Oct/2000:13:55:36 -0700] \"GET /\"\nlocalhost a\n"
+    );
+
+    conf.set_stats(&stats);
+    conf.set_keep_dash(true);
+    let mut buffer = vec![];
+    run_raw(&conf, input, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"- - - [10/Oct/2000:13:55:36 -0700] \"GET /\"\nlocalhost a\n"
+    );
+    assert_eq!(stats.hosts(), 1);
+    assert!(Config::from_toml("keep_dash = true")
+        .unwrap()
+        .get_keep_dash());
+}
+
+#[test]
+fn trailing_newline() {
+    let configs = [
+        Config::default(),
+        Config::combined(),
+        Config::varnish(),
+        Config::builder().thorough(true).build().unwrap(),
+        Config::builder().block_size(8).build().unwrap(),
+        Config::builder().jobs(2).block_size(8).build().unwrap(),
+    ];
+
+    for conf in &configs {
+        for (input, expected) in [
+            (&b"a b\n8.8.8.8"[..], &b"localhost b\n127.0.0.1"[..]),
+            (b"a b\n8.8.8.8\n", b"localhost b\n127.0.0.1\n"),
+            (b"a b\r\n::2", b"localhost b\r\n::1"),
+            (b"a b\r\n::2\r\n", b"localhost b\r\n::1\r\n"),
+        ] {
+            let mut buffer = vec![];
+            run_raw(conf, input, &mut buffer).unwrap();
+            assert_eq!(buffer, expected, "{conf:?}");
+        }
+    }
+
+    let conf = Config::builder().null_data(true).build().unwrap();
+    for (input, expected) in [(&b"8.8.8.8"[..], &b"127.0.0.1"[..]), (b"::2\0", b"::1\0")] {
+        let mut buffer = vec![];
+        run_raw(&conf, input, &mut buffer).unwrap();
+        assert_eq!(buffer, expected);
+    }
+}
+
+#[test]
+fn mail() {
+    use std::io::Cursor;
+    let mut buffer = Cursor::new(vec![]);
+    let log = Box::new("Oct 16 10:00:00 host dovecot: imap-login: Login: user=<frank@example.com>, method=PLAIN, rip=8.8.8.8, lip=10.0.0.1, mpid=42, TLS\n2023-10-16 10:00:01 1qs5ZK-0001 <= frank@example.com H=mail.example.com (helo.example.com) [2a00:1450:4001:81b::2004]:25 P=esmtpa A=dovecot_login:frank S=1234\n".as_bytes());
+    let local_log = b"Oct 16 10:00:00 host dovecot: imap-login: Login: user=<->, method=PLAIN, rip=127.0.0.1, lip=10.0.0.1, mpid=42, TLS\n2023-10-16 10:00:01 1qs5ZK-0001 <= frank@example.com H=localhost (localhost) [::1]:25 P=esmtpa A=dovecot_login:- S=1234\n";
+
+    let mut conf = Config::default();
+    conf.set_format(Format::Mail);
+
+    replace_remote_address(&conf, log, &mut buffer).unwrap();
+    assert_eq!(&buffer.into_inner(), &local_log);
+}
+
+#[test]
+fn varnish_forwarded_for() {
+    use std::io::Cursor;
+    let mut buffer = Cursor::new(vec![]);
+    let log = Box::new("8.8.8.8, 2a00:1450:4001:81b::2004 - frank [10/Oct/2000:13:55:36 -0700] \"GET /?ip=8.8.8.8 HTTP/1.0\" 200 2326 \"-\" \"-\"\n".as_bytes());
+    let local_log = b"127.0.0.1, ::1 - - [10/Oct/2000:13:55:36 -0700] \"GET /?ip=127.0.0.1 HTTP/1.0\" 200 2326 \"-\" \"-\"\n";
+
+    let mut conf = Config::default();
+    conf.set_format(Format::Varnish);
+    conf.set_authuser(true);
+    conf.set_thorough(true);
+
+    replace_remote_address(&conf, log, &mut buffer).unwrap();
+    assert_eq!(&buffer.into_inner(), &local_log);
+}
+
+#[test]
+fn syslog_envelope() {
+    use std::io::Cursor;
+    let mut buffer = Cursor::new(vec![]);
+    let log = Box::new("<134>2023-10-16T10:00:00Z cache-fra1234 service[1]: 8.8.8.8 - frank [16/Oct/2023:10:00:00 +0000] \"GET / HTTP/1.1\" 200 42\n<134>1 2023-10-16T10:00:00Z cache-fra1234 service 1 - [meta x=\"a]\"] 8.8.8.8 - - [16/Oct/2023:10:00:00 +0000] \"GET / HTTP/1.1\" 200 42\n".as_bytes());
+    let local_log = b"<134>2023-10-16T10:00:00Z cache-fra1234 service[1]: 127.0.0.1 - frank [16/Oct/2023:10:00:00 +0000] \"GET / HTTP/1.1\" 200 42\n<134>1 2023-10-16T10:00:00Z cache-fra1234 service 1 - [meta x=\"a]\"] 127.0.0.1 - - [16/Oct/2023:10:00:00 +0000] \"GET / HTTP/1.1\" 200 42\n";
+
+    let mut conf = Config::default();
+    conf.set_syslog(true);
+
+    replace_remote_address(&conf, log, &mut buffer).unwrap();
+    assert_eq!(&buffer.into_inner(), &local_log);
+}
+
+#[test]
+fn syslog_without_envelope() {
+    let conf = Config::builder().syslog(true).build().unwrap();
+    let line =
+        b"8.8.8.8 - - [16/Oct/2026:10:00:00 +0000] \"GET /a HTTP/1.1\" 200 1 \"-\" \"Agent: x\"\n";
+    let mut buffer = vec![];
+    run_raw(&conf, &line[..], &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"127.0.0.1 - - [16/Oct/2026:10:00:00 +0000] \"GET /a HTTP/1.1\" 200 1 \"-\" \"Agent: x\"\n"
+    );
+
+    let line = b"<13>Oct  6 10:00:00 app[1]: 8.8.8.8 x\n";
+    buffer.clear();
+    run_raw(&conf, &line[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"<13>Oct  6 10:00:00 app[1]: 127.0.0.1 x\n");
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_input() {
+    use flate2::{write::GzEncoder, Compression};
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-gzip-input-{}.log.gz", std::process::id()));
+    let output = dir.join(format!("alog-gzip-output-{}.log", std::process::id()));
+
+    let mut encoder = GzEncoder::new(File::create(&input).unwrap(), Compression::default());
+    encoder.write_all(b"8.8.8.8 XxX\n").unwrap();
+    encoder.finish().unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n");
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_magic() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Cursor;
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(b"8.8.8.8 XxX\n").unwrap();
+    let data = encoder.finish().unwrap();
+    assert_eq!(
+        crate::Compression::from_magic(&data),
+        crate::Compression::Gzip
+    );
+
+    let mut buffer = vec![];
+    let reader = compress::sniff(Cursor::new(data)).unwrap();
+    run_raw(&Config::default(), reader, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n");
+
+    let reader = compress::sniff(Cursor::new(b"8.8.8.8 XxX\n".to_vec())).unwrap();
+    buffer.clear();
+    run_raw(&Config::default(), reader, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n");
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn compression_extension() {
+    let path = Path::new("access.log.1.gz");
+    assert_eq!(crate::Compression::from_path(path).extension(), Some("gz"));
+    assert_eq!(crate::Compression::None.extension(), None);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_encoder() {
+    use std::io::{Cursor, Read};
+
+    let mut encoder = Encoder::new(vec![], Compression::Gzip).unwrap();
+    run_raw(
+        &Config::default(),
+        Cursor::new(b"8.8.8.8 XxX\n"),
+        &mut encoder,
+    )
+    .unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut buffer = vec![];
+    flate2::read::GzDecoder::new(&compressed[..])
+        .read_to_end(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_encoder() {
+    use std::io::Cursor;
+
+    let mut encoder = Encoder::new(vec![], Compression::Zstd).unwrap();
+    run_raw(
+        &Config::default(),
+        Cursor::new(b"8.8.8.8 XxX\n"),
+        &mut encoder,
+    )
+    .unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(
+        zstd::decode_all(&compressed[..]).unwrap(),
+        b"127.0.0.1 XxX\n"
+    );
+}
+
+#[cfg(all(feature = "gzip", feature = "zstd"))]
+#[test]
+fn encoder_dropped() {
+    use std::io::Read;
+
+    let mut gzip = vec![];
+    let mut encoder = Encoder::new(&mut gzip, Compression::Gzip).unwrap();
+    encoder.write_all(b"127.0.0.1 XxX\n").unwrap();
+    drop(encoder);
+    let mut buffer = vec![];
+    flate2::read::GzDecoder::new(&gzip[..])
+        .read_to_end(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n");
+
+    let mut zstd = vec![];
+    let mut encoder = Encoder::new(&mut zstd, Compression::Zstd).unwrap();
+    encoder.write_all(b"127.0.0.1 XxX\n").unwrap();
+    drop(encoder);
+    assert_ne!(
+        zstd::decode_all(&zstd[..]).ok().as_deref(),
+        Some(&b"127.0.0.1 XxX\n"[..])
+    );
+}
+
+#[cfg(all(feature = "bzip2", feature = "xz"))]
+#[test]
+fn bzip2_xz_input() {
+    let dir = std::env::temp_dir();
+    let bz2 = dir.join(format!("alog-input-{}.log.bz2", std::process::id()));
+    let xz = dir.join(format!("alog-input-{}.log.xz", std::process::id()));
+    let output = dir.join(format!("alog-bzip2-xz-output-{}.log", std::process::id()));
+
+    let mut encoder = Encoder::new(File::create(&bz2).unwrap(), Compression::Bzip2).unwrap();
+    encoder.write_all(b"8.8.8.8 XxX\n").unwrap();
+    encoder.finish().unwrap();
+    let mut encoder = Encoder::new(File::create(&xz).unwrap(), Compression::Xz).unwrap();
+    encoder.write_all(b"::1 XxX\n").unwrap();
+    encoder.finish().unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&bz2);
+    ioconf.push_input(&xz);
+    ioconf.set_output(&output);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n::1 XxX\n");
+    for f in [bz2, xz, output] {
+        std::fs::remove_file(f).unwrap();
+    }
+}
+
+#[test]
+fn in_place() {
+    let input = std::env::temp_dir().join(format!("alog-in-place-{}.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_in_place(true);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(std::fs::read(&input).unwrap(), b"127.0.0.1 XxX\n");
+    assert!(!temp_path(&input).exists());
+
+    let backup = PathBuf::from(format!("{}.bak", input.display()));
+    std::fs::write(&input, b"8.8.8.8 YyY\n").unwrap();
+    ioconf.set_backup_suffix(".bak");
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(std::fs::read(&input).unwrap(), b"127.0.0.1 YyY\n");
+    assert_eq!(std::fs::read(&backup).unwrap(), b"8.8.8.8 YyY\n");
+    std::fs::remove_file(backup).unwrap();
+    std::fs::remove_file(input).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn preserve_metadata() {
+    let input = std::env::temp_dir().join(format!("alog-preserve-{}.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+    let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let f = File::options().write(true).open(&input).unwrap();
+    f.set_modified(mtime).unwrap();
+    let mut permissions = f.metadata().unwrap().permissions();
+    permissions.set_readonly(true);
+    f.set_permissions(permissions).unwrap();
+    drop(f);
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_in_place(true);
+    ioconf.set_preserve(true);
+    run(&Config::default(), &ioconf).unwrap();
+
+    let meta = std::fs::metadata(&input).unwrap();
+    assert_eq!(std::fs::read(&input).unwrap(), b"127.0.0.1 XxX\n");
+    assert_eq!(meta.modified().unwrap(), mtime);
+    assert!(meta.permissions().readonly());
+    std::fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn output_dir() {
+    let dir = std::env::temp_dir().join(format!("alog-output-dir-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    let (a, b) = (dir.join("a.log"), dir.join("b.log"));
+    std::fs::write(&a, b"8.8.8.8 XxX\n").unwrap();
+    std::fs::write(&b, b"::2 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&a);
+    ioconf.push_input(&b);
+    ioconf.set_output_dir(&dir);
+    assert!(run(&Config::default(), &ioconf).is_err());
+
+    let out = dir.join("out");
+    ioconf.set_output_dir(&out);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(
+        std::fs::read(out.join("a.log")).unwrap(),
+        b"127.0.0.1 XxX\n"
+    );
+    assert_eq!(std::fs::read(out.join("b.log")).unwrap(), b"::1 XxX\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn input_output_pairs() {
+    let dir = std::env::temp_dir().join(format!("alog-pairs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (a, b) = (dir.join("a.log"), dir.join("b.log"));
+    std::fs::write(&a, b"8.8.8.8 XxX\n").unwrap();
+    std::fs::write(&b, b"::2 XxX\n").unwrap();
+    let (a_out, b_out) = (dir.join("a.out"), dir.join("b.out"));
+
+    let mut ioconf = IOConfig::default();
+    ioconf.add_pair(&a, &a_out);
+    ioconf.add_pair(&b, &b_out);
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(std::fs::read(&a_out).unwrap(), b"127.0.0.1 XxX\n");
+    assert_eq!(std::fs::read(&b_out).unwrap(), b"::1 XxX\n");
+
+    ioconf.push_input(&a);
+    assert!(matches!(
+        run(&Config::default(), &ioconf),
+        Err(Error::Usage(_))
+    ));
+
+    let mut ioconf = IOConfig::default();
+    ioconf.add_pair(&a, &a);
+    assert!(matches!(
+        run(&Config::default(), &ioconf),
+        Err(Error::Usage(_))
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn output_mode() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-output-mode-input-{}.log", std::process::id()));
+    let output = dir.join(format!(
+        "alog-output-mode-output-{}.log",
+        std::process::id()
+    ));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    run(&Config::default(), &ioconf).unwrap();
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(
+        std::fs::read(&output).unwrap(),
+        b"127.0.0.1 XxX\n127.0.0.1 XxX\n"
+    );
+
+    ioconf.set_output_mode(OutputMode::Truncate);
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n");
+
+    ioconf.set_output_mode(OutputMode::NoClobber);
+    assert!(run(&Config::default(), &ioconf).is_err());
+
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn follow() {
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("alog-follow-{}.log", std::process::id()));
+    std::fs::write(&path, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut reader = follow::Follow::open(&path, None).unwrap();
+    let mut buffer = [0; 64];
+    let n = reader.read(&mut buffer).unwrap();
+    assert_eq!(&buffer[..n], b"8.8.8.8 XxX\n");
+
+    // truncated and rewritten
+    std::fs::write(&path, b"::1 XxX\n").unwrap();
+    let n = reader.read(&mut buffer).unwrap();
+    assert_eq!(&buffer[..n], b"::1 XxX\n");
+
+    // rotated
+    std::fs::rename(&path, path.with_extension("log.1")).unwrap();
+    std::fs::write(&path, b"google.com XxX\n").unwrap();
+    let n = reader.read(&mut buffer).unwrap();
+    assert_eq!(&buffer[..n], b"google.com XxX\n");
+
+    std::fs::remove_file(path.with_extension("log.1")).unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn watch_scan() {
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("alog-watch-{}", std::process::id()));
+    let out = dir.join("out");
+    std::fs::create_dir_all(&out).unwrap();
+    std::fs::write(dir.join("access.log.1"), b"8.8.8.8 XxX\n").unwrap();
+
+    let conf = Config::default();
+    assert_eq!(
+        watch::scan(&conf, &dir, &out, Duration::from_secs(60), false).unwrap(),
+        0
+    );
+    assert_eq!(
+        watch::scan(&conf, &dir, &out, Duration::ZERO, false).unwrap(),
+        1
+    );
+    assert_eq!(
+        watch::scan(&conf, &dir, &out, Duration::ZERO, false).unwrap(),
+        0
+    );
+
+    assert_eq!(
+        std::fs::read(out.join("access.log.1")).unwrap(),
+        b"127.0.0.1 XxX\n"
+    );
+
+    #[cfg(target_os = "linux")]
+    {
+        let notify = watch::Notify::new(&conf, std::iter::once(dir.as_path()));
+        assert!(matches!(notify, watch::Notify::Inotify(_)));
+        assert!(!notify.wait(Duration::ZERO).unwrap());
+        std::fs::write(dir.join("access.log"), b"8.8.8.8 XxX\n").unwrap();
+        assert!(notify.wait(Duration::from_secs(5)).unwrap());
+        assert!(!notify.wait(Duration::ZERO).unwrap());
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn reopen_fifo() {
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("alog-fifo-{}", std::process::id()));
+    let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+    assert!(follow::is_fifo(&path));
+
+    let writer_path = path.clone();
+    let writer = std::thread::spawn(move || {
+        std::fs::write(&writer_path, b"8.8.8.8 XxX\n").unwrap();
+        std::fs::write(&writer_path, b"::1 XxX\n").unwrap();
+    });
+
+    let mut reader = follow::Reopen::open(&path, None).unwrap();
+    let mut buffer = vec![0; 20];
+    reader.read_exact(&mut buffer).unwrap();
+    assert_eq!(buffer, b"8.8.8.8 XxX\n::1 XxX\n");
+
+    writer.join().unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn syslog_frames() {
+    use std::io::Cursor;
+
+    let mut reader = Cursor::new(b"8.8.8.8 XxX\n19 <13>::1 XxX\n::1 XxX".to_vec());
+    let mut buf = vec![];
+
+    assert_eq!(listen::read_frame(&mut reader, &mut buf).unwrap(), 12);
+    assert_eq!(buf, b"8.8.8.8 XxX\n");
+    assert_eq!(listen::read_frame(&mut reader, &mut buf).unwrap(), 19);
+    assert_eq!(buf, b"<13>::1 XxX\n::1 XxX");
+    assert_eq!(listen::read_frame(&mut reader, &mut buf).unwrap(), 0);
+
+    let mut reader = Cursor::new(b"99999999999 <13>::1 XxX".to_vec());
+    let err = listen::read_frame(&mut reader, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let mut reader = Cursor::new(vec![b'X'; 70_000]);
+    let err = listen::read_frame(&mut reader, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[cfg(unix)]
+#[test]
+fn listen_unix() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path = std::env::temp_dir().join(format!("alog-listen-{}.sock", std::process::id()));
+    let output = path.with_extension("log");
+    let addr = format!("unixgram://{}", path.display());
+
+    // several jobs, every message is still anonymized on the listening thread
+    let token = CancellationToken::new();
+    let config = Config::builder()
+        .jobs(4)
+        .cancel(token.clone())
+        .build()
+        .unwrap();
+    let (listen_addr, listen_output) = (addr.clone(), output.clone());
+    let listener = std::thread::spawn(move || {
+        let mut ioconf = IOConfig::default();
+        ioconf.set_listen(&listen_addr);
+        ioconf.set_output(&listen_output);
+        run(&config, &ioconf).unwrap();
+    });
+
+    let socket = UnixDatagram::unbound().unwrap();
+    for _ in 0..50 {
+        if socket.send_to(b"8.8.8.8 XxX", &path).is_ok() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    for _ in 0..50 {
+        if std::fs::read(&output).is_ok_and(|o| !o.is_empty()) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n");
+    token.interrupt();
+    listener.join().unwrap();
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn listen_unix_stream() {
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("alog-stream-{}.sock", std::process::id()));
+    let output = path.with_extension("log");
+    let addr = format!("unix://{}", path.display());
+
+    let token = CancellationToken::new();
+    let config = Config::builder().cancel(token.clone()).build().unwrap();
+    let (listen_addr, listen_output) = (addr.clone(), output.clone());
+    let listener = std::thread::spawn(move || {
+        let mut ioconf = IOConfig::default();
+        ioconf.set_listen(&listen_addr);
+        ioconf.set_output(&listen_output);
+        run(&config, &ioconf)
+    });
+
+    let connect = || {
+        for _ in 0..50 {
+            if let Ok(stream) = UnixStream::connect(&path) {
+                return stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("listener not ready");
+    };
+    // an invalid frame closes its connection only
+    connect().write_all(&[b'X'; 70_000]).unwrap();
+    connect()
+        .write_all(b"<34>Oct 11 22:14:15 cdn fastly: 8.8.8.8 - - [11/Oct/2026:22:14:15 +0000]\n")
+        .unwrap();
+    for _ in 0..50 {
+        if std::fs::read(&output).is_ok_and(|o| !o.is_empty()) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    assert_eq!(
+        std::fs::read(&output).unwrap(),
+        b"<34>Oct 11 22:14:15 cdn fastly: 127.0.0.1 - - [11/Oct/2026:22:14:15 +0000]\n"
+    );
+    token.interrupt();
+    listener.join().unwrap().unwrap();
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[cfg(all(unix, any(feature = "s3", feature = "gcs", feature = "azure")))]
+#[test]
+fn remote_child() {
+    use std::io::Read;
+    use std::process::Command;
+
+    let mut writer = remote::ChildWriter::spawn(Command::new("cat")).unwrap();
+    writer.write_all(b"8.8.8.8 XxX\n").unwrap();
+    Box::new(writer).finish().unwrap();
+
+    let mut command = Command::new("echo");
+    command.arg("8.8.8.8 XxX");
+    let mut buffer = vec![];
+    run_raw(
+        &Config::default(),
+        BufReader::new(remote::ChildReader::spawn(command).unwrap()),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n");
+
+    let mut failing = remote::ChildReader::spawn(Command::new("false")).unwrap();
+    assert!(failing.read(&mut [0; 8]).is_err());
+    #[cfg(feature = "s3")]
+    assert!(remote::command(Path::new("s3://bucket/access.log"), false).is_some());
+    #[cfg(feature = "gcs")]
+    assert!(remote::command(Path::new("gs://bucket/access.log"), true).is_some());
+    #[cfg(feature = "azure")]
+    assert!(remote::command(Path::new("az://container"), false).is_none());
+    assert!(remote::command(Path::new("access.log"), false).is_none());
+}
+
+#[cfg(feature = "azure")]
+#[test]
+fn remote_azure_upload() {
+    let download = remote::command(Path::new("az://container/access.log"), false).unwrap();
+    assert_eq!(download.get_program(), "az");
+
+    let upload = remote::command(Path::new("az://container/access.log"), true);
+    match std::env::var("AZURE_STORAGE_ACCOUNT") {
+        Ok(account) => {
+            let upload = upload.unwrap();
+            assert_eq!(upload.get_program(), "azcopy");
+            let url = format!("https://{account}.blob.core.windows.net/container/access.log");
+            assert!(upload.get_args().any(|arg| arg == url.as_str()));
+        }
+        Err(_) => assert!(remote::writer(Path::new("az://container/access.log")).is_err()),
+    }
+}
+
+#[test]
+fn rotate_size() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-rotate-input-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-rotate-output-{}.log", std::process::id()));
+    let rotated = |n: usize| PathBuf::from(format!("{}.{n}", output.display()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n::1 YyY\nfoo ZzZ\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    ioconf.set_rotate_size(20);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(
+        std::fs::read(rotated(1)).unwrap(),
+        b"127.0.0.1 XxX\n::1 YyY\n"
+    );
+    assert_eq!(std::fs::read(&output).unwrap(), b"localhost ZzZ\n");
+    assert_eq!(rotate::rotated_path(&output, "{path}.{n}"), rotated(2));
+
+    std::fs::remove_file(rotated(1)).unwrap();
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn reopen_on_hangup() {
+    let output = std::env::temp_dir().join(format!("alog-reopen-{}.log", std::process::id()));
+    let moved = output.with_extension("log.1");
+
+    let mut ioconf = IOConfig::default();
+    ioconf.set_follow(true);
+    let mut sink = open_sink(&ioconf, &output, false).unwrap();
+    sink.write_all(b"first\n").unwrap();
+    sink.flush().unwrap();
+
+    std::fs::rename(&output, &moved).unwrap();
+    reopen_outputs();
+    sink.write_all(b"second\n").unwrap();
+    sink.finish().unwrap();
+
+    assert_eq!(std::fs::read(&moved).unwrap(), b"first\n");
+    assert_eq!(std::fs::read(&output).unwrap(), b"second\n");
+
+    std::fs::remove_file(moved).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn rotate_time() {
+    assert_eq!(
+        rotate::strftime("access-%Y-%m-%d_%H:%M:%S.log.%%", 1_700_000_000),
+        "access-2023-11-14_22:13:20.log.%"
+    );
+    assert_eq!(rotate::strftime("%s%q", 86400 * 59), "5097600%q");
+    assert_eq!(rotate::strftime("%Y-%m-%d", 86400 * 59), "1970-03-01");
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-rotate-time-input-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-rotate-time-{}-%Y.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    ioconf.set_rotate_time(Interval::Daily);
+    run(&Config::default(), &ioconf).unwrap();
+
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let expanded = PathBuf::from(rotate::strftime(&output.to_string_lossy(), secs));
+    assert_eq!(std::fs::read(&expanded).unwrap(), b"127.0.0.1 XxX\n");
+
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(expanded).unwrap();
+}
+
+#[test]
+fn stdin_input() {
+    let path = Path::new("access.log");
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(path);
+    ioconf.push_stdin();
+
+    assert_eq!(
+        ioconf.get_input(),
+        Some(&vec![Input::Path(path.into()), Input::Stdin])
+    );
+    assert_eq!(Input::Stdin.to_string(), "-");
+    assert_eq!(Input::Stdin.path(), None);
+
+    ioconf.set_in_place(true);
+    assert!(run(&Config::default(), &ioconf).is_err());
+}
+
+#[test]
+fn reader_inputs() {
+    #[derive(Clone, Default)]
+    struct Shared(Arc<std::sync::Mutex<Vec<u8>>>);
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = Shared::default();
+    let mut ioconf = IOConfig::default();
+    ioconf.push_reader("first", io::Cursor::new(b"8.8.8.8 a\n"));
+    ioconf.push_reader("second", &b"::2 b\n"[..]);
+    ioconf.set_writer(output.clone());
+
+    let stats = Stats::new();
+    let mut config = Config::default();
+    config.set_stats(&stats);
+    run(&config, &ioconf).unwrap();
+    assert_eq!(*output.0.lock().unwrap(), b"127.0.0.1 a\n::1 b\n");
+    let files = stats.summary().files;
+    assert_eq!(files[0].0, Path::new("first"));
+    assert_eq!(files[1].0, Path::new("second"));
+    assert_eq!(files[1].1.lines_read, 1);
+
+    let error = run(&Config::default(), &ioconf).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Can not open output '-': output writer was already used"
+    );
+
+    ioconf.set_in_place(true);
+    assert!(matches!(
+        run(&Config::default(), &ioconf),
+        Err(Error::Usage(_))
+    ));
+
+    struct Broken;
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken input"))
+        }
+    }
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken output"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_reader(
+        "broken",
+        io::BufReader::new((&b"8.8.8.8 a\n"[..]).chain(Broken)),
+    );
+    ioconf.set_writer(Shared::default());
+    let error = run(&Config::default(), &ioconf).unwrap_err();
+    assert!(matches!(error, Error::Anonymize { path, .. } if path == Path::new("broken")));
+
+    // longer than the output buffer
+    let mut line = b"8.8.8.8 ".to_vec();
+    line.resize(64 * 1024, b'x');
+    line.push(b'\n');
+    let mut ioconf = IOConfig::default();
+    ioconf.push_reader("first", io::Cursor::new(line));
+    ioconf.set_writer(Broken);
+    let error = run(&Config::default(), &ioconf).unwrap_err();
+    assert!(matches!(error, Error::Write { input, .. } if input == Path::new("first")));
+}
+
+#[test]
+fn owned_ioconfig() {
+    fn ioconfig(dir: &Path) -> IOConfig<'static> {
+        let mut ioconf = IOConfig::default();
+        ioconf.push_input_owned(dir.join("Cargo.toml"));
+        ioconf.push_input_owned(std::ffi::OsString::from("README.md"));
+        ioconf.set_output(std::env::temp_dir().join("alog-owned-ioconfig.log"));
+        ioconf.set_rotate_name(String::from("{path}.old"));
+        ioconf
+    }
+
+    let ioconf = ioconfig(Path::new(env!("CARGO_MANIFEST_DIR")));
+    let inputs: Vec<_> = ioconf
+        .get_input()
+        .unwrap()
+        .iter()
+        .map(Input::path)
+        .collect();
+    assert_eq!(
+        inputs[0],
+        Some(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("Cargo.toml")
+                .as_path()
+        )
+    );
+    assert_eq!(inputs[1], Some(Path::new("README.md")));
+    assert_eq!(ioconf.get_rotate_name(), "{path}.old");
+
+    let mut config = Config::default();
+    config.set_skip(true);
+    run(&config, &ioconf).unwrap();
+    std::fs::remove_file(ioconf.get_output().unwrap()).unwrap();
+}
+
+#[test]
+fn null_data() {
+    use std::io::Cursor;
+
+    let mut buffer = vec![];
+    let mut conf = Config::default();
+    conf.set_null_data(true);
+    run_raw(
+        &conf,
+        Cursor::new(b"8.8.8.8\0::1 multi\nline\0 foo bar"),
+        &mut buffer,
+    )
+    .unwrap();
+
+    assert_eq!(buffer, b"127.0.0.1\0::1 multi\nline\0localhost bar");
+}
+
+#[test]
+fn crlf() {
+    use std::io::Cursor;
+
+    let mut buffer = vec![];
+    let mut conf = Config::default();
+    conf.set_thorough(true);
+    run_raw(
+        &conf,
+        Cursor::new(b"8.8.8.8 x 8.8.8.8\r\n::2\r\nfoo\n"),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 x 127.0.0.1\r\n::1\r\nlocalhost\n");
+
+    buffer.clear();
+    conf.set_format(Format::Mail);
+    run_raw(
+        &conf,
+        Cursor::new(b"imap-login: user=<alice>, rip=8.8.8.8\r\n"),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"imap-login: user=<->, rip=127.0.0.1\r\n");
+}
+
+#[test]
+fn tee_outputs() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-tee-input-{}.log", std::process::id()));
+    let a = dir.join(format!("alog-tee-a-{}.log", std::process::id()));
+    let b = dir.join(format!("alog-tee-b-{}.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.push_output(&a);
+    ioconf.push_output(&b);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(ioconf.get_output(), Some(a.as_path()));
+    assert_eq!(std::fs::read(&a).unwrap(), b"127.0.0.1 XxX\n");
+    assert_eq!(std::fs::read(&b).unwrap(), b"127.0.0.1 XxX\n");
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(a).unwrap();
+    std::fs::remove_file(b).unwrap();
+}
+
+#[test]
+fn resume() {
+    use std::io::Cursor;
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-resume-input-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-resume-output-{}.log", std::process::id()));
+    let state = dir.join(format!("alog-resume-{}.json", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    ioconf.set_resume(&state);
+    run(&Config::default(), &ioconf).unwrap();
+
+    let mut f = File::options().append(true).open(&input).unwrap();
+    f.write_all(b"::2 YyY\n").unwrap();
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n::1 YyY\n");
+
+    let mut chunks = checkpoint::State::load(&state).unwrap();
+    assert_eq!(chunks.get(&input), 20);
+    let mut buffer = vec![];
+    checkpoint::replace(
+        &Config::default(),
+        Cursor::new(b"a b\nc d\ne f"),
+        &mut buffer,
+        &mut chunks,
+        Path::new("\"quoted\"\\path"),
+        1,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"localhost b\nlocalhost d\nlocalhost f");
+    let chunks = checkpoint::State::load(&state).unwrap();
+    assert_eq!(chunks.get(Path::new("\"quoted\"\\path")), 11);
+
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+    std::fs::remove_file(state).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn mmap_input() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("alog-mmap-input-{}.log", std::process::id()));
+    let empty = dir.join(format!("alog-mmap-empty-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-mmap-output-{}.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\r\n  ::2 YyY\nfoo").unwrap();
+    std::fs::write(&empty, b"").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.push_input(&empty);
+    ioconf.set_output(&output);
+    ioconf.set_mmap(true);
+    run(&Config::default(), &ioconf).unwrap();
+
+    assert_eq!(
+        std::fs::read(&output).unwrap(),
+        b"127.0.0.1 XxX\r\n::1 YyY\nlocalhost"
+    );
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(empty).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn max_line_len() {
+    use std::io::Cursor;
+
+    let input = b"8.8.8.8 XxX\n8.8.8.8 0123456789abcdef\n::2 YyY\n";
+    let mut conf = Config::default();
+    conf.set_max_line_len(12);
+
+    let mut buffer = vec![];
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"127.0.0.1 XxX\n8.8.8.8 0123456789abcdef\n::1 YyY\n"
+    );
+
+    buffer.clear();
+    conf.set_oversized(Oversized::Skip);
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n::1 YyY\n");
+
+    conf.set_oversized(Oversized::Error);
+    assert!(run_raw(&conf, Cursor::new(input), &mut vec![]).is_err());
+}
+
+#[test]
+fn block_size() {
+    use std::io::Cursor;
+
+    let input = b"8.8.8.8 XxX\r\n8.8.8.8 0123456789abcdef\n::2 YyY\nexample.com -";
+    let mut conf = Config::default();
+    conf.set_block_size(8);
+
+    let mut buffer = vec![];
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"127.0.0.1 XxX\r\n127.0.0.1 0123456789abcdef\n::1 YyY\nlocalhost -"
+    );
+
+    buffer.clear();
+    conf.set_max_line_len(12);
+    conf.set_oversized(Oversized::Skip);
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(buffer, b"::1 YyY\n");
+}
+
+#[test]
+fn parallel_jobs() {
+    use std::io::Cursor;
+
+    let mut input = vec![];
+    for n in 0..100_000 {
+        writeln!(
+            input,
+            "10.0.{}.{} - frank [10/Oct/2000:13:55:36 -0700] \"GET /{n}\" 200 2326",
+            n / 256 % 256,
+            n % 256
+        )
+        .unwrap();
+        writeln!(
+            input,
+            "host{n}.example.org - - [10/Oct/2000:13:55:36 -0700] \"GET /\" 200 2326"
+        )
+        .unwrap();
+    }
+
+    let mut conf = Config::default();
+    conf.set_authuser(true);
+    conf.set_thorough(true);
+    let mut serial = vec![];
+    run_raw(&conf, Cursor::new(&input), &mut serial).unwrap();
+
+    conf.set_jobs(4);
+    let mut parallel = vec![];
+    run_raw(&conf, Cursor::new(&input), &mut parallel).unwrap();
+
+    assert_eq!(serial.len(), parallel.len());
+    assert!(serial == parallel);
+}
+
+#[test]
+fn parallel_files() {
+    let dir = std::env::temp_dir().join(format!("alog-parallel-files-{}", std::process::id()));
+    let out = dir.join("out");
+    std::fs::create_dir_all(&out).unwrap();
+    let inputs: Vec<PathBuf> = (0..8).map(|n| dir.join(format!("{n}.log"))).collect();
+    for (n, input) in inputs.iter().enumerate() {
+        std::fs::write(input, format!("10.0.0.{n} XxX\nhost{n} YyY\n")).unwrap();
+    }
+
+    let mut conf = Config::default();
+    conf.set_jobs(3);
+    let mut ioconf = IOConfig::default();
+    for input in &inputs {
+        ioconf.push_input(input);
+    }
+    ioconf.set_output_dir(&out);
+    run(&conf, &ioconf).unwrap();
+
+    for n in 0..8 {
+        assert_eq!(
+            std::fs::read(out.join(format!("{n}.log"))).unwrap(),
+            b"127.0.0.1 XxX\nlocalhost YyY\n"
+        );
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn whitespace_scan() {
+    assert_eq!(find_whitespace(b"8.8.8.8 - -"), Some(7));
+    assert_eq!(find_whitespace(b"8.8.8.8\x0C- -\n"), Some(7));
+    assert_eq!(find_whitespace(b"8.8.8.8\r\n"), Some(7));
+    assert_eq!(find_whitespace(b"example.com"), None);
+    assert_eq!(skip_whitespace(b" \t 8.8.8.8"), 3);
+    assert_eq!(skip_whitespace(b"8.8.8.8"), 0);
+    assert_eq!(skip_whitespace(b" \n"), 2);
+}
+
+#[test]
+fn replacement_bytes() {
+    let conf = Config::default();
+
+    assert_eq!(replacement(&conf, b"8.8.8.8"), b"127.0.0.1");
+    assert_eq!(replacement(&conf, b"2001:db8::1"), b"::1");
+    assert_eq!(replacement(&conf, b"example.org"), b"localhost");
+    assert_eq!(replacement(&conf, b"8.8.8.8\xff"), b"localhost");
+}
+
+#[test]
+fn passthrough_writes() {
+    struct Counter(Vec<usize>);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(vec![]);
+    replace_first_word(&Config::default(), b"127.0.0.1 XxX\n", &mut counter).unwrap();
+    replace_first_word(&Config::default(), b"8.8.8.8 XxX\n", &mut counter).unwrap();
+    assert_eq!(counter.0, vec![14, 9, 5]);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn run_async_stream() {
+    let input = &b"8.8.8.8 XxX\r\n2001:db8::1 - user\nexample.com\n"[..];
+    let mut buffer = vec![];
+
+    run_async(&Config::default(), input, &mut buffer)
+        .await
+        .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\r\n::1 - user\nlocalhost\n");
+}
+
+#[test]
+fn progress_counter() {
+    use std::io::Cursor;
+
+    let progress = progress::Progress::new(b'\n');
+    let reader = progress::Counter {
+        reader: Cursor::new(b"8.8.8.8 XxX\n::2 YyY\nexample.com -"),
+        progress: Some(&progress),
+    };
+
+    let mut buffer = vec![];
+    run_raw(&Config::default(), reader, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n::1 YyY\nlocalhost -");
+    assert_eq!(progress.get(), (33, 2));
+}
+
+#[test]
+fn config_from_toml() {
+    let conf = Config::from_toml(
+        "# anonymize proxies\nhost = 'anonymous' # comment\nipv4 = \"0.0.0.0\"\n\nformat = \"varnish\"\nthorough = true\nmax_line_len = 65_536\n",
+    )
+    .unwrap();
+    assert_eq!(conf.get_host_value(), "anonymous");
+    assert_eq!(conf.get_ipv4_value(), "0.0.0.0");
+    assert_eq!(conf.get_ipv6_value(), "::1");
+    assert_eq!(conf.get_format(), Format::Varnish);
+    assert!(conf.get_thorough());
+    assert_eq!(conf.get_max_line_len(), Some(65536));
+
+    assert!(Config::from_toml("thorough = 1").is_err());
+    assert!(Config::from_toml("unknown = true").is_err());
+    assert!(Config::from_toml("host = \"a\\tb\"").is_err());
+}
+
+#[test]
+fn dry_run_diff() {
+    use std::io::Cursor;
+
+    let input = b"8.8.8.8 XxX\n127.0.0.1 YyY\n::2 ZzZ";
+    let mut buffer = vec![];
+    diff::preview(
+        &Config::default(),
+        DryRun::Diff,
+        "access.log",
+        Cursor::new(input),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "--- access.log\n+++ access.log\n@@ -1 +1 @@\n-8.8.8.8 XxX\n+127.0.0.1 XxX\n@@ -3 +3 @@\n-::2 ZzZ\n\\ No newline at end of file\n+::1 ZzZ\n\\ No newline at end of file\n"
+    );
+
+    let mut buffer = vec![];
+    diff::preview(
+        &Config::default(),
+        DryRun::Changed,
+        "-",
+        Cursor::new(input),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n::1 ZzZ");
+}
+
+#[test]
+fn stats_summary() {
+    use std::io::Cursor;
+
+    let stats = Stats::new();
+    let mut conf = Config::default();
+    conf.set_authuser(true);
+    conf.set_skip(true);
+    conf.set_stats(&stats);
+
+    let input = b"8.8.8.8 - frank [10/Oct/2000:13:55:36 -0700] x\n::2 - - [x\n 127.0.0.1 - - [x\nexample.com - - [x\r\n";
+    let mut buffer = vec![];
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+
+    assert_eq!(stats.lines_read(), 4);
+    assert_eq!(stats.lines_modified(), 4);
+    assert_eq!(stats.skipped(), 0);
+    assert_eq!(stats.ipv4(), 1);
+    assert_eq!(stats.ipv6(), 1);
+    assert_eq!(stats.hosts(), 1);
+    assert_eq!(stats.authuser(), 1);
+    assert_eq!(stats.distinct_addresses(), 3);
+    assert_eq!(stats.distinct_users(), 1);
+    assert_eq!(stats.bytes_read(), input.len() as u64);
+    assert_eq!(stats.bytes_written(), buffer.len() as u64);
+    assert_eq!(
+        stats.to_json(),
+        format!("{{\"lines_read\":4,\"lines_modified\":4,\"skipped\":0,\"dropped\":0,\"ipv4\":1,\"ipv6\":1,\"hosts\":1,\"authuser\":1,\"distinct_addresses\":3,\"distinct_users\":1,\"bytes_read\":{},\"bytes_written\":{},\"files\":[]}}", input.len(), buffer.len())
+    );
+}
+
+#[test]
+fn distinct_addresses() {
+    let stats = Stats::new();
+    let mut conf = Config::builder()
+        .authuser(true)
+        .stats(&stats)
+        .build()
+        .unwrap();
+    let t = "[16/Oct/2026:10:00:00 +0000]";
+    let input = format!("8.8.8.8 - a {t} 1\n::2 - b {t} 2\n8.8.8.8 - a {t} 3\n::2 - - {t} 4\n");
+    let input = input.as_bytes();
+    run_raw(&conf, input, &mut vec![]).unwrap();
+    assert_eq!(stats.ipv4(), 2);
+    assert_eq!(stats.distinct_addresses(), 2);
+    assert_eq!(stats.distinct_users(), 2);
+
+    // the same addresses read again by worker threads are not counted twice
+    conf.set_jobs(3);
+    run_raw(&conf, input, &mut vec![]).unwrap();
+    assert_eq!(stats.ipv4(), 4);
+    assert_eq!(stats.distinct_addresses(), 2);
+    assert!(stats.summary().to_string().contains("distinct addrs:  2\n"));
+}
+
+#[test]
+fn run_summaries() {
+    let dir = std::env::temp_dir().join(format!("alog-summary-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (a, b, out) = (dir.join("a.log"), dir.join("b.log"), dir.join("out.log"));
+    std::fs::write(&a, b"8.8.8.8 XxX\nexample.com XxX\n").unwrap();
+    std::fs::write(&b, b"::2 XxX\n127.0.0.1 XxX\n").unwrap();
+
+    let stats = Stats::new();
+    let mut conf = Config::default();
+    conf.set_stats(&stats);
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&a);
+    ioconf.push_input(&b);
+    ioconf.set_output(&out);
+    let summary = run_summary(&conf, &ioconf).unwrap();
+
+    assert_eq!(summary.lines_read, 4);
+    assert_eq!(summary.lines_modified, 3);
+    assert_eq!((summary.ipv4, summary.ipv6, summary.hosts), (1, 1, 1));
+    assert_eq!(summary.bytes_read, 50);
+    assert_eq!(summary.bytes_written, 50);
+    assert_eq!(summary.files.len(), 2);
+    assert_eq!(summary.files[0].0, a);
+    assert_eq!(summary.files[0].1.lines_read, 2);
+    assert_eq!(summary.files[1].0, b);
+    assert_eq!(summary.files[1].1.lines_modified, 1);
+    assert_eq!(stats.summary(), summary);
+
+    // Every run is summarized separately, config stats keep the totals
+    let summary = run_raw_summary(&conf, &b"8.8.8.8 XxX\n"[..], &mut vec![]).unwrap();
+    assert_eq!(summary.lines_read, 1);
+    assert!(summary.files.is_empty());
+    assert_eq!(stats.lines_read(), 5);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn logger_events() {
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<(Level, String)>>);
+
+    impl Log for Events {
+        fn enabled(&self, level: Level) -> bool {
+            level <= Level::Debug
+        }
+
+        fn log(&self, level: Level, message: fmt::Arguments<'_>) {
+            self.0.lock().unwrap().push((level, message.to_string()));
+        }
+    }
+
+    let events = Events::default();
+    let mut conf = Config::default();
+    conf.set_skip(true);
+    conf.set_trim(false);
+    conf.set_max_line_len(16);
+    conf.set_logger(&events);
+
+    let mut buffer = vec![];
+    run_raw(
+        &conf,
+        Cursor::new(b"8.8.8.8 XxX\n 8.8.8.8 XxX\n8.8.8.8 0123456789abcdef\n"),
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer, b"127.0.0.1 XxX\n8.8.8.8 0123456789abcdef\n");
+    assert_eq!(
+        *events.0.lock().unwrap(),
+        vec![
+            (Level::Debug, "line skipped".to_string()),
+            (
+                Level::Warn,
+                "line exceeds maximum length of 16 bytes, written as is".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn progress_input_size() {
+    let dir = std::env::temp_dir();
+    let a = dir.join(format!("alog-size-a-{}.log", std::process::id()));
+    let b = dir.join(format!("alog-size-b-{}.log", std::process::id()));
+    std::fs::write(&a, b"8.8.8.8 XxX\n").unwrap();
+    std::fs::write(&b, b"::2 YyY\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    assert_eq!(input_size(&ioconf), None);
+    ioconf.push_input(&a);
+    ioconf.push_input(&b);
+    assert_eq!(input_size(&ioconf), Some(20));
+    ioconf.push_stdin();
+    assert_eq!(input_size(&ioconf), None);
+
+    std::fs::remove_file(a).unwrap();
+    std::fs::remove_file(b).unwrap();
+}
+
+#[test]
+fn check_findings() {
+    use std::io::Cursor;
+
+    let input = b"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /?from=8.8.8.8. HTTP/1.1\" 200 \"Chrome/120.0.0.0\"\n::1 - - [x] \"POST /contact?mail=jane.doe@example.com\" [2001:db8::2]:443\n0.0.0.0 :: cafe::\n";
+    let mut report = vec![];
+    let count = check_raw(&Config::default(), Cursor::new(input), &mut report).unwrap();
+
+    assert_eq!(count, 4);
+    assert_eq!(
+        String::from_utf8(report).unwrap(),
+        "-:1: IPv4 address '8.8.8.8'\n-:2: email address 'jane.doe@example.com'\n-:2: IPv6 address '2001:db8::2'\n-:3: IPv6 address 'cafe::'\n"
+    );
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn drop_patterns() {
+    use std::io::Cursor;
+
+    let input = b"8.8.8.8 - - [x] \"GET /health HTTP/1.1\" 200\r\n8.8.8.8 - - [x] \"GET / HTTP/1.1\" 200\n::2 - - [x] \"GET /status HTTP/1.1\" 404\n";
+    let mut conf = Config::default();
+    conf.push_drop_pattern("\"GET /health ").unwrap();
+    conf.push_drop_pattern(" 40\\d$").unwrap();
+    assert!(conf.push_drop_pattern("(").is_err());
+
+    let mut buffer = vec![];
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 - - [x] \"GET / HTTP/1.1\" 200\n");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn match_patterns() {
+    use std::io::Cursor;
+
+    let input = b"8.8.8.8 - - [x] \"GET /api/users HTTP/1.1\" 200\n8.8.8.8 - - [x] \"GET / HTTP/1.1\" 200\n::2 - - [x] \"GET /api/health HTTP/1.1\" 200\n";
+    let mut conf = Config::default();
+    conf.push_match_pattern("\"GET /api/").unwrap();
+    conf.push_drop_pattern("/health ").unwrap();
+
+    let mut buffer = vec![];
+    run_raw(&conf, Cursor::new(input), &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        b"127.0.0.1 - - [x] \"GET /api/users HTTP/1.1\" 200\n"
+    );
+}
+
+#[test]
+fn keep_going() {
+    let dir = std::env::temp_dir();
+    let missing = dir.join(format!(
+        "alog-keep-going-missing-{}.log",
+        std::process::id()
+    ));
+    let input = dir.join(format!("alog-keep-going-input-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-keep-going-output-{}.log", std::process::id()));
+    std::fs::write(&input, b"8.8.8.8 XxX\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&missing);
+    ioconf.push_input(&input);
+    ioconf.set_output(&output);
+    let e = run(&Config::default(), &ioconf).unwrap_err();
+    assert!(e.to_string().starts_with("Can not open input"));
+    assert_eq!(std::fs::read(&output).unwrap(), b"");
+
+    ioconf.set_keep_going(true);
+    let e = run(&Config::default(), &ioconf).unwrap_err();
+    assert_eq!(e.to_string(), "1 of 2 input(s) failed");
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n");
+
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn format_profiles() {
+    for format in Format::ALL {
+        assert_eq!(format.to_string().parse::<Format>().unwrap(), format);
+        assert!(!format.description().is_empty());
+        assert!(!format.fields().is_empty());
+    }
+}
+
+#[test]
+fn input_limits() {
+    let dir = std::env::temp_dir();
+    let first = dir.join(format!("alog-limit-first-{}.log", std::process::id()));
+    let second = dir.join(format!("alog-limit-second-{}.log", std::process::id()));
+    let output = dir.join(format!("alog-limit-output-{}.log", std::process::id()));
+    std::fs::write(&first, b"8.8.8.8 XxX\n::2 YyY\n").unwrap();
+    std::fs::write(&second, b"foo ZzZ\n1.1.1.1 ZzZ\n").unwrap();
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&first);
+    ioconf.push_input(&second);
+    ioconf.set_output(&output);
+    ioconf.set_output_mode(OutputMode::Truncate);
+    ioconf.set_max_lines(3);
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(
+        std::fs::read(&output).unwrap(),
+        b"127.0.0.1 XxX\n::1 YyY\nlocalhost ZzZ\n"
+    );
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&first);
+    ioconf.push_input(&second);
+    ioconf.set_output(&output);
+    ioconf.set_output_mode(OutputMode::Truncate);
+    ioconf.set_max_bytes(13);
+    run(&Config::default(), &ioconf).unwrap();
+    assert_eq!(std::fs::read(&output).unwrap(), b"127.0.0.1 XxX\n::1 YyY\n");
+
+    std::fs::remove_file(first).unwrap();
+    std::fs::remove_file(second).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn error_kinds() {
+    let mut ioconf = IOConfig::default();
+    ioconf.set_in_place(true);
+    ioconf.push_stdin();
+    let e = run(&Config::default(), &ioconf).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Usage);
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input("/nonexistent/alog-input.log");
+    let e = run(&Config::default(), &ioconf).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Io);
+    assert!(
+        matches!(&e, Error::OpenInput { path, source } if path == Path::new("/nonexistent/alog-input.log")
+            && source.kind() == io::ErrorKind::NotFound)
+    );
+    assert!(std::error::Error::source(&e).is_some());
+
+    let e = Config::from_toml(
+        "trim = true
+
+jobs = 'x'
+",
+    )
+    .unwrap_err();
+    assert!(matches!(
+        e,
+        Error::Parse {
+            line_no: Some(3),
+            ..
+        }
+    ));
+    assert_eq!(e.to_string(), "line 3: invalid value for 'jobs'");
+
+    let input = std::env::temp_dir().join(format!("alog-check-kind-{}.log", std::process::id()));
+    std::fs::write(&input, b"127.0.0.1 from 8.8.8.8\n").unwrap();
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(&input);
+    ioconf.set_check(true);
+    let mut report = vec![];
+    let e = check_inputs(&Config::default(), &ioconf, &mut report).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Findings);
+    assert!(matches!(e, Error::Findings { count: 1 }));
+    assert_eq!(
+        String::from_utf8(report).unwrap(),
+        format!("{}:1: IPv4 address '8.8.8.8'\n", input.display())
+    );
+    std::fs::remove_file(input).unwrap();
+
+    #[cfg(feature = "regex")]
+    {
+        let stats = Stats::skips_only();
+        let mut conf = Config::default();
+        conf.set_skip(true);
+        conf.push_drop_pattern("YyY").unwrap();
+        conf.set_stats(&stats);
+        let mut buffer = vec![];
+        let input = b"8.8.8.8 XxX\n::2 YyY\n\n";
+        run_raw(&conf, &input[..], &mut buffer).unwrap();
+        assert_eq!(buffer, b"127.0.0.1 XxX\n");
+        assert_eq!(
+            (stats.skipped(), stats.dropped(), stats.lines_read()),
+            (1, 0, 0)
+        );
+    }
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn config_builder() {
+    let conf = Config::builder()
+        .host("anonymous")
+        .thorough(true)
+        .drop_pattern("^#")
+        .build()
+        .unwrap();
+    assert_eq!(conf.get_host_value(), "anonymous");
+    assert!(conf.get_thorough());
+    assert_eq!(conf.get_drop_patterns().len(), 1);
+
+    let mut buffer = vec![];
+    run_raw(&conf, &b"# comment\nexample.com foo\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"anonymous foo\n");
+
+    let conf = ConfigBuilder::from(conf)
+        .match_pattern("foo")
+        .build()
+        .unwrap();
+    assert_eq!(conf.get_drop_patterns().len(), 1);
+    assert_eq!(conf.get_match_patterns().len(), 1);
+
+    for builder in [
+        Config::builder().jobs(0),
+        Config::builder().block_size(0),
+        Config::builder().max_line_len(0),
+        Config::builder().match_pattern("("),
+    ] {
+        assert_eq!(builder.build().unwrap_err().kind(), ErrorKind::Usage);
+    }
+}
+
+#[test]
+fn config_validation() {
+    assert_eq!(Config::default().validate(), Ok(()));
+
+    let mut conf = Config::default();
+    conf.set_ipv6_value("::1\n");
+    conf.set_host_value("local\0host");
+    conf.set_block_size(0);
+    conf.set_format(Format::Sshd);
+    conf.set_authuser(true);
+    assert_eq!(
+        conf.validate().unwrap_err(),
+        [
+            ConfigError::Replacement {
+                field: "ipv6",
+                value: "::1\n".to_string()
+            },
+            ConfigError::Replacement {
+                field: "host",
+                value: "local\0host".to_string()
+            },
+            ConfigError::Zero {
+                field: "block_size"
+            },
+            ConfigError::Unsupported {
+                field: "authuser",
+                format: Format::Sshd
+            },
+        ]
+    );
+
+    let error = Config::builder().ipv4("a\rb").build().unwrap_err();
+    assert!(matches!(error, Error::Invalid(ref errors) if errors.len() == 1));
+    assert_eq!(
+        error.to_string(),
+        "Invalid ipv4 replacement \"a\\rb\", must not contain line terminators or NUL"
+    );
+}
+
+#[test]
+fn owned_replacements() {
+    let conf = {
+        let toml = String::from("host = \"anonymous\"\n");
+        let mut conf = Config::from_toml(&toml).unwrap();
+        conf.set_ipv4_value(format!("10.0.0.{}", 1));
+        conf
+    };
+    let mut buffer = vec![];
+    run_raw(&conf, &b"example.com\n8.8.8.8\n"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"anonymous\n10.0.0.1\n");
+
+    let conf = Config::builder().ipv6(String::from("::2")).build().unwrap();
+    assert_eq!(conf.get_ipv6_value(), "::2");
+}
+
+#[cfg(all(feature = "serde", feature = "regex"))]
+#[test]
+fn serde_config() {
+    let mut conf = Config::builder()
+        .host("anonymous")
+        .format(Format::Sshd)
+        .oversized(Oversized::Skip)
+        .drop_pattern("^#")
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&conf).unwrap();
+    assert!(json.contains(r#""host":"anonymous""#));
+    assert!(json.contains(r#""format":"sshd""#));
+    assert!(json.contains(r#""drop_patterns":["^#"]"#));
+    assert!(!json.contains("stats"));
+
+    conf = serde_json::from_str(&json).unwrap();
+    assert_eq!(conf.get_host_value(), "anonymous");
+    assert_eq!(conf.get_format(), Format::Sshd);
+    assert_eq!(conf.get_oversized(), Oversized::Skip);
+    assert_eq!(conf.get_drop_patterns()[0].as_str(), "^#");
+
+    let conf: Config = serde_json::from_str(r#"{"thorough": true}"#).unwrap();
+    assert!(conf.get_thorough());
+    assert_eq!(conf.get_ipv4_value(), "127.0.0.1");
+    assert!(serde_json::from_str::<Config>(r#"{"match_patterns": ["("]}"#).is_err());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn line_observer() {
+    use std::sync::Mutex;
+
+    let seen = Mutex::new(vec![]);
+    let observer = |line: &LineInfo| {
+        seen.lock().unwrap().push((
+            line.outcome(),
+            line.modified(),
+            line.replaced(),
+            line.word_len(),
+        ));
+    };
+    let config = Config::builder()
+        .authuser(true)
+        .skip(true)
+        .drop_pattern("^#")
+        .observer(&observer)
+        .build()
+        .unwrap();
+    let input = b"8.8.8.8 - - [x\n::1 - frank [10/Oct/2000:13:55:36 -0700] x\n# comment\n example.com - - [x\n";
+    run_raw(&config, &input[..], &mut vec![]).unwrap();
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [
+            (Outcome::Written, true, Some(Address::Ipv4), 7),
+            (Outcome::Written, true, None, 3),
+            (Outcome::Dropped, false, None, 1),
+            (Outcome::Written, true, Some(Address::Host), 11),
+        ]
+    );
+}
+
+#[test]
+fn process_io_errors() {
+    let mut config = Config::default();
+    config.set_max_line_len(4);
+    config.set_oversized(Oversized::Error);
+    let error = process(&config, &b"8.8.8.8 a\n"[..], vec![]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let token = CancellationToken::new();
+    token.cancel();
+    config.set_cancel(token);
+    let error = process(&config, &b"1 a\n"[..], vec![]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Other);
+    assert_eq!(error.to_string(), "Cancelled");
+}
+
+#[test]
+fn cancellation() {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let config = Config::builder()
+        .cancel(token)
+        .scrubber(move |_: &mut Vec<u8>, _: &LineCtx| cancel.cancel())
+        .build()
+        .unwrap();
+
+    let mut buffer = vec![];
+    let result = run_raw(&config, &b"8.8.8.8 a\n::2 b\n"[..], &mut buffer);
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert_eq!(buffer, b"127.0.0.1 a\n");
+
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(Path::new("Cargo.toml"));
+    ioconf.push_input(Path::new("README.md"));
+    ioconf.set_keep_going(true);
+    ioconf.set_output(Path::new("/dev/null"));
+    assert!(matches!(run(&config, &ioconf), Err(Error::Cancelled)));
+}
+
+#[test]
+fn interruption() {
+    let token = CancellationToken::new();
+    let config = Config::builder().cancel(token.clone()).build().unwrap();
+    token.interrupt();
+
+    // no signal handlers are installed, the token stops reading before the first line
+    let mut ioconf = IOConfig::default();
+    ioconf.push_input(Path::new("Cargo.toml"));
+    ioconf.set_output(Path::new("/dev/null"));
+    ioconf.set_mmap(false);
+    assert!(matches!(
+        run(&config, &ioconf),
+        Err(Error::Interrupted { offset: 0, .. })
+    ));
+
+    // interrupted mid-line the line is read to its end
+    let token = CancellationToken::new();
+    let mut reader =
+        signal::Interruptible::new(&b"8.8.8.8 a\n::2 b\n"[..], Some(token.clone()), b'\n');
+    let mut line = vec![];
+    reader.read_exact(&mut [0; 3]).unwrap();
+    token.interrupt();
+    reader.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(line, b".8.8 a\n");
+    assert_eq!(reader.read_until(b'\n', &mut line).unwrap(), 0);
+    assert_eq!(reader.consumed, 10);
+}
+
+#[cfg(feature = "cdylib")]
+#[test]
+fn ffi() {
+    use std::ffi::CStr;
+
+    let config = unsafe { ffi::alog_config_from_toml(c"host = \"anon\"".as_ptr()) };
+    assert!(!config.is_null());
+    let input = b"8.8.8.8 a\nexample.com b\n";
+    let (mut output, mut len) = (std::ptr::null_mut(), 0);
+    let status = unsafe {
+        ffi::alog_anonymize_buffer(config, input.as_ptr(), input.len(), &mut output, &mut len)
+    };
+    assert_eq!(status, ffi::ALOG_OK);
+    assert_eq!(
+        unsafe { std::slice::from_raw_parts(output, len) },
+        b"127.0.0.1 a\nanon b\n"
+    );
+    unsafe {
+        ffi::alog_buffer_free(output, len);
+        ffi::alog_config_free(config);
+    }
+
+    let config = unsafe { ffi::alog_config_from_toml(c"ipv4 = 1".as_ptr()) };
+    assert!(config.is_null());
+    let message = unsafe { CStr::from_ptr(ffi::alog_last_error()) };
+    assert!(message.to_str().unwrap().contains("ipv4"));
+    let status = unsafe {
+        ffi::alog_anonymize_buffer(config, input.as_ptr(), input.len(), &mut output, &mut len)
+    };
+    assert_eq!(status, ffi::ALOG_OK);
+    assert_eq!(
+        unsafe { std::slice::from_raw_parts(output, len) },
+        b"127.0.0.1 a\nlocalhost b\n"
+    );
+    unsafe { ffi::alog_buffer_free(output, len) };
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_chunks() {
+    fn output() -> Vec<u8> {
+        unsafe { std::slice::from_raw_parts(wasm::alog_output_ptr(), wasm::alog_output_len()) }
+            .to_vec()
+    }
+
+    let toml = b"host = \"anon\"";
+    assert_eq!(
+        unsafe { wasm::alog_configure(toml.as_ptr(), toml.len()) },
+        0
+    );
+    let mut lines = vec![];
+    for chunk in [&b"8.8.8.8 a\nexam"[..], b"ple.com b\n", b"::2 c"] {
+        assert_eq!(
+            unsafe { wasm::process_chunk(chunk.as_ptr(), chunk.len()) },
+            0
+        );
+        lines.push(output());
+    }
+    assert_eq!(wasm::alog_finish(), 0);
+    lines.push(output());
+    assert_eq!(lines, [&b"127.0.0.1 a\n"[..], b"anon b\n", b"", b"::1 c"]);
+
+    // a failing line does not stop the rest of the chunk
+    let toml = b"strict = true";
+    assert_eq!(
+        unsafe { wasm::alog_configure(toml.as_ptr(), toml.len()) },
+        0
+    );
+    let chunk = b"8.8.8.8 a\n\n::2 b\n";
+    assert_eq!(
+        unsafe { wasm::process_chunk(chunk.as_ptr(), chunk.len()) },
+        1
+    );
+    assert_eq!(output(), b"127.0.0.1 a\n::1 b\n");
+
+    let toml = b"format = \"sshd\"\nauthuser = true";
+    assert_eq!(
+        unsafe { wasm::alog_configure(toml.as_ptr(), toml.len()) },
+        2
+    );
+    let error =
+        unsafe { std::slice::from_raw_parts(wasm::alog_error_ptr(), wasm::alog_error_len()) };
+    assert_eq!(
+        error,
+        b"Option authuser is not supported with format 'sshd'"
+    );
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn python_bindings() {
+    use pyo3::prelude::*;
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let alog = PyModule::new(py, "alog").unwrap();
+        python::alog(&alog).unwrap();
+        pyo3::py_run!(
+            py,
+            alog,
+            r#"
+assert alog.anonymize_line("8.8.8.8 a\n") == "127.0.0.1 a\n"
+assert alog.anonymize_line(b"::2 b") == b"::1 b"
+assert alog.anonymize_line("") is None
+
+config = alog.Config('host = "anon"')
+lines = alog.anonymize_lines([b"example.com a\n", b"", b"1.2.3.4 b"], config)
+assert list(lines) == [b"anon a\n", b"127.0.0.1 b"]
+
+try:
+    alog.Config('format = "sshd"\nthorough = true')
+    assert False
+except ValueError as e:
+    assert "thorough" in str(e)
+
+summary = alog.anonymize_file("Cargo.toml", "/dev/null", config)
+assert summary["lines_read"] > 0 and summary["bytes_written"] > 0
+"#
+        );
+    });
+}
+
+#[test]
+fn line_scrubbers() {
+    struct Upper;
+    impl LineScrubber for Upper {
+        fn scrub(&self, line: &mut Vec<u8>, _ctx: &LineCtx) {
+            line.make_ascii_uppercase();
+        }
+    }
+    struct Original;
+    impl LineScrubber for Original {
+        fn scrub(&self, line: &mut Vec<u8>, ctx: &LineCtx) {
+            line.push(b' ');
+            line.extend_from_slice(&ctx.original()[..3]);
+        }
+    }
+
+    let mut conf = Config::default();
+    conf.set_skip(true);
+    conf.push_scrubber(Upper);
+    conf.push_scrubber(Original);
+    let mut buffer = vec![];
+    run_raw(&conf, &b"8.8.8.8 get\r\n\nexample.com get"[..], &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 GET 8.8\r\nLOCALHOST GET exa");
+}
+
+#[test]
+fn shared_mapping_store() {
+    let store = MappingStore::new();
+    let scrubber = {
+        let store = store.clone();
+        move |line: &mut Vec<u8>, _: &LineCtx| {
+            let word = line.split_off(line.len() - 1);
+            line.extend(store.get_or_insert_with(&word, |n| n.to_string().into_bytes()));
+        }
+    };
+    let mut conf = Config::default();
+    conf.set_jobs(4);
+    conf.push_scrubber(scrubber);
+
+    let input: Vec<u8> = (0..1000)
+        .flat_map(|n| format!("::2 {}\n", ["a", "b", "c"][n % 3]).into_bytes())
+        .collect();
+    let outputs: Vec<Vec<u8>> = thread::scope(|s| {
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut buffer = vec![];
+                    run_raw(&conf, &input[..], &mut buffer).unwrap();
+                    buffer
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    assert_eq!(store.len(), 3);
+    let a = store.get(b"a").unwrap();
+    assert!(outputs[0].starts_with(&[b"::1 ", &a[..], b"\n"].concat()));
+    assert!(outputs.iter().all(|o| o == &outputs[0]));
+
+    store.clear();
+    assert!(store.is_empty() && store.get(b"a").is_none());
+}
+
+#[test]
+fn pipeline_stages() {
+    let stats = Stats::new();
+    let mut conf = Config::default();
+    conf.set_stats(&stats);
+    conf.set_jobs(2);
+    let pipeline = Pipeline::new(conf)
+        .filter("comments", |_: &[u8], ctx: &LineCtx| {
+            !ctx.original().starts_with(b"#")
+        })
+        .scrubber("upper", |line: &mut Vec<u8>, _: &LineCtx| {
+            line.make_ascii_uppercase();
+        })
+        .filter("empty", |line: &[u8], ctx: &LineCtx| {
+            !line.ends_with(b" -") && ctx.format() == Format::Combined
+        });
+
+    let mut buffer = vec![];
+    let input = &b"8.8.8.8 a\n#comment\n::2 -\nexample.com b\n"[..];
+    run_raw(&pipeline, input, &mut buffer).unwrap();
+    assert_eq!(buffer, b"127.0.0.1 A\nLOCALHOST B\n");
+
+    let counts: Vec<_> = pipeline
+        .stages()
+        .iter()
+        .map(|s| (s.name(), s.lines(), s.modified(), s.dropped()))
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            ("comments", 4, 0, 1),
+            ("upper", 3, 2, 0),
+            ("empty", 3, 0, 1)
+        ]
+    );
+    assert_eq!(stats.dropped(), 2);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn anonymized_lines() {
+    let mut conf = Config::default();
+    conf.set_skip(true);
+    conf.set_max_line_len(14);
+    conf.set_oversized(Oversized::Skip);
+    conf.push_drop_pattern("^#").unwrap();
+    let input = &b"8.8.8.8 a\r\n#comment\n\n1.2.3.4 too long\nexample.com b"[..];
+    let output: Vec<_> = lines(input, &conf).map(Result::unwrap).collect();
+    assert_eq!(output, [&b"127.0.0.1 a\r\n"[..], b"localhost b"]);
+
+    conf.set_oversized(Oversized::Error);
+    let mut iter = lines(input, &conf);
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn anonymizing_reader() {
+    let mut conf = Config::default();
+    conf.set_skip(true);
+    conf.push_drop_pattern("^#").unwrap();
+    let input = &b"8.8.8.8 a\n#comment\n\nexample.com b"[..];
+
+    let mut reader = AnonymizingReader::new(input, &conf);
+    let mut first = [0; 4];
+    reader.read_exact(&mut first).unwrap();
+    assert_eq!(&first, b"127.");
+    let mut rest = vec![];
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"0.0.1 a\nlocalhost b");
+
+    let reader = AnonymizingReader::new(input, &conf);
+    let lines: Vec<_> = reader.lines().map(Result::unwrap).collect();
+    assert_eq!(lines, ["127.0.0.1 a", "localhost b"]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn anonymizing_writer() {
+    let mut conf = Config::default();
+    conf.set_null_data(true);
+    conf.push_drop_pattern("^#").unwrap();
+
+    let mut output = vec![];
+    {
+        let mut writer = AnonymizingWriter::new(&mut output, &conf);
+        writer.write_all(b"8.8.8.8 a\0#comment\0exa").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"mple.com b\0::2 d").unwrap();
+    }
+    assert_eq!(output, b"127.0.0.1 a\0localhost b\0::1 d");
+
+    let mut writer = AnonymizingWriter::new(vec![], &conf);
+    writer.write_all(b"1.2.3.4 c").unwrap();
+    assert_eq!(writer.finish().unwrap(), b"127.0.0.1 c");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn single_line() {
+    let mut conf = Config::default();
+    conf.set_skip(true);
+    conf.push_drop_pattern("^#").unwrap();
+    assert_eq!(
+        anonymize_line(&conf, b"example.com a\r\n")
+            .unwrap()
+            .as_deref(),
+        Some(&b"localhost a\r\n"[..])
+    );
+    assert_eq!(
+        anonymize_line(&conf, b"::2 b").unwrap().as_deref(),
+        Some(&b"::1 b"[..])
+    );
+    assert_eq!(anonymize_line(&conf, b"#comment\n").unwrap(), None);
+    assert_eq!(anonymize_line(&conf, b" \n").unwrap(), None);
+    assert_eq!(anonymize_line(&conf, b"").unwrap(), None);
+
+    conf.set_strict(true);
+    assert!(anonymize_line(&conf, b"[x] a\n").is_err());
+
+    let token = CancellationToken::new();
+    let conf = Config::builder().cancel(token.clone()).build().unwrap();
+    token.cancel();
+    assert!(anonymize_line(&conf, b"8.8.8.8 a\n").is_err());
+}
@@ -0,0 +1,100 @@
+/// Value of a TOML key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Value<'a> {
+    String(&'a str),
+    Integer(u64),
+    Boolean(bool),
+}
+
+/// Key and value with the line number they were read from
+pub(crate) type Entry<'a> = (usize, String, Value<'a>);
+
+/// Parses the subset of TOML used by config files: comments, `[table]` headers and keys with
+/// string, unsigned integer or boolean values
+///
+/// Keys inside a table are prefixed with the table name, e.g. `table.key`. Strings are borrowed
+/// from `s`, so basic strings must not contain escape sequences. Entries and errors carry their line
+/// number.
+pub(crate) fn parse(s: &str) -> Result<Vec<Entry<'_>>, (usize, String)> {
+    let mut entries = vec![];
+    let mut table = "";
+
+    for (n, line) in s.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| (n + 1, message.to_string());
+
+        if let Some(name) = line.strip_prefix('[') {
+            table = name
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|t| is_key(t))
+                .ok_or_else(|| error("invalid table header"))?;
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected `key = value`"))?;
+        let key = key.trim();
+        if !is_key(key) {
+            return Err(error(&format!("invalid key '{key}'")));
+        }
+        let value = parse_value(value.trim()).ok_or_else(|| error("invalid value"))?;
+
+        let key = if table.is_empty() {
+            key.to_string()
+        } else {
+            format!("{table}.{key}")
+        };
+        if entries.iter().any(|(_, k, _)| *k == key) {
+            return Err(error(&format!("duplicate key '{key}'")));
+        }
+        entries.push((n + 1, key, value));
+    }
+
+    Ok(entries)
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Removes a `#` comment outside of strings
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Option<Value<'_>> {
+    match value {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        _ => (),
+    }
+
+    if let Some(s) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return (!s.contains('\'')).then_some(Value::String(s));
+    }
+    if let Some(s) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return (!s.contains(['"', '\\'])).then_some(Value::String(s));
+    }
+
+    if value.starts_with('_') || value.ends_with('_') {
+        return None;
+    }
+    value.replace('_', "").parse().ok().map(Value::Integer)
+}
@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::slice;
+
+use crate::writer::write_line;
+use crate::{Config, Error, ErrorKind};
+
+/// Config and incomplete last line of the current stream, see [`process_chunk`]
+#[derive(Default)]
+struct Session {
+    config: Config<'static>,
+    line: Vec<u8>,
+    scratch: Vec<u8>,
+    output: Vec<u8>,
+    error: String,
+}
+
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session::default());
+}
+
+impl Session {
+    /// Records `error` and returns its status, `2` for invalid configs and `1` otherwise
+    fn fail(&mut self, error: &Error) -> i32 {
+        self.error = error.to_string();
+        match error.kind() {
+            ErrorKind::Usage => 2,
+            _ => 1,
+        }
+    }
+
+    /// Anonymizes all complete lines of `chunk`, failing lines are left out and the first error
+    /// is returned once the whole chunk is consumed
+    fn process(&mut self, chunk: &[u8]) -> i32 {
+        self.output.clear();
+        let delimiter = if self.config.get_null_data() {
+            b'\0'
+        } else {
+            b'\n'
+        };
+        let mut first_error = None;
+        for part in chunk.split_inclusive(|&b| b == delimiter) {
+            self.line.extend_from_slice(part);
+            if part.last() == Some(&delimiter) {
+                if let Err(e) = self.write_line() {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => self.fail(&e.into()),
+            None => 0,
+        }
+    }
+
+    fn write_line(&mut self) -> std::io::Result<()> {
+        let result = write_line(
+            &self.config,
+            &self.line,
+            &mut self.scratch,
+            &mut self.output,
+        );
+        self.line.clear();
+        result
+    }
+}
+
+/// Returns `bytes` as a slice, empty for a null pointer
+///
+/// # Safety
+///
+/// `bytes` must be null or point to `len` readable bytes.
+unsafe fn bytes<'b>(bytes: *const u8, len: usize) -> &'b [u8] {
+    if bytes.is_null() || len == 0 {
+        return &[];
+    }
+    // SAFETY: guaranteed by the caller
+    unsafe { slice::from_raw_parts(bytes, len) }
+}
+
+/// Allocates `len` bytes, e.g. for the input passed to [`process_chunk`]
+#[no_mangle]
+pub extern "C" fn alog_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0_u8; len].into_boxed_slice()).cast::<u8>()
+}
+
+/// Frees `len` bytes returned by [`alog_alloc`]
+///
+/// # Safety
+///
+/// `ptr` and `len` must be returned by [`alog_alloc`] and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn alog_dealloc(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)) });
+    }
+}
+
+/// Replaces the config by a TOML document, see [`Config::from_toml`], and starts a new stream
+///
+/// Returns `0` on success, otherwise the config is kept, see [`alog_error_ptr`].
+///
+/// # Safety
+///
+/// `toml` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn alog_configure(toml: *const u8, len: usize) -> i32 {
+    // SAFETY: guaranteed by the caller
+    let toml = unsafe { bytes(toml, len) };
+    SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        let config = std::str::from_utf8(toml)
+            .map_err(|e| Error::Usage(format!("Invalid config: {e}")))
+            .and_then(Config::from_toml)
+            .and_then(|config| {
+                config.validate().map_err(Error::Invalid)?;
+                Ok(config)
+            });
+        match config {
+            Ok(config) => {
+                session.config = config;
+                session.line.clear();
+                0
+            }
+            Err(e) => session.fail(&e),
+        }
+    })
+}
+
+/// Anonymizes all complete lines of `len` bytes at `input`, see [`alog_output_ptr`]
+///
+/// A trailing incomplete line is kept until the next chunk completes it or [`alog_finish`] is
+/// called, so a stream can be passed in chunks of any size. Returns `0` on success.
+///
+/// The whole chunk is consumed even if a line fails (e.g. with [`Config::strict`]): failing
+/// lines are left out of the output, all other lines are written and the status of the first
+/// error is returned. The stream can be continued with the next chunk.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn process_chunk(input: *const u8, len: usize) -> i32 {
+    // SAFETY: guaranteed by the caller
+    let input = unsafe { bytes(input, len) };
+    SESSION.with(|session| session.borrow_mut().process(input))
+}
+
+/// Anonymizes the incomplete last line of the stream, if any, see [`alog_output_ptr`]
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn alog_finish() -> i32 {
+    SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        session.output.clear();
+        match session.write_line() {
+            Ok(()) => 0,
+            Err(e) => session.fail(&e.into()),
+        }
+    })
+}
+
+/// Returns the output of the last [`process_chunk`] or [`alog_finish`] call
+///
+/// Valid until the next call of either function, the length is returned by
+/// [`alog_output_len`].
+#[no_mangle]
+pub extern "C" fn alog_output_ptr() -> *const u8 {
+    SESSION.with(|session| session.borrow().output.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn alog_output_len() -> usize {
+    SESSION.with(|session| session.borrow().output.len())
+}
+
+/// Returns the UTF-8 message of the last error, the length is returned by [`alog_error_len`]
+#[no_mangle]
+pub extern "C" fn alog_error_ptr() -> *const u8 {
+    SESSION.with(|session| session.borrow().error.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn alog_error_len() -> usize {
+    SESSION.with(|session| session.borrow().error.len())
+}
@@ -0,0 +1,226 @@
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::fs::File;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::io::Read;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd};
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{
+    replace_file, temp_path, Compression, Config, Error, IOConfig, Input, Level, OutputMode,
+};
+
+/// Time between two directory scans
+const INTERVAL: Duration = Duration::from_secs(1);
+/// Files modified within this time are considered to be still written to
+const SETTLE: Duration = Duration::from_secs(5);
+
+/// Watches all input directories, anonymizing new or changed files to the output directory
+///
+/// On Linux the directories are scanned whenever inotify reports a change, and every
+/// [`INTERVAL`] until the changed files settled. Elsewhere (or if inotify fails) they are
+/// scanned every [`INTERVAL`].
+///
+/// Returns once [`Config::cancel`] is interrupted or cancelled and the current scan is done,
+/// otherwise only if an error occurs.
+pub(crate) fn watch(config: &Config, ioconfig: &IOConfig) -> Result<(), Error> {
+    let (Some(dirs), Some(out)) = (
+        ioconfig.get_input().filter(|i| !i.contains(&Input::Stdin)),
+        ioconfig.get_output_dir(),
+    ) else {
+        return Err(Error::Usage(
+            "Watch mode requires input and output directories".to_string(),
+        ));
+    };
+
+    let notify = Notify::new(config, dirs.iter().filter_map(|d| d.path()));
+    // files found on the first scan may still settle
+    let mut changed = Instant::now();
+    while !config.is_interrupted() && !config.is_cancelled() {
+        if changed.elapsed() <= SETTLE + INTERVAL {
+            for dir in dirs.iter().filter_map(|d| d.path()) {
+                scan(config, dir, out, SETTLE, ioconfig.get_preserve())?;
+            }
+        }
+        if notify.wait(INTERVAL)? {
+            changed = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+/// Change notifications of the watched directories
+pub(crate) enum Notify {
+    /// inotify instance watching all directories
+    #[cfg(target_os = "linux")]
+    Inotify(File),
+    /// No notifications, every wait reports a change
+    Poll,
+}
+
+impl Notify {
+    /// Watches `dirs` for new and changed files, falls back to [`Notify::Poll`] with a warning
+    /// if that fails
+    pub(crate) fn new<'p>(config: &Config, dirs: impl Iterator<Item = &'p Path>) -> Self {
+        #[cfg(target_os = "linux")]
+        match inotify(dirs) {
+            Ok(file) => return Notify::Inotify(file),
+            Err(e) => config.log(
+                Level::Warn,
+                format_args!("can not watch input directories, scanning every second: {e}"),
+            ),
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = dirs;
+            config.log(
+                Level::Debug,
+                format_args!("scanning input directories every second"),
+            );
+        }
+        Notify::Poll
+    }
+
+    /// Waits up to `timeout` for changes, returns `true` if any file changed (or might have)
+    pub(crate) fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Notify::Inotify(file) => {
+                let mut fd = libc::pollfd {
+                    fd: file.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let timeout =
+                    libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+                // SAFETY: `fd` is a single valid pollfd
+                match unsafe { libc::poll(&mut fd, 1, timeout) } {
+                    -1 => {
+                        let e = io::Error::last_os_error();
+                        match e.kind() {
+                            io::ErrorKind::Interrupted => Ok(false),
+                            _ => Err(e),
+                        }
+                    }
+                    0 => Ok(false),
+                    _ => {
+                        // only the fact that something changed is used, not the events
+                        let mut buf = [0; 4096];
+                        loop {
+                            match (&*file).read(&mut buf) {
+                                Ok(0) => return Ok(true),
+                                Ok(_) => (),
+                                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                                Err(e) => return Err(e),
+                            }
+                        }
+                    }
+                }
+            }
+            Notify::Poll => {
+                thread::sleep(timeout);
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Returns a non-blocking inotify instance watching `dirs` for new, changed and moved files
+#[cfg(target_os = "linux")]
+fn inotify<'p>(dirs: impl Iterator<Item = &'p Path>) -> io::Result<File> {
+    // SAFETY: no pointers are passed
+    let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a new file descriptor not owned by anything else
+    let file = unsafe { File::from_raw_fd(fd) };
+
+    let mask = libc::IN_CREATE
+        | libc::IN_MODIFY
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_ATTRIB;
+    for dir in dirs {
+        let path = std::ffi::CString::new(dir.as_os_str().as_bytes())?;
+        // SAFETY: `path` is a valid C string
+        if unsafe { libc::inotify_add_watch(file.as_raw_fd(), path.as_ptr(), mask) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(file)
+}
+
+/// Anonymizes every file in `dir` not modified within `settle` to a file with the same name in
+/// `out`, unless that file is newer than its input
+///
+/// Output files are replaced atomically, with `preserve` set to `true` they keep the metadata of
+/// their input. Returns the number of anonymized files.
+pub(crate) fn scan(
+    config: &Config,
+    dir: &Path,
+    out: &Path,
+    settle: Duration,
+    preserve: bool,
+) -> Result<usize, Error> {
+    let entries = fs::read_dir(dir).map_err(|source| Error::ReadDir {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let now = SystemTime::now();
+    let mut count = 0;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let Ok(modified) = meta.modified() else {
+            continue;
+        };
+        if !meta.is_file()
+            || name.to_string_lossy().starts_with('.')
+            || now.duration_since(modified).unwrap_or_default() < settle
+        {
+            continue;
+        }
+
+        let input = entry.path();
+        let output = out.join(&name);
+        if fs::metadata(&output)
+            .and_then(|m| m.modified())
+            .is_ok_and(|m| m >= modified)
+        {
+            continue;
+        }
+
+        let tmp = temp_path(&output);
+        let result = replace_file(
+            config,
+            &input,
+            &tmp,
+            &OutputMode::NoClobber.open_options(),
+            Compression::from_path(&input),
+            preserve,
+        )
+        .and_then(|()| fs::rename(&tmp, &output));
+        if let Err(source) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(Error::Write {
+                input,
+                output,
+                source,
+            });
+        }
+        count += 1;
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,113 @@
+use std::io::{self, Write};
+
+use crate::{oversized, replace_record, Config};
+
+/// Writer anonymizing every complete line written to it before passing it on to `writer`
+///
+/// Incomplete lines are buffered until their terminator is written, [`Write::flush`] does not
+/// write them either. The last line is written by [`AnonymizingWriter::finish`] or on drop,
+/// ignoring any error.
+///
+/// ## Example
+///
+/// ```
+/// use std::io::Write;
+///
+/// let config = alog::Config::default();
+/// let mut writer = alog::AnonymizingWriter::new(vec![], &config);
+/// write!(writer, "8.8.8.8 GET /\n::2 ").unwrap();
+/// write!(writer, "GET /").unwrap();
+/// let output = writer.finish().unwrap();
+/// assert_eq!(output, b"127.0.0.1 GET /\n::1 GET /");
+/// ```
+#[derive(Debug)]
+pub struct AnonymizingWriter<'c, W: Write> {
+    writer: Option<W>,
+    config: &'c Config<'c>,
+    line: Vec<u8>,
+    scratch: Vec<u8>,
+}
+
+impl<'c, W: Write> AnonymizingWriter<'c, W> {
+    pub fn new(writer: W, config: &'c impl AsRef<Config<'c>>) -> Self {
+        AnonymizingWriter {
+            writer: Some(writer),
+            config: config.as_ref(),
+            line: vec![],
+            scratch: vec![],
+        }
+    }
+
+    /// Writes the buffered incomplete line, if any, flushes and returns the wrapped writer
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the last line can not be anonymized or written.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_line()?;
+        let mut writer = self.writer.take().expect("writer is only taken once");
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    /// Anonymizes and writes the buffered line
+    fn write_line(&mut self) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        let result = write_line(self.config, &self.line, &mut self.scratch, writer);
+        self.line.clear();
+        result
+    }
+}
+
+/// Anonymizes the complete `line` to `writer`, nothing is written for an empty line
+pub(crate) fn write_line<W: Write>(
+    config: &Config,
+    line: &[u8],
+    scratch: &mut Vec<u8>,
+    writer: &mut W,
+) -> io::Result<()> {
+    if line.is_empty() {
+        return Ok(());
+    }
+    if config
+        .get_max_line_len()
+        .is_some_and(|max| line.len() > max)
+    {
+        oversized(config, line, writer)
+    } else {
+        replace_record(config, line, scratch, writer)
+    }
+}
+
+impl<W: Write> Write for AnonymizingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let delimiter = if self.config.get_null_data() {
+            b'\0'
+        } else {
+            b'\n'
+        };
+        for chunk in buf.split_inclusive(|&b| b == delimiter) {
+            self.line.extend_from_slice(chunk);
+            if chunk.last() == Some(&delimiter) {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for AnonymizingWriter<'_, W> {
+    fn drop(&mut self) {
+        let _ = self.write_line();
+        let _ = self.flush();
+    }
+}
@@ -5,7 +5,7 @@ fn main() {
     let mut buffer = vec![];
     alog::run_raw(
         &alog::Config {
-            ipv4: "XXX",
+            ipv4: "XXX".into(),
             ..Default::default()
         },
         Cursor::new(b"8.8.8.8 test line"),